@@ -0,0 +1,101 @@
+#!/usr/bin/env python3
+# Writes reference.nnue and reference.txt next to this script.
+#
+# The network is a fixed 768 -> 32x2 -> 1 net: the first hidden unit counts material, the
+# rest are small pseudo-random weights from a xorshift generator, so every byte is
+# reproducible. The scores come from a forward pass written here independently of the
+# engine; check them with `chess --nnue-check nets/reference.nnue nets/reference.txt`.
+import os
+import struct
+
+HIDDEN = 32
+QA, QB, SCALE = 255, 64, 400
+PIECES = "pnbrqk"
+MATERIAL = [1, 3, 3, 5, 9, 0]
+MASK = 0xFFFFFFFFFFFFFFFF
+FENS = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+    "rnbqkb1r/ppp1pppp/5n2/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+    "8/2P5/8/8/8/8/5p2/K6k w - - 0 1",
+    "8/2P5/8/8/8/8/5p2/K6k b - - 0 1",
+    "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8",
+    "8/8/4k3/3pP3/8/8/8/4K3 w - d6 0 1",
+]
+
+state = 0x9E3779B97F4A7C15
+
+
+def random_between(low, high):
+    global state
+    state ^= (state << 13) & MASK
+    state ^= state >> 7
+    state ^= (state << 17) & MASK
+    return low + state % (high - low + 1)
+
+
+# feature weights [768][HIDDEN]: own pieces first, then the opponent's, each piece by square
+feature_weights = [[0] * HIDDEN for _ in range(768)]
+for feature in range(768):
+    theirs, rest = divmod(feature, 384)
+    piece = rest // 64
+    feature_weights[feature][0] = 3 * MATERIAL[piece] * (-1 if theirs else 1)
+    for unit in range(1, HIDDEN):
+        feature_weights[feature][unit] = random_between(-8, 8)
+feature_biases = [127] + [64] * (HIDDEN - 1)
+output_weights = (
+    [680]
+    + [random_between(-4, 4) for _ in range(HIDDEN - 1)]
+    + [-680]
+    + [random_between(-4, 4) for _ in range(HIDDEN - 1)]
+)
+output_bias = random_between(-500, 500)
+
+
+def network_bytes():
+    words = [weight for row in feature_weights for weight in row]
+    words += feature_biases + output_weights + [output_bias]
+    data = b"".join(struct.pack("<h", word) for word in words)
+    return data + b"\0" * (-len(data) % 64)
+
+
+def evaluate(fen):
+    board, turn = fen.split()[:2]
+    accumulators = {"w": feature_biases[:], "b": feature_biases[:]}
+    for row_number, row in enumerate(board.split("/")):
+        rank = 7 - row_number
+        file = 0
+        for char in row:
+            if char.isdigit():
+                file += int(char)
+                continue
+            team = "w" if char.isupper() else "b"
+            square = rank * 8 + file
+            for perspective, values in accumulators.items():
+                # black sees the board with the ranks flipped
+                seen = square if perspective == "w" else square ^ 56
+                feature = (team != perspective) * 384 + PIECES.index(char.lower()) * 64 + seen
+                for unit in range(HIDDEN):
+                    values[unit] += feature_weights[feature][unit]
+            file += 1
+    ours, theirs = accumulators[turn], accumulators["b" if turn == "w" else "w"]
+    output = sum(min(max(value, 0), QA) * weight for value, weight in zip(ours, output_weights))
+    output += sum(
+        min(max(value, 0), QA) * weight for value, weight in zip(theirs, output_weights[HIDDEN:])
+    )
+    scaled = (output + output_bias) * SCALE
+    # integer division truncating toward zero, as in Rust
+    score = abs(scaled) // (QA * QB)
+    return score if scaled >= 0 else -score
+
+
+directory = os.path.dirname(os.path.abspath(__file__))
+with open(os.path.join(directory, "reference.nnue"), "wb") as file:
+    file.write(network_bytes())
+with open(os.path.join(directory, "reference.txt"), "w") as file:
+    file.write("# scores for nets/reference.nnue from the side to move, check with --nnue-check\n")
+    for fen in FENS:
+        file.write(f"{fen} ; {evaluate(fen)}\n")
@@ -0,0 +1,193 @@
+use crate::eval::{MATE_SCORE, MATE_THRESHOLD};
+use crate::ordering::OrderingConfig;
+use crate::retrograde::EndgameTables;
+use crate::search::{SearchLimits, SearchResult, Searcher};
+use crate::Game;
+use colored::Colorize;
+use crossterm::event::{self, Event};
+use crossterm::terminal;
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_ANALYSIS_TIME: Duration = Duration::from_secs(10);
+
+// scores are shown from white's point of view, like most chess GUIs
+pub fn describe_score(score: i32, game: &Game) -> String {
+    let score = if game.is_white_turn() { score } else { -score };
+    if score > MATE_THRESHOLD {
+        format!("White mates in {}", (MATE_SCORE - score + 1) / 2)
+    } else if score < -MATE_THRESHOLD {
+        format!("Black mates in {}", (MATE_SCORE + score + 1) / 2)
+    } else {
+        format!("{score:+} cp")
+    }
+}
+
+fn analysis_line(result: &SearchResult, game: &Game) -> String {
+    format!(
+        "depth {:>2}  {:<18} nodes {:>9}  nps {:>8}  pv {}",
+        result.depth,
+        describe_score(result.score, game),
+        result.stats.nodes,
+        result.nodes_per_second(),
+        game.san_line(&result.pv).join(" ")
+    )
+}
+
+// with several lines the depth and node counts come first, then one ranked line per move
+fn analysis_lines(result: &SearchResult, game: &Game, line_end: &str) -> String {
+    if result.lines.len() <= 1 {
+        return format!("{}{line_end}", analysis_line(result, game));
+    }
+    let mut text = format!(
+        "depth {:>2}  nodes {:>9}  nps {:>8}{line_end}",
+        result.depth,
+        result.stats.nodes,
+        result.nodes_per_second()
+    );
+    for (index, line) in result.lines.iter().enumerate() {
+        text.push_str(&format!(
+            "  {}. {:<18} {}{line_end}",
+            index + 1,
+            describe_score(line.score, game),
+            game.san_line(&line.pv).join(" ")
+        ));
+    }
+    text
+}
+
+// `analyze`, `analyze <seconds>` or `analyze <seconds> <lines>` at the move prompt
+pub fn parse_analyze_command(input: &str) -> Option<Result<(Duration, usize), String>> {
+    let mut words = input.split_whitespace();
+    if words.next() != Some("analyze") {
+        return None;
+    }
+    let time_limit = match words.next() {
+        None => Ok(DEFAULT_ANALYSIS_TIME),
+        Some(seconds) => seconds
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .map_err(|_| "Error: analyze takes a number of seconds".to_string()),
+    };
+    let lines = match words.next() {
+        None => Ok(1),
+        Some(lines) => lines
+            .parse::<usize>()
+            .ok()
+            .filter(|lines| *lines >= 1)
+            .ok_or_else(|| "Error: the number of lines must be at least 1".to_string()),
+    };
+    Some(time_limit.and_then(|time_limit| Ok((time_limit, lines?))))
+}
+
+// searches the position until a key is pressed or `time_limit` runs out, printing every
+// completed depth with the best `lines` moves; without a terminal (e.g. piped input) only the
+// time limit applies
+pub fn analyze(
+    game: &Game,
+    time_limit: Duration,
+    lines: usize,
+    tablebases: Option<&Arc<EndgameTables>>,
+) {
+    let mut searcher = Searcher::new(OrderingConfig::all());
+    searcher.set_tablebases(tablebases.cloned());
+    searcher.set_multi_pv(lines);
+    let stop = searcher.stop_flag();
+    let (sender, results) = mpsc::channel();
+    let position = *game;
+    let search = thread::spawn(move || {
+        let limits = SearchLimits {
+            movetime: Some(time_limit),
+            ..SearchLimits::default()
+        };
+        searcher.search_with(&position, limits, |result| {
+            let _ = sender.send(result.clone());
+        })
+    });
+
+    let raw_mode = terminal::enable_raw_mode().is_ok();
+    let line_end = if raw_mode { "\r\n" } else { "\n" };
+    print!(
+        "Analyzing for up to {}s{}{}",
+        time_limit.as_secs(),
+        if raw_mode {
+            ", press any key to stop"
+        } else {
+            ""
+        },
+        line_end
+    );
+    let deadline = Instant::now() + time_limit;
+    loop {
+        match results.recv_timeout(Duration::from_millis(50)) {
+            Ok(result) => print!("{}", analysis_lines(&result, game, line_end)),
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+        let key_pressed = raw_mode
+            && event::poll(Duration::ZERO).unwrap_or(false)
+            && matches!(event::read(), Ok(Event::Key(_)));
+        if key_pressed || Instant::now() >= deadline {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+    if raw_mode {
+        let _ = terminal::disable_raw_mode();
+    }
+
+    let result = search.join().unwrap();
+    match result.best_move {
+        Some(mv) => println!(
+            "{}",
+            format!(
+                "Best move {} ({}) at depth {}",
+                game.san(mv),
+                describe_score(result.score, game),
+                result.depth
+            )
+            .green()
+        ),
+        None => println!("{}", "No analysis available for this position".red()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_are_shown_from_whites_side() {
+        let white = Game::init();
+        let mut black = white;
+        black.switch_turn();
+        assert_eq!(describe_score(35, &white), "+35 cp");
+        assert_eq!(describe_score(35, &black), "-35 cp");
+        assert_eq!(describe_score(MATE_SCORE - 3, &white), "White mates in 2");
+        assert_eq!(describe_score(MATE_SCORE - 1, &black), "Black mates in 1");
+        assert_eq!(
+            describe_score(-(MATE_SCORE - 2), &white),
+            "Black mates in 1"
+        );
+    }
+
+    #[test]
+    fn analyze_command_takes_seconds_and_lines() {
+        assert_eq!(parse_analyze_command("e2e4"), None);
+        assert_eq!(
+            parse_analyze_command("analyze"),
+            Some(Ok((DEFAULT_ANALYSIS_TIME, 1)))
+        );
+        assert_eq!(
+            parse_analyze_command("analyze 3"),
+            Some(Ok((Duration::from_secs(3), 1)))
+        );
+        assert_eq!(
+            parse_analyze_command("analyze 3 4"),
+            Some(Ok((Duration::from_secs(3), 4)))
+        );
+        assert!(parse_analyze_command("analyze soon").unwrap().is_err());
+        assert!(parse_analyze_command("analyze 3 0").unwrap().is_err());
+    }
+}
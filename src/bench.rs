@@ -0,0 +1,164 @@
+use crate::ordering::OrderingConfig;
+use crate::search::{SearchStats, Searcher};
+use crate::Game;
+use std::time::Instant;
+
+// openings and middlegames reached from the start position, in UCI coordinates
+pub const BENCH_LINES: [&str; 4] = [
+    "",
+    "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6",
+    "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c1g5 f8e7",
+    "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6",
+];
+
+// a quiet Queen's Gambit middlegame with many sensible quiet moves, where killers, history and
+// counter moves each change the node count
+pub const MIDDLEGAME_FEN: &str =
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ - 0 1";
+
+pub fn game_from_line(line: &str) -> Result<Game, String> {
+    let mut game = Game::init();
+    for coords in line.split_whitespace() {
+        let mv = game.find_move(coords)?;
+        game.make_move(mv)?;
+    }
+    Ok(game)
+}
+
+// each row adds one heuristic on top of the previous one, so the node counts show what it buys
+fn bench_configs() -> Vec<(&'static str, OrderingConfig)> {
+    let mut config = OrderingConfig::none();
+    let mut configs = vec![("no ordering", config)];
+    config.hash_move = true;
+    configs.push(("+ hash move", config));
+    config.mvv_lva = true;
+    configs.push(("+ mvv-lva", config));
+    config.killers = true;
+    configs.push(("+ killers", config));
+    config.history = true;
+    configs.push(("+ history", config));
+    config.counter_moves = true;
+    configs.push(("+ counter moves", config));
+    configs
+}
+
+fn bench_stats(games: &[Game], config: OrderingConfig, depth: u32) -> SearchStats {
+    let mut total = SearchStats::default();
+    for game in games {
+        let mut searcher = Searcher::new(config);
+        total += searcher.search(game, depth).stats;
+    }
+    total
+}
+
+fn print_table(games: &[Game], configs: &[(&str, OrderingConfig)], depth: u32) {
+    println!(
+        "{:<16} {:>10} {:>10} {:>9} {:>8}",
+        "ordering", "nodes", "qnodes", "1st-cut", "ms"
+    );
+    for (name, config) in configs {
+        let start = Instant::now();
+        let total = bench_stats(games, *config, depth);
+        println!(
+            "{:<16} {:>10} {:>10} {:>8.1}% {:>8}",
+            name,
+            total.nodes,
+            total.quiescence_nodes,
+            100.0 * total.first_move_cutoff_rate(),
+            start.elapsed().as_millis()
+        );
+    }
+}
+
+// the ordering tables always run single threaded so their node counts are reproducible
+pub fn run_bench(depth: u32, threads: usize) -> Result<(), String> {
+    let games = BENCH_LINES
+        .iter()
+        .map(|line| game_from_line(line))
+        .collect::<Result<Vec<Game>, String>>()?;
+
+    println!(
+        "Move ordering bench at depth {depth}, {} positions",
+        games.len()
+    );
+    print_table(&games, &bench_configs(), depth);
+
+    // without capture ordering the middlegame takes minutes, so its table starts at mvv-lva
+    println!("\nQuiet move heuristics on a middlegame at depth {depth}");
+    let middlegame = Game::from_fen(MIDDLEGAME_FEN)?;
+    let configs: Vec<_> = bench_configs()
+        .into_iter()
+        .filter(|(_, config)| config.mvv_lva)
+        .collect();
+    print_table(&[middlegame], &configs, depth);
+
+    println!("\nPrincipal variations with full ordering, {threads} thread(s)");
+    for (index, game) in games.iter().enumerate() {
+        let mut searcher = Searcher::new(OrderingConfig::all());
+        searcher.set_threads(threads);
+        let result = searcher.search(game, depth);
+        let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+        println!(
+            "{}: depth {} score {} best {} pv {}",
+            index + 1,
+            result.depth,
+            result.score,
+            result
+                .best_move
+                .map_or("none".to_string(), |mv| mv.to_string()),
+            pv.join(" ")
+        );
+        if result.threads.len() > 1 {
+            for (id, thread) in result.threads.iter().enumerate() {
+                println!(
+                    "   thread {id}: depth {} nodes {} time {}ms",
+                    thread.depth,
+                    thread.nodes,
+                    thread.time.as_millis()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_counts(games: &[Game], configs: &[(&str, OrderingConfig)], depth: u32) -> Vec<u64> {
+        configs
+            .iter()
+            .map(|(_, config)| bench_stats(games, *config, depth).nodes)
+            .collect()
+    }
+
+    #[test]
+    fn single_threaded_bench_is_deterministic() {
+        let games: Vec<Game> = BENCH_LINES
+            .iter()
+            .map(|line| game_from_line(line).unwrap())
+            .collect();
+        // the unordered rows are left out, they take minutes in a debug build
+        let configs: Vec<_> = bench_configs()
+            .into_iter()
+            .filter(|(_, config)| config.mvv_lva)
+            .collect();
+        let first = node_counts(&games, &configs, 3);
+        assert_eq!(node_counts(&games, &configs, 3), first);
+        assert!(first.iter().all(|nodes| *nodes > 0));
+    }
+
+    #[test]
+    fn quiet_move_heuristics_change_the_middlegame_node_count() {
+        let game = Game::from_fen(MIDDLEGAME_FEN).unwrap();
+        let configs: Vec<_> = bench_configs()
+            .into_iter()
+            .filter(|(_, config)| config.killers)
+            .collect();
+        let counts = node_counts(&[game], &configs, 4);
+        assert_ne!(counts[0], counts[1], "killers vs history");
+        assert_ne!(counts[1], counts[2], "history vs counter moves");
+        assert_ne!(counts[0], counts[2], "killers vs counter moves");
+    }
+}
@@ -0,0 +1,47 @@
+// A stand-in UCI engine for checking the external engine plumbing offline.
+// It plays the moves given on its command line in order, e.g.
+// `mock_engine e7e5 b8c6 g8f6`, and answers `bestmove 0000` once they run out.
+// `--delay <ms>` makes it sit on every `go` for that long before answering.
+use std::io::{self, BufRead, Write};
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut delay = Duration::ZERO;
+    let mut script = vec![];
+    while let Some(arg) = args.next() {
+        if arg == "--delay" {
+            let millis = args.next().and_then(|millis| millis.parse().ok());
+            delay = Duration::from_millis(millis.unwrap_or(0));
+        } else {
+            script.push(arg);
+        }
+    }
+    let mut script = script.into_iter();
+
+    let stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        let mut out = stdout.lock();
+        match line.split_whitespace().next() {
+            Some("uci") => {
+                let _ = writeln!(out, "id name Mock Engine");
+                let _ = writeln!(out, "id author chess-cli-game");
+                let _ = writeln!(out, "uciok");
+            }
+            Some("isready") => {
+                let _ = writeln!(out, "readyok");
+            }
+            Some("go") => {
+                thread::sleep(delay);
+                let mv = script.next().unwrap_or_else(|| "0000".to_string());
+                let _ = writeln!(out, "info depth 1 score cp 0 pv {mv}");
+                let _ = writeln!(out, "bestmove {mv}");
+            }
+            Some("quit") => break,
+            _ => {}
+        }
+        let _ = out.flush();
+    }
+}
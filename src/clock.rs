@@ -0,0 +1,223 @@
+use crate::Team::{self, *};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClockMode {
+    // the increment is added after every move
+    Fischer,
+    // time used is given back after the move, up to the increment
+    Bronstein,
+    // the clock waits for the increment before it starts running each move
+    Delay,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+    pub mode: ClockMode,
+}
+
+impl TimeControl {
+    // `<minutes>[+<seconds>]`, e.g. `5`, `3+2` or `0.5+1`
+    pub fn parse(text: &str, mode: ClockMode) -> Result<TimeControl, String> {
+        let invalid = || format!("Error: Invalid time control '{text}', expected minutes+seconds");
+        let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+        let base: f64 = base.parse().map_err(|_| invalid())?;
+        let increment: f64 = increment.parse().map_err(|_| invalid())?;
+        if base <= 0.0 || increment < 0.0 {
+            return Err(invalid());
+        }
+        Ok(TimeControl {
+            base: Duration::from_secs_f64(base * 60.0),
+            increment: Duration::from_secs_f64(increment),
+            mode,
+        })
+    }
+}
+
+pub fn parse_mode(text: &str) -> Result<ClockMode, String> {
+    match text {
+        "fischer" | "increment" => Ok(ClockMode::Fischer),
+        "bronstein" => Ok(ClockMode::Bronstein),
+        "delay" => Ok(ClockMode::Delay),
+        _ => Err(format!("Error: Unknown clock mode '{text}'")),
+    }
+}
+
+// `m:ss` with tenths under a minute, when they start to matter
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 60 {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else if seconds < 3600 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+// both players' clocks; at most one runs at a time
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    running: Option<(Team, Instant)>,
+}
+
+fn side(team: Team) -> usize {
+    match team {
+        White => 0,
+        Black => 1,
+    }
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        Clock {
+            control,
+            remaining: [control.base; 2],
+            running: None,
+        }
+    }
+    pub fn increment(&self) -> Duration {
+        self.control.increment
+    }
+    pub fn start(&mut self, team: Team) {
+        self.running = Some((team, Instant::now()));
+    }
+    // what the running clock has taken off so far this move
+    fn charged(&self, team: Team) -> Duration {
+        match self.running {
+            Some((running, started)) if running == team => {
+                let elapsed = started.elapsed();
+                match self.control.mode {
+                    ClockMode::Delay => elapsed.saturating_sub(self.control.increment),
+                    ClockMode::Fischer | ClockMode::Bronstein => elapsed,
+                }
+            }
+            _ => Duration::ZERO,
+        }
+    }
+    // live, counting the move in progress
+    pub fn remaining(&self, team: Team) -> Duration {
+        self.remaining[side(team)].saturating_sub(self.charged(team))
+    }
+    // the running side once its time is gone, checked while it is still thinking
+    pub fn flag_fallen(&self) -> Option<Team> {
+        let (team, _) = self.running?;
+        self.remaining(team).is_zero().then_some(team)
+    }
+    // ends the running player's move and returns their time left, or `None` when it ran out
+    pub fn stop(&mut self) -> Option<Duration> {
+        let (team, _) = self.running?;
+        let charged = self.charged(team);
+        self.running = None;
+        let remaining = &mut self.remaining[side(team)];
+        if charged >= *remaining {
+            *remaining = Duration::ZERO;
+            return None;
+        }
+        *remaining -= charged;
+        *remaining += match self.control.mode {
+            ClockMode::Fischer => self.control.increment,
+            ClockMode::Bronstein => charged.min(self.control.increment),
+            ClockMode::Delay => Duration::ZERO,
+        };
+        Some(*remaining)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn new_clock(text: &str, mode: ClockMode) -> Clock {
+        Clock::new(TimeControl::parse(text, mode).unwrap())
+    }
+
+    // plays one move for `team` that takes `think`
+    fn play(clock: &mut Clock, team: Team, think: Duration) -> Option<Duration> {
+        clock.start(team);
+        thread::sleep(think);
+        clock.stop()
+    }
+
+    #[test]
+    fn parses_time_controls() {
+        let control = TimeControl::parse("3+2", ClockMode::Fischer).unwrap();
+        assert_eq!(control.base, Duration::from_secs(180));
+        assert_eq!(control.increment, Duration::from_secs(2));
+        let control = TimeControl::parse("0.5", ClockMode::Delay).unwrap();
+        assert_eq!(control.base, Duration::from_secs(30));
+        assert_eq!(control.increment, Duration::ZERO);
+        for text in ["", "0", "5+", "-1+2", "3+-1", "five"] {
+            assert!(
+                TimeControl::parse(text, ClockMode::Fischer).is_err(),
+                "{text}"
+            );
+        }
+        assert_eq!(parse_mode("increment"), Ok(ClockMode::Fischer));
+        assert_eq!(parse_mode("bronstein"), Ok(ClockMode::Bronstein));
+        assert!(parse_mode("hourglass").is_err());
+    }
+
+    #[test]
+    fn formats_times() {
+        assert_eq!(format_time(Duration::from_millis(9_450)), "0:09.4");
+        assert_eq!(format_time(Duration::from_secs(185)), "3:05");
+        assert_eq!(format_time(Duration::from_secs(3_725)), "1:02:05");
+        assert_eq!(format_time(Duration::ZERO), "0:00.0");
+    }
+
+    #[test]
+    fn fischer_adds_the_increment_every_move() {
+        let mut clock = new_clock("1+1", ClockMode::Fischer);
+        let remaining = play(&mut clock, White, Duration::from_millis(20)).unwrap();
+        assert!(remaining > Duration::from_secs(60));
+        assert!(remaining < Duration::from_secs(61) - Duration::from_millis(20));
+        assert_eq!(clock.remaining(Black), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn bronstein_gives_back_at_most_the_increment() {
+        let mut clock = new_clock("1+1", ClockMode::Bronstein);
+        let remaining = play(&mut clock, White, Duration::from_millis(20));
+        assert_eq!(remaining, Some(Duration::from_secs(60)));
+        let mut clock = new_clock("1+0.01", ClockMode::Bronstein);
+        let remaining = play(&mut clock, White, Duration::from_millis(40)).unwrap();
+        assert!(remaining < Duration::from_secs(60) - Duration::from_millis(20));
+    }
+
+    #[test]
+    fn delay_waits_before_the_clock_runs() {
+        let mut clock = new_clock("1+1", ClockMode::Delay);
+        clock.start(Black);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(clock.remaining(Black), Duration::from_secs(60));
+        assert_eq!(clock.stop(), Some(Duration::from_secs(60)));
+        let mut clock = new_clock("1+0.01", ClockMode::Delay);
+        let remaining = play(&mut clock, Black, Duration::from_millis(40)).unwrap();
+        assert!(remaining < Duration::from_secs(60) - Duration::from_millis(20));
+    }
+
+    #[test]
+    fn flags_fall_while_the_player_thinks() {
+        // 0.0005 minutes is 30ms
+        let mut clock = new_clock("0.0005", ClockMode::Fischer);
+        assert_eq!(clock.flag_fallen(), None);
+        clock.start(White);
+        assert_eq!(clock.flag_fallen(), None);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(clock.flag_fallen(), Some(White));
+        assert_eq!(clock.remaining(White), Duration::ZERO);
+        assert_eq!(clock.stop(), None);
+        assert_eq!(clock.flag_fallen(), None);
+    }
+}
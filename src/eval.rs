@@ -0,0 +1,476 @@
+use crate::moves::Move;
+use crate::PieceType::{self, *};
+use crate::Team::{self, *};
+use crate::{Game, Square};
+use std::fs;
+use std::sync::OnceLock;
+
+pub const MATE_SCORE: i32 = 30000;
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1000;
+
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        Pawn => 100,
+        Knight => 320,
+        Bishop => 330,
+        Rook => 500,
+        Queen => 900,
+        King => 20000,
+    }
+}
+
+// material won outright by a move: the captured piece plus what a promotion adds
+pub fn capture_gain(game: &Game, mv: Move) -> i32 {
+    game.captured_piece(mv).map_or(0, piece_value)
+        + mv.promotion
+            .map_or(0, |piece_type| piece_value(piece_type) - 100)
+}
+
+// piece-square tables from white's point of view, rank 1 first
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5,  5, 10, 25, 25, 10,  5,  5,
+    10, 10, 20, 30, 30, 20, 10, 10,
+    50, 50, 50, 50, 50, 50, 50, 50,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+   -50,-40,-30,-30,-30,-30,-40,-50,
+   -40,-20,  0,  5,  5,  0,-20,-40,
+   -30,  5, 10, 15, 15, 10,  5,-30,
+   -30,  0, 15, 20, 20, 15,  0,-30,
+   -30,  5, 15, 20, 20, 15,  5,-30,
+   -30,  0, 10, 15, 15, 10,  0,-30,
+   -40,-20,  0,  0,  0,  0,-20,-40,
+   -50,-40,-30,-30,-30,-30,-40,-50,
+];
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+   -20,-10,-10,-10,-10,-10,-10,-20,
+   -10,  5,  0,  0,  0,  0,  5,-10,
+   -10, 10, 10, 10, 10, 10, 10,-10,
+   -10,  0, 10, 10, 10, 10,  0,-10,
+   -10,  5,  5, 10, 10,  5,  5,-10,
+   -10,  0,  5, 10, 10,  5,  0,-10,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -20,-10,-10,-10,-10,-10,-10,-20,
+];
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  5,  5,  0,  0,  0,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     5, 10, 10, 10, 10, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+   -20,-10,-10, -5, -5,-10,-10,-20,
+   -10,  0,  5,  0,  0,  0,  0,-10,
+   -10,  5,  5,  5,  5,  5,  0,-10,
+     0,  0,  5,  5,  5,  5,  0, -5,
+    -5,  0,  5,  5,  5,  5,  0, -5,
+   -10,  0,  5,  5,  5,  5,  0,-10,
+   -10,  0,  0,  0,  0,  0,  0,-10,
+   -20,-10,-10, -5, -5,-10,-10,-20,
+];
+#[rustfmt::skip]
+const KING_TABLE: [i32; 64] = [
+    20, 30, 10,  0,  0, 10, 30, 20,
+    20, 20,  0,  0,  0,  0, 20, 20,
+   -10,-20,-20,-20,-20,-20,-20,-10,
+   -20,-30,-30,-40,-40,-30,-30,-20,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+   -30,-40,-40,-50,-50,-40,-40,-30,
+];
+
+// the evaluation is a weighted sum of features, so the tuner can fit the weights directly;
+// weights are stored flat in this order
+pub const MATERIAL: usize = 0;
+pub const PIECE_SQUARE: usize = MATERIAL + 5;
+pub const MOBILITY: usize = PIECE_SQUARE + 6 * 64;
+pub const KING_SHIELD: usize = MOBILITY + 4;
+pub const KING_ATTACK: usize = KING_SHIELD + 1;
+pub const PARAM_COUNT: usize = KING_ATTACK + 1;
+
+const PIECE_NAMES: [&str; 6] = ["pawn", "knight", "bishop", "rook", "queen", "king"];
+const KNIGHT_JUMPS: [(i32, i32); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const DIAGONALS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const STRAIGHTS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+pub fn piece_index(piece_type: PieceType) -> usize {
+    match piece_type {
+        Pawn => 0,
+        Knight => 1,
+        Bishop => 2,
+        Rook => 3,
+        Queen => 4,
+        King => 5,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EvalParams {
+    pub weights: [i32; PARAM_COUNT],
+}
+
+impl Default for EvalParams {
+    fn default() -> EvalParams {
+        let mut weights = [0; PARAM_COUNT];
+        for piece_type in [Pawn, Knight, Bishop, Rook, Queen] {
+            weights[MATERIAL + piece_index(piece_type)] = piece_value(piece_type);
+        }
+        let tables = [
+            PAWN_TABLE,
+            KNIGHT_TABLE,
+            BISHOP_TABLE,
+            ROOK_TABLE,
+            QUEEN_TABLE,
+            KING_TABLE,
+        ];
+        for (piece, table) in tables.iter().enumerate() {
+            weights[PIECE_SQUARE + piece * 64..][..64].copy_from_slice(table);
+        }
+        weights[MOBILITY..KING_SHIELD].copy_from_slice(&[4, 5, 2, 1]);
+        weights[KING_SHIELD] = 10;
+        weights[KING_ATTACK] = 6;
+        EvalParams { weights }
+    }
+}
+
+impl EvalParams {
+    // a text file of named groups; groups that are left out keep their default weights
+    pub fn load(path: &str) -> Result<EvalParams, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Error: Could not read {path}: {err}"))?;
+        let mut params = EvalParams::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut tokens = line.split_whitespace();
+            let (offset, count) = match tokens.next().unwrap() {
+                "material" => (MATERIAL, 5),
+                "pst" => {
+                    let name = tokens.next().unwrap_or_default();
+                    let piece = PIECE_NAMES
+                        .iter()
+                        .position(|piece| *piece == name)
+                        .ok_or_else(|| {
+                            format!("Error: Unknown piece '{name}' on line {}", number + 1)
+                        })?;
+                    (PIECE_SQUARE + piece * 64, 64)
+                }
+                "mobility" => (MOBILITY, 4),
+                "king_shield" => (KING_SHIELD, 1),
+                "king_attack" => (KING_ATTACK, 1),
+                other => {
+                    return Err(format!(
+                        "Error: Unknown group '{other}' on line {}",
+                        number + 1
+                    ))
+                }
+            };
+            let values = tokens
+                .map(|token| token.parse::<i32>())
+                .collect::<Result<Vec<i32>, _>>()
+                .map_err(|_| format!("Error: Invalid weight on line {}", number + 1))?;
+            if values.len() != count {
+                return Err(format!(
+                    "Error: Expected {count} weights on line {}, found {}",
+                    number + 1,
+                    values.len()
+                ));
+            }
+            params.weights[offset..offset + count].copy_from_slice(&values);
+        }
+        Ok(params)
+    }
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let join = |weights: &[i32]| {
+            weights
+                .iter()
+                .map(|weight| weight.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let mut text = String::from(
+            "# evaluation weights in centipawns, tables from white's side, a1 first\n",
+        );
+        text.push_str(&format!(
+            "material {}\n",
+            join(&self.weights[MATERIAL..PIECE_SQUARE])
+        ));
+        for (piece, name) in PIECE_NAMES.iter().enumerate() {
+            let table = &self.weights[PIECE_SQUARE + piece * 64..][..64];
+            text.push_str(&format!("pst {name} {}\n", join(table)));
+        }
+        text.push_str(&format!(
+            "mobility {}\n",
+            join(&self.weights[MOBILITY..KING_SHIELD])
+        ));
+        text.push_str(&format!("king_shield {}\n", self.weights[KING_SHIELD]));
+        text.push_str(&format!("king_attack {}\n", self.weights[KING_ATTACK]));
+        fs::write(path, text).map_err(|err| format!("Error: Could not write {path}: {err}"))
+    }
+}
+
+static PARAMS: OnceLock<EvalParams> = OnceLock::new();
+
+// installs weights for the rest of the run; only the first call before any evaluation counts
+pub fn set_params(params: EvalParams) -> Result<(), String> {
+    PARAMS
+        .set(params)
+        .map_err(|_| "Error: Evaluation weights are already set".to_string())
+}
+
+pub fn params() -> &'static EvalParams {
+    PARAMS.get_or_init(EvalParams::default)
+}
+
+fn step(square: (i32, i32), direction: (i32, i32)) -> Option<(usize, usize)> {
+    let (rank, file) = (square.0 + direction.0, square.1 + direction.1);
+    ((0..8).contains(&rank) && (0..8).contains(&file)).then_some((rank as usize, file as usize))
+}
+
+// squares a knight, bishop, rook or queen could move to, and how many of them touch the enemy king
+fn mobility(
+    game: &Game,
+    piece_type: PieceType,
+    team: Team,
+    from: (i32, i32),
+    enemy_king: Square,
+) -> (i32, i32) {
+    let mut reachable = 0;
+    let mut king_attacks = 0;
+    let mut visit = |rank: usize, file: usize| {
+        let target = game.board[rank][file];
+        if target.is_none_or(|piece| piece.team() != team) {
+            reachable += 1;
+        }
+        if rank.abs_diff(enemy_king.rank()) <= 1 && file.abs_diff(enemy_king.file()) <= 1 {
+            king_attacks += 1;
+        }
+        target.is_none()
+    };
+    let directions: &[(i32, i32)] = match piece_type {
+        Knight => {
+            for jump in KNIGHT_JUMPS {
+                if let Some((rank, file)) = step(from, jump) {
+                    visit(rank, file);
+                }
+            }
+            return (reachable, king_attacks);
+        }
+        Bishop => &DIAGONALS,
+        Rook => &STRAIGHTS,
+        _ => &[
+            (1, 1),
+            (1, -1),
+            (-1, 1),
+            (-1, -1),
+            (1, 0),
+            (-1, 0),
+            (0, 1),
+            (0, -1),
+        ],
+    };
+    for direction in directions {
+        let mut square = from;
+        while let Some((rank, file)) = step(square, *direction) {
+            if !visit(rank, file) {
+                break;
+            }
+            square = (rank as i32, file as i32);
+        }
+    }
+    (reachable, king_attacks)
+}
+
+// own pawns on the two ranks in front of the king, on its file and the neighbouring ones
+fn pawn_shield(game: &Game, team: Team, king: Square) -> i32 {
+    let forward = if team == White { 1 } else { -1 };
+    let mut shield = 0;
+    for distance in 1..=2 {
+        for file_offset in -1..=1 {
+            let square = (king.rank() as i32, king.file() as i32);
+            let Some((rank, file)) = step(square, (forward * distance, file_offset)) else {
+                continue;
+            };
+            if game.board[rank][file].is_some_and(|piece| piece.is_pawn() && piece.team() == team) {
+                shield += 1;
+            }
+        }
+    }
+    shield
+}
+
+// calls `term` with each weight index and how often it applies, counted positive for white
+pub fn eval_terms(game: &Game, mut term: impl FnMut(usize, i32)) {
+    let mut kings = [None, None];
+    for (rank, row) in game.board.iter().enumerate() {
+        for (file, piece) in row.iter().enumerate() {
+            if let Some(piece) = piece.filter(|piece| piece.is_king()) {
+                kings[(piece.team() == Black) as usize] = Some(Square::new(rank, file));
+            }
+        }
+    }
+    for (rank, row) in game.board.iter().enumerate() {
+        for (file, piece) in row.iter().enumerate() {
+            let Some(piece) = piece else { continue };
+            let piece_type = piece.piece_type();
+            let index = piece_index(piece_type);
+            let (sign, own_rank, enemy) = match piece.team() {
+                White => (1, rank, Black),
+                // mirror the rank so black reads the tables from its own side
+                Black => (-1, 7 - rank, White),
+            };
+            if piece_type != King {
+                term(MATERIAL + index, sign);
+            }
+            term(
+                PIECE_SQUARE + index * 64 + Square::new(own_rank, file).index(),
+                sign,
+            );
+            if matches!(piece_type, Knight | Bishop | Rook | Queen) {
+                let Some(enemy_king) = kings[(enemy == Black) as usize] else {
+                    continue;
+                };
+                let (reachable, king_attacks) = mobility(
+                    game,
+                    piece_type,
+                    piece.team(),
+                    (rank as i32, file as i32),
+                    enemy_king,
+                );
+                term(MOBILITY + index - 1, sign * reachable);
+                term(KING_ATTACK, sign * king_attacks);
+            }
+        }
+    }
+    for (team, sign) in [(White, 1), (Black, -1)] {
+        if let Some(king) = kings[(team == Black) as usize] {
+            term(KING_SHIELD, sign * pawn_shield(game, team, king));
+        }
+    }
+}
+
+// static evaluation in centipawns from the side to move's point of view
+pub fn evaluate(game: &Game) -> i32 {
+    evaluate_with(game, params())
+}
+
+// the same with weights other than the installed ones, e.g. a bot's playing style
+pub fn evaluate_with(game: &Game, params: &EvalParams) -> i32 {
+    let weights = &params.weights;
+    let mut score = 0;
+    eval_terms(game, |index, count| score += weights[index] * count);
+    if game.is_white_turn() {
+        score
+    } else {
+        -score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(fen: &str) -> Game {
+        Game::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn start_position_is_balanced() {
+        assert_eq!(evaluate(&Game::init()), 0);
+    }
+
+    #[test]
+    fn score_is_from_the_side_to_move() {
+        let white = position("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+        let black = position("4k3/8/8/8/8/8/8/3QK3 b - - 0 1");
+        assert!(evaluate(&white) > 800);
+        assert_eq!(evaluate(&black), -evaluate(&white));
+    }
+
+    #[test]
+    fn mirrored_positions_score_the_same() {
+        let white = position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1");
+        let black = position("rnbqkb1r/pppp1ppp/5n2/4p3/4P3/2N5/PPPP1PPP/R1BQKBNR b KQkq - 0 1");
+        assert_eq!(evaluate(&white), evaluate(&black));
+    }
+
+    #[test]
+    fn capture_gain_counts_victims_and_promotions() {
+        let game = position("3r2k1/4P3/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            capture_gain(&game, Move::from_coords("e7d8q").unwrap()),
+            500 + 800
+        );
+        assert_eq!(
+            capture_gain(&game, Move::from_coords("e7e8n").unwrap()),
+            220
+        );
+        assert_eq!(capture_gain(&game, Move::from_coords("e1d1").unwrap()), 0);
+    }
+
+    #[test]
+    fn saved_weights_load_back() {
+        let path = std::env::temp_dir().join(format!("params-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut params = EvalParams::default();
+        params.weights[MATERIAL] = 93;
+        params.weights[PIECE_SQUARE + 64 + 27] = -17;
+        params.weights[KING_ATTACK] = 8;
+        params.save(path).unwrap();
+        let loaded = EvalParams::load(path);
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded.unwrap().weights, params.weights);
+    }
+
+    #[test]
+    fn partial_weight_files_keep_the_defaults() {
+        let path = std::env::temp_dir().join(format!("partial-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, "# only mobility\nmobility 1 2 3 4\n").unwrap();
+        let loaded = EvalParams::load(path).unwrap();
+        fs::write(path, "mobility 1 2 3\n").unwrap();
+        let short = EvalParams::load(path);
+        fs::write(path, "pst dragon 1\n").unwrap();
+        let unknown = EvalParams::load(path);
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded.weights[MOBILITY..KING_SHIELD], [1, 2, 3, 4]);
+        assert_eq!(
+            loaded.weights[MATERIAL],
+            EvalParams::default().weights[MATERIAL]
+        );
+        assert_eq!(
+            short.err().unwrap(),
+            "Error: Expected 4 weights on line 1, found 3"
+        );
+        assert_eq!(
+            unknown.err().unwrap(),
+            "Error: Unknown piece 'dragon' on line 1"
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use crate::eval::MATE_SCORE;
+use crate::moves::Move;
+use crate::search::{allocate_time, SearchLimits};
+use crate::Game;
+use crate::Team::{self, *};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// how long past its budget an engine may run before we send `stop`
+const MOVE_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy)]
+pub enum EngineLimit {
+    MoveTime(Duration),
+    Depth(u32),
+    // both sides' clocks, leaving the engine to manage its own time
+    Clock {
+        white_time: Duration,
+        black_time: Duration,
+        white_increment: Duration,
+        black_increment: Duration,
+    },
+}
+
+impl EngineLimit {
+    fn go_command(&self) -> String {
+        match self {
+            EngineLimit::MoveTime(movetime) => format!("go movetime {}", movetime.as_millis()),
+            EngineLimit::Depth(depth) => format!("go depth {depth}"),
+            EngineLimit::Clock {
+                white_time,
+                black_time,
+                white_increment,
+                black_increment,
+            } => format!(
+                "go wtime {} btime {} winc {} binc {}",
+                white_time.as_millis(),
+                black_time.as_millis(),
+                white_increment.as_millis(),
+                black_increment.as_millis()
+            ),
+        }
+    }
+    // the time `team` may spend on this move, if the limit is a time at all
+    pub fn movetime(&self, team: Team) -> Option<Duration> {
+        match self {
+            EngineLimit::MoveTime(movetime) => Some(*movetime),
+            EngineLimit::Depth(_) => None,
+            EngineLimit::Clock { .. } => {
+                let (time_left, increment) = self.clock(team)?;
+                Some(allocate_time(time_left, increment, None))
+            }
+        }
+    }
+    fn clock(&self, team: Team) -> Option<(Duration, Duration)> {
+        match (self, team) {
+            (
+                EngineLimit::Clock {
+                    white_time,
+                    white_increment,
+                    ..
+                },
+                White,
+            ) => Some((*white_time, *white_increment)),
+            (
+                EngineLimit::Clock {
+                    black_time,
+                    black_increment,
+                    ..
+                },
+                Black,
+            ) => Some((*black_time, *black_increment)),
+            _ => None,
+        }
+    }
+    // the same limit for the built-in search, moving for `team`
+    pub fn search_limits(&self, team: Team) -> SearchLimits {
+        match self {
+            EngineLimit::Depth(depth) => SearchLimits::depth(*depth),
+            _ => SearchLimits {
+                movetime: self.movetime(team),
+                ..SearchLimits::default()
+            },
+        }
+    }
+    // the longest we expect to wait for `bestmove`, before any grace period; on a clock
+    // the engine may spend all it has left
+    fn budget(&self, team: Team) -> Option<Duration> {
+        match self.clock(team) {
+            Some((time_left, _)) => Some(time_left),
+            None => self.movetime(team),
+        }
+    }
+}
+
+// `score cp <n>` or `score mate <moves>` from an info line
+fn parse_score(line: &str) -> Option<i32> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let index = tokens.iter().position(|token| *token == "score")?;
+    let value: i32 = tokens.get(index + 2)?.parse().ok()?;
+    match *tokens.get(index + 1)? {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(MATE_SCORE - (2 * value - 1)),
+        "mate" => Some(-MATE_SCORE - 2 * value),
+        _ => None,
+    }
+}
+
+// a UCI engine running as a child process, driven over its stdin/stdout
+pub struct ExternalEngine {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    start_fen: Option<String>,
+    last_score: Option<i32>,
+}
+
+impl ExternalEngine {
+    // `command` is the program followed by its arguments, split on whitespace
+    pub fn spawn(command: &str) -> Result<ExternalEngine, String> {
+        let mut parts = command.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| "Error: Empty engine command".to_string())?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("Error: Could not start engine '{program}': {err}"))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // a reader thread lets us wait on the engine with a timeout
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = ExternalEngine {
+            name: program.to_string(),
+            child,
+            stdin,
+            lines,
+            start_fen: None,
+            last_score: None,
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = engine.read_line(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+            if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.send("ucinewgame")?;
+        engine.sync()?;
+        Ok(engine)
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    // later `best_move` calls count their moves from this position, `None` being the start
+    pub fn new_game(&mut self, start_fen: Option<&str>) -> Result<(), String> {
+        self.start_fen = start_fen.map(str::to_string);
+        self.send("ucinewgame")?;
+        self.sync()
+    }
+    // the score from the engine's last `info` line, for the side it moved for
+    pub fn last_score(&self) -> Option<i32> {
+        self.last_score
+    }
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{command}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|err| format!("Error: Lost connection to engine: {err}"))
+    }
+    fn read_line(&mut self, deadline: Instant) -> Result<String, String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err("Error: Engine timed out".to_string()),
+            Err(RecvTimeoutError::Disconnected) => Err("Error: Engine exited".to_string()),
+        }
+    }
+    fn sync(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.read_line(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+    // asks for a move in the position reached by `moves` from the start position;
+    // `Ok(None)` means the engine has no move to play
+    pub fn best_move(
+        &mut self,
+        moves: &[Move],
+        game: &Game,
+        limit: EngineLimit,
+    ) -> Result<Option<Move>, String> {
+        let moves: Vec<String> = moves.iter().map(|mv| mv.to_string()).collect();
+        let start = match &self.start_fen {
+            Some(fen) => format!("position fen {fen}"),
+            None => "position startpos".to_string(),
+        };
+        if moves.is_empty() {
+            self.send(&start)?;
+        } else {
+            self.send(&format!("{start} moves {}", moves.join(" ")))?;
+        }
+        self.send(&limit.go_command())?;
+        self.last_score = None;
+
+        // on a clock the engine is stopped as soon as its time is gone, the flag decides
+        let grace = match limit {
+            EngineLimit::Clock { .. } => Duration::ZERO,
+            _ => MOVE_GRACE,
+        };
+        let mut deadline = limit
+            .budget(game.turn())
+            .map(|budget| Instant::now() + budget + grace);
+        let mut stop_sent = false;
+        loop {
+            let line = match deadline {
+                Some(time) => match self.read_line(time) {
+                    Ok(line) => line,
+                    Err(err) if stop_sent => return Err(err),
+                    Err(_) => {
+                        // over budget: ask for whatever it has, then give it one more grace period
+                        self.send("stop")?;
+                        stop_sent = true;
+                        deadline = Some(Instant::now() + MOVE_GRACE);
+                        continue;
+                    }
+                },
+                None => self
+                    .lines
+                    .recv()
+                    .map_err(|_| "Error: Engine exited".to_string())?,
+            };
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("bestmove") => {}
+                Some("info") => {
+                    self.last_score = parse_score(&line).or(self.last_score);
+                    continue;
+                }
+                _ => continue,
+            }
+            return match tokens.next() {
+                None | Some("0000") | Some("(none)") => Ok(None),
+                Some(coords) => game
+                    .find_move(coords)
+                    .map(Some)
+                    .map_err(|_| format!("Error: Engine sent an illegal move: {coords}")),
+            };
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // give it a moment to exit cleanly before killing it
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn go_commands() {
+        assert_eq!(
+            EngineLimit::MoveTime(Duration::from_millis(250)).go_command(),
+            "go movetime 250"
+        );
+        assert_eq!(EngineLimit::Depth(7).go_command(), "go depth 7");
+        let clock = EngineLimit::Clock {
+            white_time: Duration::from_secs(60),
+            black_time: Duration::from_millis(59500),
+            white_increment: Duration::from_secs(2),
+            black_increment: Duration::ZERO,
+        };
+        assert_eq!(
+            clock.go_command(),
+            "go wtime 60000 btime 59500 winc 2000 binc 0"
+        );
+    }
+
+    #[test]
+    fn clock_limits_budget_from_the_movers_clock() {
+        let clock = EngineLimit::Clock {
+            white_time: Duration::from_secs(60),
+            black_time: Duration::from_secs(3),
+            white_increment: Duration::ZERO,
+            black_increment: Duration::ZERO,
+        };
+        assert_eq!(
+            clock.movetime(White),
+            Some(allocate_time(Duration::from_secs(60), Duration::ZERO, None))
+        );
+        assert_eq!(clock.budget(Black), Some(Duration::from_secs(3)));
+        assert_eq!(clock.search_limits(Black).depth, None);
+        assert_eq!(EngineLimit::Depth(5).movetime(White), None);
+        assert_eq!(EngineLimit::Depth(5).search_limits(White).depth, Some(5));
+    }
+
+    #[test]
+    fn scores_from_info_lines() {
+        assert_eq!(
+            parse_score("info depth 9 score cp -35 nodes 1200 pv e2e4"),
+            Some(-35)
+        );
+        assert_eq!(
+            parse_score("info depth 9 score mate 1 pv d8h4"),
+            Some(MATE_SCORE - 1)
+        );
+        assert_eq!(
+            parse_score("info depth 9 score mate -2 pv a2a3"),
+            Some(-MATE_SCORE + 4)
+        );
+        assert_eq!(parse_score("info depth 9 nodes 1200"), None);
+        assert_eq!(parse_score("info score lowerbound 20"), None);
+    }
+}
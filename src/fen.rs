@@ -0,0 +1,193 @@
+use crate::PieceType::*;
+use crate::Team::*;
+use crate::{Game, Piece, Square};
+
+impl Game {
+    // halfmove and fullmove counters are accepted but not tracked by `Game`
+    pub fn from_fen(fen: &str) -> Result<Game, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 4 {
+            return Err("Error: FEN needs at least 4 fields".to_string());
+        }
+        let mut game = Game::init();
+        game.board = [[None; 8]; 8];
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err("Error: FEN board must have 8 ranks".to_string());
+        }
+        for (index, rank_str) in ranks.iter().enumerate() {
+            let rank = 7 - index;
+            let mut file = 0;
+            for char in rank_str.chars() {
+                if let Some(empty) = char.to_digit(10) {
+                    file += empty as usize;
+                    continue;
+                }
+                let team = if char.is_ascii_uppercase() {
+                    White
+                } else {
+                    Black
+                };
+                let piece_type = match char.to_ascii_lowercase() {
+                    'k' => King,
+                    'q' => Queen,
+                    'r' => Rook,
+                    'b' => Bishop,
+                    'n' => Knight,
+                    'p' => Pawn,
+                    _ => return Err(format!("Error: Invalid FEN piece '{char}'")),
+                };
+                if file > 7 {
+                    return Err("Error: FEN rank is too long".to_string());
+                }
+                game.board[rank][file] = Some(Piece::new(piece_type, team));
+                file += 1;
+            }
+            if file != 8 {
+                return Err("Error: FEN rank must cover 8 files".to_string());
+            }
+        }
+        for team in [White, Black] {
+            let kings = game
+                .find_team_pieces(team)
+                .iter()
+                .filter(|square| game.get_piece(**square).unwrap().is_king())
+                .count();
+            if kings != 1 {
+                return Err(format!("Error: FEN must have exactly one {team:?} king"));
+            }
+        }
+
+        game.turn = match fields[1] {
+            "w" => White,
+            "b" => Black,
+            _ => return Err("Error: FEN side to move must be 'w' or 'b'".to_string()),
+        };
+
+        let castling = fields[2];
+        game.white_rook_h_moved = !castling.contains('K');
+        game.white_rook_a_moved = !castling.contains('Q');
+        game.white_king_moved = game.white_rook_h_moved && game.white_rook_a_moved;
+        game.black_rook_h_moved = !castling.contains('k');
+        game.black_rook_a_moved = !castling.contains('q');
+        game.black_king_moved = game.black_rook_h_moved && game.black_rook_a_moved;
+
+        // FEN names the square behind the pawn, `Game` tracks the pawn itself
+        game.en_passant_pawn = match fields[3] {
+            "-" => None,
+            coords => {
+                let target = Square::from_coords(coords)?;
+                let pawn_rank = if game.is_white_turn() {
+                    target.rank() as i32 - 1
+                } else {
+                    target.rank() as i32 + 1
+                };
+                Some(Square::from_i32(pawn_rank, target.file() as i32)?)
+            }
+        };
+        Ok(game)
+    }
+    // halfmove and fullmove counters are not tracked, so they are written as "0 1"
+    pub fn fen(&self) -> String {
+        let ranks: Vec<String> = (0..8)
+            .rev()
+            .map(|rank| {
+                let mut rank_str = String::new();
+                let mut empty = 0;
+                for file in 0..8 {
+                    let Some(piece) = self.board[rank][file] else {
+                        empty += 1;
+                        continue;
+                    };
+                    if empty > 0 {
+                        rank_str.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    let char = match piece.piece_type() {
+                        King => 'k',
+                        Queen => 'q',
+                        Rook => 'r',
+                        Bishop => 'b',
+                        Knight => 'n',
+                        Pawn => 'p',
+                    };
+                    rank_str.push(if piece.team() == White {
+                        char.to_ascii_uppercase()
+                    } else {
+                        char
+                    });
+                }
+                if empty > 0 {
+                    rank_str.push_str(&empty.to_string());
+                }
+                rank_str
+            })
+            .collect();
+
+        let rights = [
+            (self.white_king_moved || self.white_rook_h_moved, 'K'),
+            (self.white_king_moved || self.white_rook_a_moved, 'Q'),
+            (self.black_king_moved || self.black_rook_h_moved, 'k'),
+            (self.black_king_moved || self.black_rook_a_moved, 'q'),
+        ];
+        let castling: String = rights
+            .iter()
+            .filter(|(lost, _)| !lost)
+            .map(|(_, char)| *char)
+            .collect();
+        let en_passant = match self.en_passant_pawn {
+            Some(pawn) => {
+                let behind = if pawn.rank() == 3 { 2 } else { 5 };
+                Square::new(behind, pawn.file()).to_coords()
+            }
+            None => "-".to_string(),
+        };
+        format!(
+            "{} {} {} {} 0 1",
+            ranks.join("/"),
+            if self.is_white_turn() { "w" } else { "b" },
+            if castling.is_empty() { "-" } else { &castling },
+            en_passant
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Game;
+
+    #[test]
+    fn start_position_fen() {
+        assert_eq!(
+            Game::init().fen(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+    }
+
+    #[test]
+    fn fen_round_trips() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 1",
+            "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b Kq d3 0 1",
+            "8/8/8/8/8/8/8/K6k b - - 0 1",
+        ] {
+            assert_eq!(Game::from_fen(fen).unwrap().fen(), fen);
+        }
+    }
+
+    #[test]
+    fn invalid_fens_are_rejected() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq -",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQQBNR w KQkq -",
+            "rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w",
+        ] {
+            assert!(Game::from_fen(fen).is_err(), "{fen}");
+        }
+    }
+}
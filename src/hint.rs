@@ -0,0 +1,188 @@
+use crate::moves::{Move, Outcome};
+use crate::ordering::OrderingConfig;
+use crate::retrograde::EndgameTables;
+use crate::search::{SearchLimits, Searcher};
+use crate::PieceType::{self, *};
+use crate::{Game, Square};
+use std::sync::Arc;
+use std::time::Duration;
+
+const HINT_TIME: Duration = Duration::from_secs(1);
+const HINT_DEPTH: u32 = 6;
+
+fn piece_name(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        King => "king",
+        Queen => "queen",
+        Rook => "rook",
+        Bishop => "bishop",
+        Knight => "knight",
+        Pawn => "pawn",
+    }
+}
+
+// the side to move's pieces that the opponent attacks and nothing defends
+fn threatened_pieces(game: &Game) -> Vec<Square> {
+    let mut opponent = *game;
+    opponent.switch_turn();
+    game.find_team_pieces(game.turn())
+        .into_iter()
+        .filter(|square| {
+            !game.get_piece(*square).unwrap().is_king()
+                && game.is_attacked_by(*square, opponent.turn())
+                && !game.is_attacked_by(*square, game.turn())
+        })
+        .collect()
+}
+
+// a short plain-English reason for playing `mv`, e.g. "captures the knight on f6 and gives check"
+pub fn explain_move(game: &Game, mv: Move) -> String {
+    let piece = game.get_piece(mv.from).unwrap();
+    let mut after = *game;
+    after.make_legal_move(mv);
+    // the mover's view of the position after the move, for threat checks
+    let mut mover_view = after;
+    mover_view.switch_turn();
+
+    let mut reasons = vec![];
+    if piece.is_king() && mv.from.file_diff(mv.to) > 1 {
+        reasons.push("castles the king to safety".to_string());
+    }
+    if let Some(captured) = game.captured_piece(mv) {
+        reasons.push(format!(
+            "captures the {} on {}",
+            piece_name(captured),
+            mv.to.to_coords()
+        ));
+    }
+    if let Some(promotion) = mv.promotion {
+        reasons.push(format!("promotes to a {}", piece_name(promotion)));
+    }
+    for square in threatened_pieces(game) {
+        let still_threatened = threatened_pieces(&mover_view);
+        if square == mv.from && !still_threatened.contains(&mv.to) {
+            reasons.push(format!(
+                "moves the threatened {} on {} to safety",
+                piece_name(piece.piece_type()),
+                square.to_coords()
+            ));
+        } else if square != mv.from && !still_threatened.contains(&square) {
+            reasons.push(format!(
+                "defends the threatened {} on {}",
+                piece_name(game.get_piece(square).unwrap().piece_type()),
+                square.to_coords()
+            ));
+        }
+    }
+    match after.outcome() {
+        Some(Outcome::Checkmate { .. }) => reasons.push("delivers checkmate".to_string()),
+        Some(Outcome::Stalemate) => reasons.push("forces stalemate".to_string()),
+        None if after.in_check() => reasons.push("gives check".to_string()),
+        None => {}
+    }
+    if reasons.is_empty() {
+        reasons.push(format!(
+            "improves the {}'s position",
+            piece_name(piece.piece_type())
+        ));
+    }
+
+    match reasons.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        None => unreachable!(),
+    }
+}
+
+// asks the engine for a move without playing it
+pub fn suggest_move(game: &Game, tablebases: Option<&Arc<EndgameTables>>) -> Option<String> {
+    let limits = SearchLimits {
+        depth: Some(HINT_DEPTH),
+        movetime: Some(HINT_TIME),
+        nodes: None,
+    };
+    let mut searcher = Searcher::new(OrderingConfig::all());
+    searcher.set_tablebases(tablebases.cloned());
+    let result = searcher.search_with(game, limits, |_| {});
+    let mv = result.best_move?;
+    Some(format!(
+        "Hint: {} ({} to {}) {}",
+        game.san(mv),
+        mv.from.to_coords(),
+        mv.to.to_coords(),
+        explain_move(game, mv)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explain(fen: &str, coords: &str) -> String {
+        explain_move(
+            &Game::from_fen(fen).unwrap(),
+            Move::from_coords(coords).unwrap(),
+        )
+    }
+
+    #[test]
+    fn explains_captures_checks_and_mates() {
+        assert_eq!(
+            explain("4k3/8/5n2/8/8/8/8/4KQ2 w - - 0 1", "f1f6"),
+            "captures the knight on f6"
+        );
+        assert_eq!(
+            explain("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", "a1a8"),
+            "gives check"
+        );
+        assert_eq!(
+            explain("r5k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"),
+            "captures the rook on a8, moves the threatened rook on a1 to safety and delivers checkmate"
+        );
+    }
+
+    #[test]
+    fn explains_castling_promotion_and_quiet_moves() {
+        assert_eq!(
+            explain("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1"),
+            "castles the king to safety"
+        );
+        assert_eq!(
+            explain("8/P7/8/8/8/7k/8/4K3 w - - 0 1", "a7a8q"),
+            "promotes to a queen"
+        );
+        assert_eq!(
+            explain(
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "g1f3"
+            ),
+            "improves the knight's position"
+        );
+    }
+
+    #[test]
+    fn explains_saving_and_defending_threatened_pieces() {
+        // the rook attacks the undefended bishop on b4
+        assert_eq!(
+            explain("4k3/8/8/8/1B5r/8/8/4K3 w - - 0 1", "b4c3"),
+            "moves the threatened bishop on b4 to safety"
+        );
+        assert_eq!(
+            explain("4k3/8/8/8/1B5r/8/8/4K3 w - - 0 1", "e1d1"),
+            "improves the king's position"
+        );
+        assert_eq!(
+            explain("4k3/8/8/8/1B5r/8/2K5/8 w - - 0 1", "c2c3"),
+            "defends the threatened bishop on b4"
+        );
+    }
+
+    #[test]
+    fn suggests_the_mating_move() {
+        let game = Game::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(
+            suggest_move(&game, None).unwrap(),
+            "Hint: Ra8# (a1 to a8) delivers checkmate"
+        );
+    }
+}
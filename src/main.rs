@@ -1,24 +1,90 @@
+mod analysis;
+mod bench;
+mod book;
+mod clock;
+mod eval;
+mod external_engine;
+mod fen;
+mod hint;
+mod mcts;
+mod moves;
+mod nnue;
+mod ordering;
+mod personality;
+mod pgn;
+mod player;
+mod random;
+mod record;
+mod retrograde;
+mod san;
+mod search;
+mod table;
+mod theme;
+mod tournament;
+mod tui;
+mod tuner;
+mod uci;
+mod view;
+mod xboard;
+mod zobrist;
+
+use book::OpeningBook;
+use clock::{Clock, ClockMode, TimeControl};
 use colored::ColoredString;
 use colored::Colorize;
+use external_engine::EngineLimit;
+use moves::Move;
+use player::{Assistance, GameEnd};
+use record::GameRecord;
+use retrograde::EndgameTables;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use theme::{Mark, Theme};
+use tui::Tui;
+use view::{ConsoleView, Orientation, Perspective, View};
 use PieceType::*;
 use Team::*;
+// padded to the width of a square, so a background covers all of it
 fn get_piece_char(piece: &Option<Piece>) -> ColoredString {
     if piece.is_none() {
-        return " ".into();
-    }
-    let char = match piece.unwrap().piece_type() {
-        King => "♚",
-        Queen => "♛",
-        Rook => "♜",
-        Bishop => "♝",
-        Knight => "♞",
-        Pawn => "♟",
-    };
+        return "    ".into();
+    }
+    let theme = theme::theme();
+    let char = format!(" {}  ", theme.glyph(piece.unwrap()));
     match piece.unwrap().team() {
+        _ if !theme.team_colors() => char.normal(),
         Black => char.red(),
         White => char.blue(),
     }
 }
+// squares picked out when the board is drawn
+#[derive(Default)]
+struct Highlight {
+    last_move: Option<Move>,
+    check: Option<Square>,
+    selected: Option<Square>,
+    targets: Vec<Square>,
+}
+impl Highlight {
+    // the move that led to the position and a king left in check
+    fn position(game: &Game, last_move: Option<Move>) -> Highlight {
+        Highlight {
+            last_move,
+            check: game.in_check().then(|| game.find_king(game.turn())),
+            ..Highlight::default()
+        }
+    }
+    // a piece and the squares it can move to
+    fn select(self, game: &Game, square: Square) -> Highlight {
+        Highlight {
+            selected: Some(square),
+            targets: game.get_legal_moves(square),
+            ..self
+        }
+    }
+}
 fn get_default_board() -> [[Option<Piece>; 8]; 8] {
     [
         [
@@ -116,7 +182,7 @@ impl Square {
         if rank > 7 || file > 7 {
             return Err("Error: Invalid Square".to_string());
         };
-        return Ok(Square { rank, file });
+        Ok(Square { rank, file })
     }
     fn from_i32(rank: i32, file: i32) -> Result<Square, String> {
         if rank > 7 || file > 7 || rank < 0 || file < 0 {
@@ -124,7 +190,7 @@ impl Square {
         };
         let rank = rank as usize;
         let file = file as usize;
-        return Ok(Square { rank, file });
+        Ok(Square { rank, file })
     }
     fn from_coords(coords: &str) -> Result<Square, String> {
         if coords.chars().count() != 2 {
@@ -139,16 +205,23 @@ impl Square {
             None => return Err("Error: Invalid Coordinates".to_string()),
         };
         let rank = match rank_str.to_string().parse::<usize>() {
-            Ok(rank) => rank - 1,
-            Err(_) => return Err("Error: Invalid Coordinates".to_string()),
+            Ok(rank) if rank > 0 => rank - 1,
+            _ => return Err("Error: Invalid Coordinates".to_string()),
         };
         Square::from_usize(rank, file)
     }
+    fn to_coords(self) -> String {
+        let file_strs = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+        format!("{}{}", file_strs[self.file()], self.rank() + 1)
+    }
+    fn index(&self) -> usize {
+        self.rank() * 8 + self.file()
+    }
     fn rank_diff(&self, target_sq: Square) -> usize {
-        (self.rank() as i32 - target_sq.rank() as i32).abs() as usize
+        self.rank().abs_diff(target_sq.rank())
     }
     fn file_diff(&self, target_sq: Square) -> usize {
-        (self.file() as i32 - target_sq.file() as i32).abs() as usize
+        self.file().abs_diff(target_sq.file())
     }
 }
 impl Game {
@@ -217,58 +290,71 @@ impl Game {
         let rank_index = if self.is_white_turn() { 7 } else { 0 };
         self.board[rank_index][file] = Some(Piece::new(piece_type, self.turn()));
     }
-    fn check_for_checkmate(&self) -> (bool, Option<String>) {
-        let mut error_message = None;
-        if self.is_white_turn() && self.black_in_check() {
-            if self.is_checkmate() {
-                println!("Checkmate");
-                return (true, error_message);
-            }
-            error_message = Some("Red, you're in check!".to_string());
-        } else if self.is_black_turn() && self.white_in_check() {
-            if self.is_checkmate() {
-                println!("Checkmate");
-                return (true, error_message);
-            }
-            error_message = Some("Blue, you're in check!".to_string());
-        } else {
-            error_message = None;
-        }
-        return (false, error_message);
-    }
-    fn is_checkmate(&self) -> bool {
-        let loser_team = if self.is_white_turn() { Black } else { White };
-        let loser_piece_squares = self.find_team_pieces(loser_team);
-        for loser_piece_sq in loser_piece_squares {
-            if self.get_legal_moves(loser_piece_sq).len() > 0 {
-                return false;
-            }
-        }
-        return true;
-    }
     fn white_in_check(&self) -> bool {
-        let black_piece_squares = self.find_team_pieces(Black);
-        let white_king_sq = &self.find_king(White);
-        for black_piece_sq in black_piece_squares {
-            if self
-                .get_possible_moves(Black, black_piece_sq)
-                .contains(white_king_sq)
-            {
-                return true;
-            };
-        }
-        false
+        self.is_attacked_by(self.find_king(White), Black)
     }
     fn black_in_check(&self) -> bool {
-        let white_piece_squares = self.find_team_pieces(White);
-        let black_king_sq = &self.find_king(Black);
-        for white_piece_sq in white_piece_squares {
-            if self
-                .get_possible_moves(White, white_piece_sq)
-                .contains(black_king_sq)
-            {
-                return true;
+        self.is_attacked_by(self.find_king(Black), White)
+    }
+    fn is_attacked_by(&self, target_sq: Square, team: Team) -> bool {
+        let target_rank = target_sq.rank() as i32;
+        let target_file = target_sq.file() as i32;
+        let has_attacker =
+            |rank: i32, file: i32, piece_types: &[PieceType]| match Square::from_i32(rank, file) {
+                Ok(square) => self.get_piece(square).is_some_and(|piece| {
+                    piece.team() == team && piece_types.contains(&piece.piece_type())
+                }),
+                Err(_) => false,
             };
+
+        // pawns attack diagonally towards the far side of their own team
+        let pawn_rank = if team == White {
+            target_rank - 1
+        } else {
+            target_rank + 1
+        };
+        if has_attacker(pawn_rank, target_file - 1, &[Pawn])
+            || has_attacker(pawn_rank, target_file + 1, &[Pawn])
+        {
+            return true;
+        }
+        for rank_diff in [-2_i32, -1, 1, 2] {
+            for file_diff in [-2_i32, -1, 1, 2] {
+                if rank_diff.abs() + file_diff.abs() == 3
+                    && has_attacker(target_rank + rank_diff, target_file + file_diff, &[Knight])
+                {
+                    return true;
+                }
+            }
+        }
+        for rank_diff in [-1, 0, 1] {
+            for file_diff in [-1, 0, 1] {
+                if (rank_diff, file_diff) == (0, 0) {
+                    continue;
+                }
+                let sliders: &[PieceType] = if rank_diff == 0 || file_diff == 0 {
+                    &[Rook, Queen]
+                } else {
+                    &[Bishop, Queen]
+                };
+                if has_attacker(target_rank + rank_diff, target_file + file_diff, &[King]) {
+                    return true;
+                }
+                // walk the ray until the first piece
+                for distance in 1..8 {
+                    let rank = target_rank + rank_diff * distance;
+                    let file = target_file + file_diff * distance;
+                    let Ok(square) = Square::from_i32(rank, file) else {
+                        break;
+                    };
+                    if self.get_piece(square).is_some() {
+                        if has_attacker(rank, file, sliders) {
+                            return true;
+                        }
+                        break;
+                    }
+                }
+            }
         }
         false
     }
@@ -376,21 +462,21 @@ impl Game {
                 }
                 let new_rank = start_rank + rank_movement;
                 let new_file = start_file + file_movement;
-                let new_sq = Square::from_i32(new_rank, new_file);
-                if new_sq.is_ok() {
-                    potential_moves.push(new_sq.unwrap())
+                if let Ok(new_sq) = Square::from_i32(new_rank, new_file) {
+                    potential_moves.push(new_sq)
                 }
             }
         }
 
         // castle
-        if self.is_white_turn() && !self.white_king_moved {
+        let is_white_king = self.get_piece(start_sq).unwrap().team() == White;
+        if is_white_king && self.is_white_turn() && !self.white_king_moved {
             if !self.white_rook_a_moved
                 && self.get_piece(Square::new(0, 1)).is_none()
                 && self.get_piece(Square::new(0, 2)).is_none()
                 && self.get_piece(Square::new(0, 3)).is_none()
             {
-                potential_moves.push(Square::new(0, 1));
+                potential_moves.push(Square::new(0, 2));
             }
             if !self.white_rook_h_moved
                 && self.get_piece(Square::new(0, 5)).is_none()
@@ -398,13 +484,13 @@ impl Game {
             {
                 potential_moves.push(Square::new(0, 6));
             }
-        } else if self.is_black_turn() && !self.black_king_moved {
+        } else if !is_white_king && self.is_black_turn() && !self.black_king_moved {
             if !self.black_rook_a_moved
                 && self.get_piece(Square::new(7, 1)).is_none()
                 && self.get_piece(Square::new(7, 2)).is_none()
                 && self.get_piece(Square::new(7, 3)).is_none()
             {
-                potential_moves.push(Square::new(7, 1));
+                potential_moves.push(Square::new(7, 2));
             }
             if !self.black_rook_h_moved
                 && self.get_piece(Square::new(7, 5)).is_none()
@@ -434,8 +520,8 @@ impl Game {
             for file_diff in [-2_i32, -1, 1, 2] {
                 if rank_diff.abs() + file_diff.abs() == 3 {
                     let new_sq = Square::from_i32(start_rank + rank_diff, start_file + file_diff);
-                    if new_sq.is_ok() {
-                        potential_moves.push(new_sq.unwrap())
+                    if let Ok(new_sq) = new_sq {
+                        potential_moves.push(new_sq)
                     }
                 }
             }
@@ -451,8 +537,10 @@ impl Game {
 
         let start_rank = start_sq.rank() as i32;
         let start_file = start_sq.file() as i32;
-        let unmoved_rank = if self.is_white_turn() { 1 } else { 6 };
-        let move_direction = if self.is_white_turn() { 1 } else { -1 };
+        // pawns move by their own team's direction, so attacks are right on either turn
+        let is_white_pawn = self.get_piece(start_sq).unwrap().team() == White;
+        let unmoved_rank = if is_white_pawn { 1 } else { 6 };
+        let move_direction = if is_white_pawn { 1 } else { -1 };
         let single_move_rank = start_rank + move_direction;
         let double_move_rank = single_move_rank + move_direction;
 
@@ -484,14 +572,14 @@ impl Game {
         }
 
         // en passant
-        if self.en_passant_pawn.is_some()
-            && start_sq.rank_diff(self.en_passant_pawn.unwrap()) == 0
-            && start_sq.file_diff(self.en_passant_pawn.unwrap()) == 1
-        {
-            potential_moves.push(Square::new(
-                single_move_rank as usize,
-                self.en_passant_pawn.unwrap().file(),
-            ));
+        if let Some(en_passant_pawn) = self.en_passant_pawn {
+            if start_sq.rank_diff(en_passant_pawn) == 0 && start_sq.file_diff(en_passant_pawn) == 1
+            {
+                potential_moves.push(Square::new(
+                    single_move_rank as usize,
+                    en_passant_pawn.file(),
+                ));
+            }
         }
 
         potential_moves
@@ -519,11 +607,7 @@ impl Game {
         possible_moves.retain(|target_sq| {
             let target_piece = self.get_piece(*target_sq);
 
-            if target_piece.is_some() && target_piece.unwrap().team() == start_piece.team() {
-                return false;
-            } else {
-                return true;
-            }
+            !(target_piece.is_some() && target_piece.unwrap().team() == start_piece.team())
         });
 
         possible_moves
@@ -569,7 +653,7 @@ impl Game {
                 }
             }
 
-            if target_sq.file() == 1 {
+            if target_sq.file() == 2 {
                 let test_move_1 = self.move_piece_test(start_sq, Square::new(target_sq.rank(), 2));
                 let test_move_2 = self.move_piece_test(start_sq, Square::new(target_sq.rank(), 3));
 
@@ -581,7 +665,17 @@ impl Game {
                     return Err("You cannot castle through check".to_string());
                 }
             }
+        }
+
+        self.execute_move(start_sq, target_sq);
+        Ok(())
+    }
+    // applies a move already known to be legal, without validating it again
+    fn execute_move(&mut self, start_sq: Square, target_sq: Square) {
+        let piece = self.board[start_sq.rank()][start_sq.file()].unwrap();
 
+        // castle
+        if piece.is_king() && start_sq.file_diff(target_sq) > 1 {
             let rook_start_file;
             let rook_end_file;
             if target_sq.file() == 6 {
@@ -589,7 +683,7 @@ impl Game {
                 rook_end_file = 5;
             } else {
                 rook_start_file = 0;
-                rook_end_file = 2;
+                rook_end_file = 3;
             }
             let rook = self.board[target_sq.rank()][rook_start_file];
             self.board[target_sq.rank()][rook_start_file] = None;
@@ -617,46 +711,117 @@ impl Game {
             (7, 7) => self.black_rook_h_moved = true,
             _ => {}
         }
+        // a rook captured on its corner can no longer castle either
+        match (target_sq.rank(), target_sq.file()) {
+            (0, 0) => self.white_rook_a_moved = true,
+            (0, 7) => self.white_rook_h_moved = true,
+            (7, 0) => self.black_rook_a_moved = true,
+            (7, 7) => self.black_rook_h_moved = true,
+            _ => {}
+        }
         if piece.is_pawn() && start_sq.rank_diff(target_sq) == 2 {
             self.en_passant_pawn = Some(target_sq);
         } else {
             self.en_passant_pawn = None;
         }
-
-        Ok(())
     }
     fn move_piece_test(&self, start_sq: Square, target_sq: Square) -> Game {
-        let mut test_game = self.clone();
+        let mut test_game = *self;
         let piece = self.board[start_sq.rank][start_sq.file];
+        // an en passant capture also clears the passed pawn, which may uncover the king
+        if piece.is_some_and(|piece| piece.is_pawn())
+            && start_sq.file != target_sq.file
+            && self.board[target_sq.rank][target_sq.file].is_none()
+        {
+            test_game.board[start_sq.rank][target_sq.file] = None;
+        }
         test_game.board[start_sq.rank][start_sq.file] = None;
         test_game.board[target_sq.rank][target_sq.file] = piece;
         test_game
     }
-    fn display_board(&self) {
-        println!("   +----+----+----+----+----+----+----+----+");
-        let mut board = self.get_board().clone();
-
-        if self.is_white_turn() {
-            board.reverse();
-        }
-
-        for (index, rank) in board.iter().enumerate() {
-            let rank_label = if self.is_white_turn() {
-                8 - index
-            } else {
-                index + 1
-            };
-            print!("{}  ", rank_label);
-            let mut rank = rank.to_owned();
-            if self.is_black_turn() {
-                rank.reverse();
+    // the cell of one square, with its background marking a selected piece, a square it can
+    // move to or capture on, a king in check, or the last move; without colors the piece is
+    // bracketed instead, e.g. `(♞)` selected, `*♟*` capture, `!♚!` check, `[♟]` last move
+    fn square_cell(&self, square: Square, highlight: &Highlight) -> ColoredString {
+        let piece = self.get_piece(square);
+        let is_target = highlight.targets.contains(&square);
+        let is_capture = highlight
+            .selected
+            .is_some_and(|from| self.captured_piece(Move::new(from, square)).is_some());
+        let is_last_move = highlight
+            .last_move
+            .is_some_and(|mv| mv.from == square || mv.to == square);
+        let mark = Mark::strongest([
+            (highlight.selected == Some(square)).then_some(Mark::Selected),
+            (is_target && is_capture).then_some(Mark::Capture),
+            is_target.then_some(Mark::Target),
+            (highlight.check == Some(square)).then_some(Mark::Check),
+            is_last_move.then_some(Mark::LastMove),
+        ]);
+        let cell = match piece {
+            None if is_target => " ·  ".normal(),
+            _ => get_piece_char(&piece),
+        };
+        let theme = theme::theme();
+        match mark {
+            None if theme.checkered && theme.colors() => {
+                let (r, g, b) = Theme::square_color(square);
+                let cell = if theme.team_colors() || piece.is_none() {
+                    cell
+                } else {
+                    cell.black()
+                };
+                cell.on_truecolor(r, g, b)
             }
-            for square in rank.iter() {
-                print!("| {}  ", get_piece_char(square))
+            None => cell,
+            Some(mark) if theme.colors() => {
+                let (r, g, b) = mark.color();
+                // a red king would vanish on the red of check
+                if mark == Mark::Check {
+                    cell.bright_white().bold().on_truecolor(r, g, b)
+                } else {
+                    cell.on_truecolor(r, g, b)
+                }
+            }
+            Some(mark) => {
+                let glyph = match piece {
+                    Some(piece) => theme.glyph(piece),
+                    None if is_target => "·",
+                    None => " ",
+                };
+                let (open, close) = mark.brackets();
+                format!("{open}{glyph}{close} ").normal()
             }
-            println!("|\n   +----+----+----+----+----+----+----+----+");
         }
-        if self.is_white_turn() {
+    }
+    // with a clock, each side's time is shown beside the rank nearest to them
+    fn display_board(&self, clock: Option<&Clock>, highlight: &Highlight, bottom: Team) {
+        let clock_text = |team: Team| {
+            clock.map_or(String::new(), |clock| {
+                format!(
+                    "   {} {}",
+                    player::team_name(team),
+                    clock::format_time(clock.remaining(team))
+                )
+            })
+        };
+        let top = if bottom == White { Black } else { White };
+        println!("   +----+----+----+----+----+----+----+----+");
+        for index in 0..8 {
+            let rank = if bottom == White { 7 - index } else { index };
+            print!("{}  ", rank + 1);
+            for file in 0..8 {
+                let file = if bottom == White { file } else { 7 - file };
+                print!("|{}", self.square_cell(Square::new(rank, file), highlight))
+            }
+            let side_note = match index {
+                0 => clock_text(top),
+                7 => clock_text(bottom),
+                _ => String::new(),
+            };
+            println!("|{side_note}\n   +----+----+----+----+----+----+----+----+");
+        }
+        if bottom == White {
             println!("     a    b    c    d    e    f    g    h\n")
         } else {
             println!("     h    g    f    e    d    c    b    a\n")
@@ -664,114 +829,377 @@ impl Game {
     }
 }
 
-fn parse_coords(input: &str) -> Result<(Square, Square), String> {
-    let coords: Vec<&str> = input.split(" ").collect();
-    if coords.len() != 2 {
-        return Err("Error: Must input 2 coordinates".to_string());
-    }
-    let start_sq = Square::from_coords(coords[0]);
-    let target_sq = Square::from_coords(coords[1]);
-
-    let (start_sq, target_sq) = match (start_sq, target_sq) {
-        (Ok(start_sq), Ok(target_sq)) => (start_sq, target_sq),
-        _ => return Err("Invalid Coordinates".to_string()),
-    };
-
-    Ok((start_sq, target_sq))
+struct GameOptions {
+    // player specs for each side as `player::player_from_spec` takes, e.g. `greedy` or
+    // `mcts:time=500`; --opponent and --engine fill in the side --engine-color names
+    white: Option<String>,
+    black: Option<String>,
+    opponent: Option<String>,
+    engine_team: Team,
+    engine_limit: EngineLimit,
+    book_path: Option<String>,
+    tablebase_path: Option<String>,
+    time_control: Option<TimeControl>,
+    theme: Option<Theme>,
+    orientation: Orientation,
+    // the full-screen interface instead of the scrolling console
+    tui: bool,
 }
 
-fn handle_pawn_on_last_rank(game: &mut Game, pawn_file: usize) {
-    loop {
-        println!("Congrats! You got a pawn to the last rank. Which piece would you like:\na) Queen\nb) Rook\nc) Bishop\nd) Knight");
-        let mut input = "".to_string();
-        match std::io::stdin().read_line(&mut input) {
-            Ok(_) => {}
-            Err(err) => {
-                println!("Error reading input: {}", err);
-                continue;
+fn parse_game_options(args: &[String]) -> Result<GameOptions, String> {
+    let mut options = GameOptions {
+        white: None,
+        black: None,
+        opponent: None,
+        engine_team: Black,
+        engine_limit: EngineLimit::MoveTime(Duration::from_millis(1000)),
+        book_path: None,
+        tablebase_path: None,
+        time_control: None,
+        theme: None,
+        orientation: Orientation::Human,
+        tui: false,
+    };
+    let mut time_control = None;
+    let mut clock_mode = ClockMode::Fischer;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Error: {arg} needs a value"))
+        };
+        match arg.as_str() {
+            "--engine" => options.opponent = Some(format!("uci:{}", value()?)),
+            "--opponent" => options.opponent = Some(value()?.clone()),
+            "--white" | "--blue" => options.white = Some(value()?.clone()),
+            "--black" | "--red" => options.black = Some(value()?.clone()),
+            "--engine-color" => {
+                options.engine_team = match value()?.as_str() {
+                    "white" | "blue" => White,
+                    "black" | "red" => Black,
+                    other => return Err(format!("Error: Unknown color '{other}'")),
+                }
             }
-        }
-        input = input.trim().to_string();
-        let replacement_piece = match &input[..] {
-            "a" => Queen,
-            "b" => Rook,
-            "c" => Bishop,
-            "d" => Knight,
-            _ => {
-                println!("{}", "Input Error: Pick from the listed options".red());
-                continue;
+            "--movetime" => {
+                let millis = value()?
+                    .parse()
+                    .map_err(|_| "Error: --movetime needs milliseconds".to_string())?;
+                options.engine_limit = EngineLimit::MoveTime(Duration::from_millis(millis));
             }
+            "--book" => options.book_path = Some(value()?.clone()),
+            "--tablebases" => options.tablebase_path = Some(value()?.clone()),
+            "--clock" => time_control = Some(value()?.clone()),
+            "--clock-mode" => clock_mode = clock::parse_mode(value()?)?,
+            "--tui" => options.tui = true,
+            "--theme" => options.theme = Some(Theme::parse(value()?)?),
+            "--orientation" => options.orientation = view::parse_orientation(value()?)?,
+            "--depth" => {
+                let depth = value()?
+                    .parse()
+                    .map_err(|_| "Error: --depth needs a number".to_string())?;
+                options.engine_limit = EngineLimit::Depth(depth);
+            }
+            _ => return Err(format!("Unknown option: {arg}")),
+        }
+    }
+    options.time_control = time_control
+        .map(|text| TimeControl::parse(&text, clock_mode))
+        .transpose()?;
+    Ok(options)
+}
+
+impl GameOptions {
+    fn player_spec(&self, team: Team) -> String {
+        let own = match team {
+            White => &self.white,
+            Black => &self.black,
         };
-        game.replace_last_rank_pawn(pawn_file, replacement_piece);
-        break;
+        own.clone()
+            .or_else(|| self.opponent.clone().filter(|_| self.engine_team == team))
+            .unwrap_or_else(|| "human".to_string())
     }
 }
 
 fn main() {
-    let mut game = Game::init();
-    let mut error_message: Option<String> = None;
-    let was_checkmate_win;
-
-    let white_lost = loop {
-        game.display_board();
-        if let Some(error_message) = error_message {
-            println!("{}\n", format!("{error_message}").red().bold());
-        }
-        println!(
-            "{}, your turn! Enter the coordinate of the piece you want to move followed by the coordinate of the target square",
-            if game.is_white_turn() { "Blue" } else { "Red" }
-        );
-
-        let mut input = String::new();
-        match std::io::stdin().read_line(&mut input) {
-            Ok(_) => {}
-            Err(err) => {
-                error_message = Some(format!("Error reading input: {}", err));
-                continue;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    // tuned evaluation weights apply in every mode, so they are taken out before dispatch
+    if let Some(index) = args.iter().position(|arg| arg == "--eval-params") {
+        let loaded = match args.get(index + 1) {
+            Some(path) => eval::EvalParams::load(path).and_then(eval::set_params),
+            None => Err("Error: --eval-params needs a value".to_string()),
+        };
+        if let Err(err) = loaded {
+            println!("{}", err.red());
+            return;
+        }
+        args.drain(index..index + 2);
+    }
+    match args.first().map(|arg| arg.as_str()) {
+        Some("--bench") => {
+            let depth = args
+                .get(1)
+                .and_then(|depth| depth.parse().ok())
+                .unwrap_or(4);
+            let threads = args
+                .get(2)
+                .and_then(|threads| threads.parse().ok())
+                .unwrap_or(1);
+            if let Err(err) = bench::run_bench(depth, threads) {
+                println!("{}", err.red());
             }
+            return;
         }
-        input = input.trim().to_string();
-        if input == "q" {
-            was_checkmate_win = false;
-            break game.is_white_turn();
+        Some("--uci") => {
+            uci::run_uci();
+            return;
         }
-
-        let (start_sq, target_sq) = match parse_coords(&input) {
-            Ok(res) => res,
+        Some("--generate-tables") => {
+            if let Err(err) = retrograde::run_generate(&args[1..]) {
+                println!("{}", err.red());
+            }
+            return;
+        }
+        Some("--build-book") => {
+            if let Err(err) = book::run_build_book(&args[1..]) {
+                println!("{}", err.red());
+            }
+            return;
+        }
+        Some("--match") => {
+            if let Err(err) = tournament::run_match(&args[1..]) {
+                println!("{}", err.red());
+            }
+            return;
+        }
+        Some("--nnue-check") => {
+            if let Err(err) = nnue::run_check(&args[1..]) {
+                println!("{}", err.red());
+            }
+            return;
+        }
+        Some("--tune") => {
+            if let Err(err) = tuner::run_tune(&args[1..]) {
+                println!("{}", err.red());
+            }
+            return;
+        }
+        Some("--xboard") => {
+            xboard::run_xboard();
+            return;
+        }
+        _ => {}
+    }
+    let options = match parse_game_options(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            println!("{}", err.red());
+            return;
+        }
+    };
+    if let Some(theme) = options.theme {
+        if let Err(err) = theme::set_theme(theme) {
+            println!("{}", err.red());
+            return;
+        }
+    }
+    if theme::theme().monochrome {
+        colored::control::set_override(false);
+        crossterm::style::force_color_output(false);
+    }
+    let book = match &options.book_path {
+        Some(path) => match OpeningBook::open(path) {
+            Ok(book) => {
+                println!(
+                    "Loaded opening book {} ({} entries)",
+                    path,
+                    book.entry_count()
+                );
+                Some(Rc::new(book))
+            }
             Err(err) => {
-                error_message = Some(err);
-                continue;
+                println!("{}", err.red());
+                return;
             }
-        };
+        },
+        None => None,
+    };
+    let endgame_tables = match &options.tablebase_path {
+        Some(path) => match EndgameTables::load(path) {
+            Ok(tables) => {
+                println!("Loaded endgame tables {}", tables.names().join(" "));
+                Some(Arc::new(tables))
+            }
+            Err(err) => {
+                println!("{}", err.red());
+                return;
+            }
+        },
+        None => None,
+    };
 
-        if let Err(e) = game.move_piece(start_sq, target_sq) {
-            error_message = Some(format!("{e}"));
-            continue;
+    let assistance = Rc::new(Assistance {
+        book,
+        endgame_tables,
+    });
+    let humans = [White, Black].map(|team| options.player_spec(team) == "human");
+    let perspective = Perspective::new(options.orientation, humans);
+    let tui = options.tui.then(|| Tui::new(perspective));
+    let console_perspective = Rc::new(Cell::new(perspective));
+    let make_player = |team: Team| {
+        let spec = options.player_spec(team);
+        player::player_from_spec(
+            &spec,
+            team,
+            &assistance,
+            options.engine_limit,
+            tui.as_ref(),
+            &console_perspective,
+        )
+    };
+    let mut players = match make_player(White).and_then(|white| Ok([white, make_player(Black)?])) {
+        Ok(players) => players,
+        Err(err) => {
+            println!("{}", err.red());
+            return;
         }
-
-        let has_last_rank_pawn = game.last_rank_pawn_index();
-        if let Some(last_rank_pawn_index) = has_last_rank_pawn {
-            handle_pawn_on_last_rank(&mut game, last_rank_pawn_index)
+    };
+    let mut view: Box<dyn View> = match &tui {
+        Some(tui) => {
+            let names = players.each_ref().map(|player| player.name().to_string());
+            if let Err(err) = tui.open(names) {
+                println!("{}", err.red());
+                return;
+            }
+            Box::new(tui.clone())
         }
-
-        let (is_checkmate, error_msg) = game.check_for_checkmate();
-        if is_checkmate {
-            was_checkmate_win = true;
-            break !game.is_white_turn();
+        None => Box::new(ConsoleView {
+            perspective: console_perspective.clone(),
+        }),
+    };
+    for (team, player) in [White, Black].into_iter().zip(&players) {
+        if !player.interactive() {
+            view.message(&format!(
+                "{} plays {}",
+                player.name(),
+                player::team_name(team)
+            ));
+            if let Some(description) = player.description() {
+                match tui {
+                    Some(_) => view.message(description),
+                    None => println!("{}", description.italic()),
+                }
+            }
         }
-        error_message = error_msg;
+    }
 
-        game.switch_turn();
+    let mut record = GameRecord::new();
+    let (game, end) = player::play_game(
+        &mut players,
+        &mut record,
+        options.time_control,
+        view.as_mut(),
+    );
+
+    // humans go by their color, bots get it added so two of a kind can be told apart
+    let winner_name = |team: Team| {
+        let name = players[if team == White { 0 } else { 1 }].name();
+        if name == player::team_name(team) {
+            name.to_string()
+        } else {
+            format!("{name} ({})", player::team_name(team))
+        }
+    };
+    let other = |team: Team| if team == White { Black } else { White };
+    let result = match end {
+        GameEnd::Checkmate { winner } => vec![
+            "Checkmate!!".to_string(),
+            format!("Congratulations {}!!", winner_name(winner)),
+        ],
+        GameEnd::Resigned { loser }
+        | GameEnd::OutOfTime {
+            loser,
+            drawn: false,
+        } => {
+            vec![format!("Congratulations {}!!", winner_name(other(loser)))]
+        }
+        GameEnd::OutOfTime { loser, drawn: true } => vec![format!(
+            "{} cannot checkmate, so the game is drawn",
+            winner_name(other(loser))
+        )],
+        GameEnd::Stalemate => vec!["Stalemate, the game is drawn".to_string()],
+        GameEnd::InsufficientMaterial => {
+            vec!["Neither side can checkmate, the game is drawn".to_string()]
+        }
+        GameEnd::Repetition => {
+            vec!["The same position came up three times, the game is drawn".to_string()]
+        }
+        GameEnd::FiftyMoves => vec![
+            "Fifty moves went by without a capture or pawn move, the game is drawn".to_string(),
+        ],
+        GameEnd::DrawAgreed => vec!["The game is drawn by agreement".to_string()],
     };
+    view.game_over(&game, &record, &result);
+    println!("{}", record.summary());
+
+    println!("Thanks for playing!!")
+}
 
-    game.display_board();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if was_checkmate_win {
-        println!("Checkmate!!")
+    fn square(coords: &str) -> Square {
+        Square::from_coords(coords).unwrap()
     }
 
-    let winner = if white_lost { "Red" } else { "Blue" };
-    println!("Congratulations {}!!", winner);
+    fn squares(coords: &[&str]) -> Vec<Square> {
+        coords.iter().map(|coords| square(coords)).collect()
+    }
 
-    println!("Thanks for playing!!")
+    #[test]
+    fn reads_typed_squares() {
+        assert_eq!(square("a1"), Square::new(0, 0));
+        assert_eq!(square("h8"), Square::new(7, 7));
+        for coords in ["a0", "a9", "i1", "e", "e22", ""] {
+            assert!(Square::from_coords(coords).is_err(), "{coords}");
+        }
+    }
+
+    #[test]
+    fn selection_shows_where_a_piece_can_go() {
+        let game = Game::init();
+        let highlight = Highlight::default().select(&game, square("b1"));
+        assert_eq!(highlight.selected, Some(square("b1")));
+        let mut targets = highlight.targets;
+        targets.sort_by_key(|square| square.index());
+        assert_eq!(targets, squares(&["a3", "c3"]));
+        // a pinned piece and an empty square have nowhere to go
+        let pinned = Game::from_fen("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert!(Highlight::default()
+            .select(&pinned, square("e2"))
+            .targets
+            .is_empty());
+        assert!(Highlight::default()
+            .select(&game, square("e4"))
+            .targets
+            .is_empty());
+    }
+
+    #[test]
+    fn position_marks_the_last_move_and_a_checked_king() {
+        let quiet = Highlight::position(&Game::init(), None);
+        assert_eq!((quiet.last_move, quiet.check), (None, None));
+
+        let mate = Game::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+            .unwrap();
+        let last_move = Move::from_coords("d8h4").ok();
+        let highlight = Highlight::position(&mate, last_move);
+        assert_eq!(highlight.last_move, last_move);
+        assert_eq!(highlight.check, Some(square("e1")));
+        assert_eq!(highlight.selected, None);
+        // selecting keeps both marks
+        let highlight = highlight.select(&mate, square("e1"));
+        assert_eq!(highlight.check, Some(square("e1")));
+        assert!(highlight.targets.is_empty());
+    }
 }
@@ -0,0 +1,272 @@
+use crate::eval::{capture_gain, evaluate};
+use crate::moves::Move;
+use crate::random::Rng;
+use crate::Game;
+use std::time::{Duration, Instant};
+
+const DEFAULT_EXPLORATION: f64 = 1.4;
+// used when neither the player nor the caller sets a budget
+const DEFAULT_ITERATIONS: u32 = 2000;
+// playouts stop here and the static evaluation, read as an expected score, decides them
+const PLAYOUT_PLIES: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Playout {
+    // uniformly random legal moves
+    Random,
+    // the best capture or promotion by victim value when there is one, otherwise random
+    Light,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MctsConfig {
+    pub iterations: Option<u32>,
+    pub time: Option<Duration>,
+    pub playout: Playout,
+    pub exploration: f64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> MctsConfig {
+        MctsConfig {
+            iterations: None,
+            time: None,
+            playout: Playout::Light,
+            exploration: DEFAULT_EXPLORATION,
+        }
+    }
+}
+
+struct Node {
+    game: Game,
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Move>,
+    visits: u32,
+    // summed results for the side that played `mv`: 1 a win, 0.5 a draw
+    score: f64,
+}
+
+impl Node {
+    fn new(game: Game, mv: Option<Move>, parent: Option<usize>) -> Node {
+        Node {
+            game,
+            mv,
+            parent,
+            children: vec![],
+            untried: game.legal_moves(),
+            visits: 0,
+            score: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MctsResult {
+    pub best_move: Option<Move>,
+    // the expected score of the best move for the side to move
+    pub win_rate: f64,
+}
+
+impl MctsResult {
+    // the win rate as a centipawn score, so match adjudication can treat it like a search score
+    pub fn score(&self) -> i32 {
+        let rate = self.win_rate.clamp(0.001, 0.999);
+        (-400.0 * (1.0 / rate - 1.0).log10()).round() as i32
+    }
+}
+
+// UCT: Monte Carlo tree search that picks children by their upper confidence bound
+pub struct Mcts {
+    config: MctsConfig,
+    rng: Rng,
+}
+
+impl Mcts {
+    pub fn new(config: MctsConfig, rng: Rng) -> Mcts {
+        Mcts { config, rng }
+    }
+    // the player's own budget wins over `time`, which callers pass from their move time
+    pub fn search(&mut self, game: &Game, time: Option<Duration>) -> MctsResult {
+        let start = Instant::now();
+        let (iteration_limit, time_limit) = match (self.config.iterations, self.config.time) {
+            (None, None) => (time.is_none().then_some(DEFAULT_ITERATIONS), time),
+            budget => budget,
+        };
+        let mut nodes = vec![Node::new(*game, None, None)];
+        let mut iterations = 0;
+        while iteration_limit.is_none_or(|limit| iterations < limit)
+            && time_limit.is_none_or(|limit| start.elapsed() < limit)
+        {
+            let leaf = self.select_and_expand(&mut nodes);
+            let result = self.playout(nodes[leaf].game);
+            let mut current = Some(leaf);
+            while let Some(index) = current {
+                let node = &mut nodes[index];
+                node.visits += 1;
+                // `result` is white's; a node is scored for the side that moved into it
+                node.score += if node.game.is_white_turn() {
+                    1.0 - result
+                } else {
+                    result
+                };
+                current = node.parent;
+            }
+            iterations += 1;
+        }
+
+        let best = nodes[0]
+            .children
+            .iter()
+            .max_by_key(|child| nodes[**child].visits)
+            .map(|child| &nodes[*child]);
+        MctsResult {
+            best_move: best.and_then(|node| node.mv),
+            win_rate: best.map_or(0.5, |node| node.score / node.visits.max(1) as f64),
+        }
+    }
+    fn select_and_expand(&mut self, nodes: &mut Vec<Node>) -> usize {
+        let mut index = 0;
+        while nodes[index].untried.is_empty() && !nodes[index].children.is_empty() {
+            let parent_visits = (nodes[index].visits.max(1) as f64).ln();
+            let exploration = self.config.exploration;
+            let uct = |child: &Node| {
+                child.score / child.visits as f64
+                    + exploration * (parent_visits / child.visits as f64).sqrt()
+            };
+            index = *nodes[index]
+                .children
+                .iter()
+                .max_by(|a, b| uct(&nodes[**a]).total_cmp(&uct(&nodes[**b])))
+                .unwrap();
+        }
+        if nodes[index].untried.is_empty() {
+            return index;
+        }
+        let pick = self.rng.below(nodes[index].untried.len());
+        let mv = nodes[index].untried.swap_remove(pick);
+        let mut game = nodes[index].game;
+        game.make_legal_move(mv);
+        nodes.push(Node::new(game, Some(mv), Some(index)));
+        let child = nodes.len() - 1;
+        nodes[index].children.push(child);
+        child
+    }
+    fn playout_move(&mut self, game: &Game, moves: &[Move]) -> Move {
+        if self.config.playout == Playout::Light {
+            let gain = |mv: &Move| capture_gain(game, *mv);
+            let best_gain = moves.iter().map(gain).max().unwrap_or(0);
+            if best_gain > 0 {
+                let best: Vec<Move> = moves
+                    .iter()
+                    .filter(|mv| gain(mv) == best_gain)
+                    .copied()
+                    .collect();
+                return self.rng.choose(&best).unwrap();
+            }
+        }
+        self.rng.choose(moves).unwrap()
+    }
+    // plays the position out and returns the result for white: 1 a win, 0.5 a draw, 0 a loss
+    fn playout(&mut self, mut game: Game) -> f64 {
+        for _ in 0..PLAYOUT_PLIES {
+            let moves = game.legal_moves();
+            if moves.is_empty() {
+                return match (game.in_check(), game.is_white_turn()) {
+                    (true, true) => 0.0,
+                    (true, false) => 1.0,
+                    (false, _) => 0.5,
+                };
+            }
+            if game.insufficient_material() {
+                return 0.5;
+            }
+            let mv = self.playout_move(&game, &moves);
+            game.make_legal_move(mv);
+        }
+        let score = if game.is_white_turn() {
+            evaluate(&game)
+        } else {
+            -evaluate(&game)
+        };
+        1.0 / (1.0 + 10f64.powf(-score as f64 / 400.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(fen: &str, playout: Playout, iterations: u32) -> MctsResult {
+        let config = MctsConfig {
+            iterations: Some(iterations),
+            playout,
+            ..MctsConfig::default()
+        };
+        Mcts::new(config, Rng::with_seed(1)).search(&Game::from_fen(fen).unwrap(), None)
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        for playout in [Playout::Random, Playout::Light] {
+            let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", playout, 400);
+            assert_eq!(result.best_move, Move::from_coords("a1a8").ok());
+            assert!(result.win_rate > 0.9, "{}", result.win_rate);
+        }
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+        let result = search("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", Playout::Light, 1500);
+        assert_eq!(result.best_move, Move::from_coords("d2d5").ok());
+        assert!(result.score() > 0);
+    }
+
+    #[test]
+    fn a_seed_repeats_the_search() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1";
+        let first = search(fen, Playout::Light, 300);
+        let second = search(fen, Playout::Light, 300);
+        assert_eq!(first.best_move, second.best_move);
+        assert_eq!(first.win_rate, second.win_rate);
+    }
+
+    #[test]
+    fn finished_games_have_no_move() {
+        let result = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", Playout::Light, 100);
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.win_rate, 0.5);
+    }
+
+    #[test]
+    fn playouts_score_mates_and_draws_for_white() {
+        let mut mcts = Mcts::new(MctsConfig::default(), Rng::with_seed(4));
+        let position = |fen: &str| Game::from_fen(fen).unwrap();
+        assert_eq!(
+            mcts.playout(position("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1")),
+            1.0
+        );
+        assert_eq!(
+            mcts.playout(position("6k1/8/8/8/8/8/5PPP/r5K1 w - - 0 1")),
+            0.0
+        );
+        assert_eq!(
+            mcts.playout(position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")),
+            0.5
+        );
+        assert_eq!(mcts.playout(position("7k/8/8/8/8/8/8/5NK1 w - - 0 1")), 0.5);
+    }
+
+    #[test]
+    fn win_rates_convert_to_centipawns() {
+        let result = |win_rate: f64| MctsResult {
+            best_move: None,
+            win_rate,
+        };
+        assert_eq!(result(0.5).score(), 0);
+        assert_eq!(result(10.0 / 11.0).score(), 400);
+        assert_eq!(result(1.0 / 11.0).score(), -400);
+        assert_eq!(result(1.0).score(), result(0.999).score());
+    }
+}
@@ -0,0 +1,427 @@
+use crate::PieceType::{self, *};
+use crate::{Game, Square, Team};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+        }
+    }
+    pub fn with_promotion(from: Square, to: Square, promotion: PieceType) -> Move {
+        Move {
+            from,
+            to,
+            promotion: Some(promotion),
+        }
+    }
+    // long algebraic coordinates as used by UCI, e.g. "e2e4" or "e7e8q"
+    pub fn from_coords(coords: &str) -> Result<Move, String> {
+        if !coords.is_ascii() || !(4..=5).contains(&coords.len()) {
+            return Err("Error: Invalid Move".to_string());
+        }
+        let from = Square::from_coords(&coords[0..2])?;
+        let to = Square::from_coords(&coords[2..4])?;
+        let promotion = match coords.get(4..5) {
+            None => None,
+            Some("q") => Some(Queen),
+            Some("r") => Some(Rook),
+            Some("b") => Some(Bishop),
+            Some("n") => Some(Knight),
+            Some(_) => return Err("Error: Invalid Promotion".to_string()),
+        };
+        Ok(Move {
+            from,
+            to,
+            promotion,
+        })
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let promotion = match self.promotion {
+            Some(Queen) => "q",
+            Some(Rook) => "r",
+            Some(Bishop) => "b",
+            Some(Knight) => "n",
+            _ => "",
+        };
+        write!(
+            f,
+            "{}{}{}",
+            self.from.to_coords(),
+            self.to.to_coords(),
+            promotion
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Checkmate { winner: Team },
+    Stalemate,
+}
+
+// positions since the last capture or pawn move, for repetitions and the fifty-move rule
+#[derive(Debug, Clone)]
+pub struct PositionHistory {
+    positions: Vec<u64>,
+}
+
+impl PositionHistory {
+    pub fn new(game: &Game) -> PositionHistory {
+        PositionHistory {
+            positions: vec![game.hash()],
+        }
+    }
+    // plays a legal move on `game` and remembers where it led
+    pub fn make_move(&mut self, game: &mut Game, mv: Move) {
+        let irreversible = game.is_capture(mv) || game.get_piece(mv.from).unwrap().is_pawn();
+        game.make_legal_move(mv);
+        if irreversible {
+            self.positions.clear();
+        }
+        self.positions.push(game.hash());
+    }
+    // the current position has been seen three times
+    pub fn threefold(&self) -> bool {
+        let current = self.positions.last().unwrap();
+        self.positions
+            .iter()
+            .filter(|hash| *hash == current)
+            .count()
+            >= 3
+    }
+    // fifty moves by each side without a capture or pawn move
+    pub fn fifty_moves(&self) -> bool {
+        self.positions.len() > 100
+    }
+}
+
+impl Game {
+    // `None` while the side to move still has a legal move
+    pub fn outcome(&self) -> Option<Outcome> {
+        if !self.legal_moves().is_empty() {
+            return None;
+        }
+        if self.in_check() {
+            let mut winner = *self;
+            winner.switch_turn();
+            Some(Outcome::Checkmate {
+                winner: winner.turn(),
+            })
+        } else {
+            Some(Outcome::Stalemate)
+        }
+    }
+    // neither side can ever mate: bare kings, or one knight or bishop beside them
+    pub fn insufficient_material(&self) -> bool {
+        let mut minor_pieces = 0;
+        for piece in self.board.iter().flatten().flatten() {
+            match piece.piece_type() {
+                King => {}
+                Knight | Bishop => minor_pieces += 1,
+                _ => return false,
+            }
+        }
+        minor_pieces <= 1
+    }
+    // whether `team` could still mate by any series of legal moves, the test for a win on time
+    // (FIDE 6.9): a lone knight or bishops on one colour need the opponent's own men to box
+    // its king in, which same-coloured bishops never do
+    pub fn has_mating_material(&self, team: Team) -> bool {
+        let mut knights = 0;
+        // whether `team` has a bishop on the light and on the dark squares
+        let mut bishops = [false; 2];
+        let mut blockers = vec![];
+        for (rank, row) in self.board.iter().enumerate() {
+            for (file, piece) in row.iter().enumerate() {
+                let Some(piece) = piece.filter(|piece| !piece.is_king()) else {
+                    continue;
+                };
+                let colour = (rank + file) % 2;
+                if piece.team() != team {
+                    blockers.push((piece.piece_type(), colour));
+                    continue;
+                }
+                match piece.piece_type() {
+                    Knight => knights += 1,
+                    Bishop => bishops[colour] = true,
+                    _ => return true,
+                }
+            }
+        }
+        match (knights, bishops) {
+            (0, [false, false]) => false,
+            (0, [true, false]) | (0, [false, true]) => {
+                let colour = bishops[1] as usize;
+                blockers.iter().any(|blocker| *blocker != (Bishop, colour))
+            }
+            (1, [false, false]) => !blockers.is_empty(),
+            _ => true,
+        }
+    }
+    pub fn in_check(&self) -> bool {
+        if self.is_white_turn() {
+            self.white_in_check()
+        } else {
+            self.black_in_check()
+        }
+    }
+    pub fn is_capture(&self, mv: Move) -> bool {
+        self.get_piece(mv.to).is_some() || self.is_en_passant(mv)
+    }
+    pub fn is_en_passant(&self, mv: Move) -> bool {
+        let piece = self.get_piece(mv.from);
+        piece.is_some()
+            && piece.unwrap().is_pawn()
+            && self.get_piece(mv.to).is_none()
+            && mv.from.file() != mv.to.file()
+    }
+    pub fn captured_piece(&self, mv: Move) -> Option<PieceType> {
+        if self.is_en_passant(mv) {
+            return Some(Pawn);
+        }
+        self.get_piece(mv.to).map(|piece| piece.piece_type())
+    }
+    // every move the side to move can play, with promotions expanded and
+    // castling through check filtered out the same way `move_piece` does
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = vec![];
+        let last_rank = if self.is_white_turn() { 7 } else { 0 };
+        for start_sq in self.find_team_pieces(self.turn()) {
+            let piece = self.get_piece(start_sq).unwrap();
+            for target_sq in self.get_legal_moves(start_sq) {
+                if piece.is_king()
+                    && start_sq.file_diff(target_sq) > 1
+                    && self.clone_and_move(start_sq, target_sq).is_err()
+                {
+                    continue;
+                }
+                if piece.is_pawn() && target_sq.rank() == last_rank {
+                    for promotion in [Queen, Knight, Rook, Bishop] {
+                        moves.push(Move::with_promotion(start_sq, target_sq, promotion));
+                    }
+                } else {
+                    moves.push(Move::new(start_sq, target_sq));
+                }
+            }
+        }
+        moves
+    }
+    fn clone_and_move(&self, start_sq: Square, target_sq: Square) -> Result<Game, String> {
+        let mut game = *self;
+        game.move_piece(start_sq, target_sq)?;
+        Ok(game)
+    }
+    // plays a full move for the side to move, including promotion and passing the turn
+    pub fn make_move(&mut self, mv: Move) -> Result<(), String> {
+        self.move_piece(mv.from, mv.to)?;
+        self.finish_move(mv);
+        Ok(())
+    }
+    // same as `make_move` for moves taken straight from `legal_moves`
+    pub fn make_legal_move(&mut self, mv: Move) {
+        self.execute_move(mv.from, mv.to);
+        self.finish_move(mv);
+    }
+    // a move a player picked by its squares, tried on a copy so an illegal one comes back with
+    // the specific reason, e.g. castling through check, and the position is left alone
+    pub fn try_move(&self, mv: Move) -> Result<Move, String> {
+        let mut test_game = *self;
+        test_game.make_move(mv).map(|_| mv)
+    }
+    fn finish_move(&mut self, mv: Move) {
+        if let Some(pawn_file) = self.last_rank_pawn_index() {
+            self.replace_last_rank_pawn(pawn_file, mv.promotion.unwrap_or(Queen));
+        }
+        self.switch_turn();
+    }
+    // resolves a from/to pair typed by a player against the legal move list
+    pub fn find_move(&self, coords: &str) -> Result<Move, String> {
+        let wanted = Move::from_coords(coords)?;
+        self.legal_moves()
+            .into_iter()
+            .find(|mv| {
+                mv.from == wanted.from
+                    && mv.to == wanted.to
+                    && (mv.promotion.is_none() || mv.promotion == wanted.promotion.or(Some(Queen)))
+            })
+            .ok_or_else(|| "Error: Invalid Move".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn history_counts_repetitions_and_quiet_moves() {
+        let mut game = Game::init();
+        let mut history = PositionHistory::new(&game);
+        let knights =
+            ["g1f3", "g8f6", "f3g1", "f6g8"].map(|coords| Move::from_coords(coords).unwrap());
+        for (ply, mv) in knights.iter().cycle().take(100).enumerate() {
+            // every position comes back every four plies
+            assert_eq!(history.threefold(), ply >= 8, "{ply}");
+            assert!(!history.fifty_moves());
+            history.make_move(&mut game, *mv);
+        }
+        assert!(history.fifty_moves());
+        history.make_move(&mut game, Move::from_coords("e2e4").unwrap());
+        assert!(!history.fifty_moves() && !history.threefold());
+    }
+
+    fn perft(game: &Game, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        game.legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let mut child = *game;
+                child.make_legal_move(mv);
+                perft(&child, depth - 1)
+            })
+            .sum()
+    }
+
+    fn position(fen: &str) -> Game {
+        Game::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn perft_start_position() {
+        let game = Game::init();
+        assert_eq!(perft(&game, 1), 20);
+        assert_eq!(perft(&game, 2), 400);
+        assert_eq!(perft(&game, 3), 8902);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let game = position(KIWIPETE);
+        assert_eq!(perft(&game, 1), 48);
+        assert_eq!(perft(&game, 2), 2039);
+        assert_eq!(perft(&game, 3), 97862);
+    }
+
+    #[test]
+    fn perft_en_passant_and_pins() {
+        let game = position("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+        assert_eq!(perft(&game, 1), 14);
+        assert_eq!(perft(&game, 2), 191);
+        assert_eq!(perft(&game, 3), 2812);
+    }
+
+    #[test]
+    fn perft_promotions() {
+        let game = position("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8");
+        assert_eq!(perft(&game, 1), 44);
+        assert_eq!(perft(&game, 2), 1486);
+    }
+
+    #[test]
+    fn queenside_castling_moves_king_to_c_file_and_rook_to_d_file() {
+        let mut game = position(KIWIPETE);
+        game.make_move(Move::from_coords("e1c1").unwrap()).unwrap();
+        let king = game.get_piece(Square::new(0, 2)).unwrap();
+        let rook = game.get_piece(Square::new(0, 3)).unwrap();
+        assert!(king.is_king());
+        assert_eq!(rook.piece_type(), Rook);
+        assert!(game.get_piece(Square::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn captured_corner_rook_loses_castling_right() {
+        // the bishop takes the rook on h8, so Black may not castle kingside afterwards
+        let mut game = position("r3k2r/8/8/8/8/8/8/B3K3 w kq - 0 1");
+        let mut after = game;
+        after.make_move(Move::from_coords("a1b2").unwrap()).unwrap();
+        game.make_move(Move::from_coords("a1h8").unwrap()).unwrap();
+        let castles = |game: &Game| {
+            game.legal_moves()
+                .into_iter()
+                .filter(|mv| mv.from == Square::new(7, 4) && mv.from.file_diff(mv.to) == 2)
+                .count()
+        };
+        assert_eq!(castles(&after), 2);
+        assert_eq!(castles(&game), 1);
+    }
+
+    #[test]
+    fn checkmate_and_stalemate_outcomes() {
+        let mate = position("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        assert_eq!(
+            mate.outcome(),
+            Some(Outcome::Checkmate {
+                winner: Team::Black
+            })
+        );
+        let stalemate = position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(stalemate.outcome(), Some(Outcome::Stalemate));
+        assert_eq!(Game::init().outcome(), None);
+    }
+
+    #[test]
+    fn insufficient_material() {
+        assert!(position("8/8/4k3/8/8/3NK3/8/8 w - - 0 1").insufficient_material());
+        assert!(!position("8/8/4k3/8/8/3RK3/8/8 w - - 0 1").insufficient_material());
+    }
+
+    #[test]
+    fn mating_material_allows_helpmates() {
+        let mates = |fen: &str| position(fen).has_mating_material(Team::White);
+        assert!(mates("4k3/8/8/8/8/8/8/3RK3 w - - 0 1"));
+        assert!(mates("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        assert!(!mates("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!mates("4k3/8/8/8/8/8/8/3NK3 w - - 0 1"));
+        // a lone minor piece can mate a king boxed in by its own men
+        assert!(mates("4k3/4p3/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(mates("4k3/4p3/8/8/8/8/8/3NK3 w - - 0 1"));
+        assert!(mates("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        // except by bishops that all share one colour
+        assert!(!mates("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1"));
+        assert!(!mates("4k3/8/8/8/8/4B3/8/2B1K3 w - - 0 1"));
+        assert!(mates("4k3/8/8/8/8/4B3/8/2B1KB2 w - - 0 1"));
+        assert!(mates("4k3/8/8/8/8/8/8/2BNK3 w - - 0 1"));
+        assert!(mates("4k3/8/8/8/8/8/8/1N1NK3 w - - 0 1"));
+        assert!(!position("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").has_mating_material(Team::Black));
+    }
+
+    #[test]
+    fn tried_moves_explain_themselves() {
+        let game = position("4k3/8/8/8/8/8/8/4K2R w K - 0 1");
+        let castle = Move::from_coords("e1g1").unwrap();
+        assert_eq!(game.try_move(castle), Ok(castle));
+        let through_check = position("4kr2/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert_eq!(
+            through_check.try_move(castle),
+            Err("You cannot castle through check".to_string())
+        );
+        assert_eq!(through_check.fen(), "4kr2/8/8/8/8/8/8/4K2R w K - 0 1");
+        assert!(game.try_move(Move::from_coords("e1e3").unwrap()).is_err());
+    }
+
+    #[test]
+    fn move_coordinates_round_trip() {
+        for coords in ["e2e4", "a7a8q", "h2h1n"] {
+            assert_eq!(Move::from_coords(coords).unwrap().to_string(), coords);
+        }
+        assert!(Move::from_coords("e2e9").is_err());
+        assert!(Move::from_coords("a7a8k").is_err());
+        assert!(Move::from_coords("e0e4").is_err());
+    }
+}
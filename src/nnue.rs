@@ -0,0 +1,294 @@
+use crate::eval::{piece_index, MATE_THRESHOLD};
+use crate::Team::{self, *};
+use crate::{Game, Piece};
+use std::fs;
+
+// one input per piece kind and square, seen from each side: 2 teams x 6 pieces x 64 squares
+const INPUTS: usize = 768;
+const MAX_HIDDEN: usize = 4096;
+// quantisation of the feature layer and the output layer, and the centipawn scale
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i32 = 400;
+
+// a 768 -> N x 2 -> 1 network with a clipped ReLU, stored as headerless little-endian i16s:
+// feature weights [768][N], feature biases [N], output weights [2N] (side to move's half
+// first), then the output bias, optionally padded to a multiple of 64 bytes
+pub struct Network {
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+// the hidden layer before activation, kept for both perspectives so a move only has to add
+// and remove the columns of the pieces it touched
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Accumulator {
+    white: Vec<i16>,
+    black: Vec<i16>,
+}
+
+// inputs are relative to the perspective: its own pieces come first, and black sees the
+// board with the ranks flipped
+fn feature(perspective: Team, piece: Piece, square: usize) -> usize {
+    let (theirs, square) = match perspective {
+        White => (piece.team() != White, square),
+        Black => (piece.team() != Black, square ^ 56),
+    };
+    theirs as usize * 384 + piece_index(piece.piece_type()) * 64 + square
+}
+
+impl Network {
+    pub fn load(path: &str) -> Result<Network, String> {
+        let bytes = fs::read(path).map_err(|err| format!("Error: Could not read {path}: {err}"))?;
+        let words_for = |hidden: usize| INPUTS * hidden + hidden + 2 * hidden + 1;
+        let hidden = (1..=MAX_HIDDEN)
+            .find(|hidden| {
+                let size = 2 * words_for(*hidden);
+                bytes.len() >= size && bytes.len() - size < 64
+            })
+            .ok_or_else(|| {
+                format!(
+                    "Error: {path} is not a network file ({} bytes)",
+                    bytes.len()
+                )
+            })?;
+        let mut words = bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]));
+        let mut take = |count: usize| words.by_ref().take(count).collect::<Vec<i16>>();
+        Ok(Network {
+            hidden,
+            feature_weights: take(INPUTS * hidden),
+            feature_biases: take(hidden),
+            output_weights: take(2 * hidden),
+            output_bias: take(1)[0],
+        })
+    }
+    pub fn hidden_size(&self) -> usize {
+        self.hidden
+    }
+    fn column(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden..][..self.hidden]
+    }
+    fn apply(&self, accumulator: &mut Accumulator, piece: Piece, square: usize, added: bool) {
+        for (perspective, values) in [
+            (White, &mut accumulator.white),
+            (Black, &mut accumulator.black),
+        ] {
+            let column = self.column(feature(perspective, piece, square));
+            for (value, weight) in values.iter_mut().zip(column) {
+                // any file of the right size loads, so wrap rather than overflow on large
+                // weights; wrapping still lets updates match a refresh exactly
+                *value = if added {
+                    value.wrapping_add(*weight)
+                } else {
+                    value.wrapping_sub(*weight)
+                };
+            }
+        }
+    }
+    // the accumulator built from scratch
+    pub fn refresh(&self, game: &Game) -> Accumulator {
+        let mut accumulator = Accumulator {
+            white: self.feature_biases.clone(),
+            black: self.feature_biases.clone(),
+        };
+        for (rank, row) in game.board.iter().enumerate() {
+            for (file, piece) in row.iter().enumerate() {
+                if let Some(piece) = piece {
+                    self.apply(&mut accumulator, *piece, rank * 8 + file, true);
+                }
+            }
+        }
+        accumulator
+    }
+    // `after`'s accumulator from `before`'s, touching only the squares that changed; comparing
+    // boards covers castling, en passant and promotion without special cases
+    pub fn update(
+        &self,
+        previous: &Accumulator,
+        next: &mut Accumulator,
+        before: &Game,
+        after: &Game,
+    ) {
+        next.clone_from(previous);
+        for rank in 0..8 {
+            for file in 0..8 {
+                let (old, new) = (before.board[rank][file], after.board[rank][file]);
+                if old == new {
+                    continue;
+                }
+                if let Some(piece) = old {
+                    self.apply(next, piece, rank * 8 + file, false);
+                }
+                if let Some(piece) = new {
+                    self.apply(next, piece, rank * 8 + file, true);
+                }
+            }
+        }
+    }
+    // centipawns from the side to move's point of view
+    pub fn evaluate(&self, accumulator: &Accumulator, turn: Team) -> i32 {
+        let (ours, theirs) = match turn {
+            White => (&accumulator.white, &accumulator.black),
+            Black => (&accumulator.black, &accumulator.white),
+        };
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden);
+        let activate = |values: &[i16], weights: &[i16]| -> i64 {
+            values
+                .iter()
+                .zip(weights)
+                .map(|(value, weight)| (*value as i32).clamp(0, QA) as i64 * *weight as i64)
+                .sum()
+        };
+        let output = activate(ours, our_weights) + activate(theirs, their_weights);
+        let score = (output + self.output_bias as i64) * SCALE as i64 / (QA * QB) as i64;
+        // extreme weights must not pass for a mate
+        score.clamp(-(MATE_THRESHOLD as i64 - 1), MATE_THRESHOLD as i64 - 1) as i32
+    }
+}
+
+// `--nnue-check <network> <reference>`: the reference file has one `<fen> ; <score>` per line,
+// with scores precomputed outside the engine; every legal move from each position is also
+// checked to update the accumulator exactly as a refresh would
+pub fn run_check(args: &[String]) -> Result<(), String> {
+    let (Some(network_path), Some(reference_path)) = (args.first(), args.get(1)) else {
+        return Err("Error: Usage: --nnue-check <network> <reference>".to_string());
+    };
+    let network = Network::load(network_path)?;
+    let text = fs::read_to_string(reference_path)
+        .map_err(|err| format!("Error: Could not read {reference_path}: {err}"))?;
+    println!("{network_path}: 768 -> {}x2 -> 1", network.hidden_size());
+
+    let mut positions = 0;
+    let mut updates = 0;
+    let mut failures = 0;
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (fen, expected) = line
+            .rsplit_once(';')
+            .ok_or_else(|| format!("Error: Expected '<fen> ; <score>' on line {}", number + 1))?;
+        let expected: i32 = expected
+            .trim()
+            .parse()
+            .map_err(|_| format!("Error: Invalid score on line {}", number + 1))?;
+        let game = Game::from_fen(fen.trim())?;
+        let accumulator = network.refresh(&game);
+        let score = network.evaluate(&accumulator, game.turn());
+        positions += 1;
+        if score != expected {
+            failures += 1;
+            println!(
+                "line {}: expected {expected}, got {score}: {}",
+                number + 1,
+                fen.trim()
+            );
+        }
+        for mv in game.legal_moves() {
+            let mut child = game;
+            child.make_legal_move(mv);
+            let mut updated = Accumulator::default();
+            network.update(&accumulator, &mut updated, &game, &child);
+            updates += 1;
+            if updated != network.refresh(&child) {
+                failures += 1;
+                println!(
+                    "line {}: incremental update after {mv} differs from a refresh",
+                    number + 1
+                );
+            }
+        }
+    }
+    if failures > 0 {
+        return Err(format!("Error: {failures} mismatches"));
+    }
+    println!("All {positions} reference scores match, {updates} incremental updates checked");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net_path(name: &str) -> String {
+        format!("{}/nets/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn reference() -> Vec<(Game, i32)> {
+        fs::read_to_string(net_path("reference.txt"))
+            .unwrap()
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (fen, score) = line.rsplit_once(';').unwrap();
+                let game = Game::from_fen(fen.trim()).unwrap();
+                (game, score.trim().parse().unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_the_reference_scores() {
+        let network = Network::load(&net_path("reference.nnue")).unwrap();
+        assert_eq!(network.hidden_size(), 32);
+        let positions = reference();
+        assert_eq!(positions.len(), 10);
+        for (game, expected) in positions {
+            let score = network.evaluate(&network.refresh(&game), game.turn());
+            assert_eq!(score, expected, "{}", game.fen());
+        }
+    }
+
+    #[test]
+    fn updates_match_a_refresh() {
+        let network = Network::load(&net_path("reference.nnue")).unwrap();
+        for (game, _) in reference() {
+            let accumulator = network.refresh(&game);
+            for mv in game.legal_moves() {
+                let mut child = game;
+                child.make_legal_move(mv);
+                let mut updated = Accumulator::default();
+                network.update(&accumulator, &mut updated, &game, &child);
+                assert_eq!(updated, network.refresh(&child), "{} {mv}", game.fen());
+            }
+        }
+        let args = [net_path("reference.nnue"), net_path("reference.txt")];
+        assert!(run_check(&args).is_ok());
+    }
+
+    #[test]
+    fn rejects_files_of_the_wrong_size() {
+        let err = Network::load(&net_path("reference.txt")).err().unwrap();
+        assert!(err.contains("is not a network file"), "{err}");
+        assert!(Network::load(&net_path("missing.nnue")).is_err());
+    }
+
+    #[test]
+    fn extreme_weights_neither_overflow_nor_mate() {
+        let hidden = 8;
+        let network = Network {
+            hidden,
+            feature_weights: vec![i16::MAX; INPUTS * hidden],
+            feature_biases: vec![i16::MAX; hidden],
+            output_weights: vec![i16::MAX; 2 * hidden],
+            output_bias: i16::MAX,
+        };
+        let game = Game::init();
+        let accumulator = network.refresh(&game);
+        let score = network.evaluate(&accumulator, game.turn());
+        assert!(score.abs() < MATE_THRESHOLD, "{score}");
+        for mv in game.legal_moves() {
+            let mut child = game;
+            child.make_legal_move(mv);
+            let mut updated = Accumulator::default();
+            network.update(&accumulator, &mut updated, &game, &child);
+            assert_eq!(updated, network.refresh(&child));
+        }
+    }
+}
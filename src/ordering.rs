@@ -0,0 +1,232 @@
+use crate::eval::piece_value;
+use crate::moves::Move;
+use crate::Game;
+
+const HASH_MOVE_SCORE: i32 = 1_000_000;
+const CAPTURE_SCORE: i32 = 500_000;
+const FIRST_KILLER_SCORE: i32 = 400_000;
+const SECOND_KILLER_SCORE: i32 = 390_000;
+const COUNTER_MOVE_SCORE: i32 = 380_000;
+const HISTORY_MAX: i32 = 300_000;
+
+// which heuristics the orderer applies; each can be switched off to measure its effect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderingConfig {
+    pub hash_move: bool,
+    pub mvv_lva: bool,
+    pub killers: bool,
+    pub history: bool,
+    pub counter_moves: bool,
+}
+
+impl OrderingConfig {
+    pub fn all() -> OrderingConfig {
+        OrderingConfig {
+            hash_move: true,
+            mvv_lva: true,
+            killers: true,
+            history: true,
+            counter_moves: true,
+        }
+    }
+    pub fn none() -> OrderingConfig {
+        OrderingConfig {
+            hash_move: false,
+            mvv_lva: false,
+            killers: false,
+            history: false,
+            counter_moves: false,
+        }
+    }
+}
+
+impl Default for OrderingConfig {
+    fn default() -> OrderingConfig {
+        OrderingConfig::all()
+    }
+}
+
+// sorts the legal move list before the search walks it and learns from beta cutoffs
+pub struct MoveOrderer {
+    config: OrderingConfig,
+    killers: Vec<[Option<Move>; 2]>,
+    history: Vec<i32>,
+    counter_moves: Vec<Option<Move>>,
+}
+
+impl MoveOrderer {
+    pub fn new(config: OrderingConfig) -> MoveOrderer {
+        MoveOrderer {
+            config,
+            killers: vec![],
+            history: vec![0; 2 * 64 * 64],
+            counter_moves: vec![None; 64 * 64],
+        }
+    }
+    pub fn config(&self) -> OrderingConfig {
+        self.config
+    }
+    fn history_index(game: &Game, mv: Move) -> usize {
+        let team_index = if game.is_white_turn() { 0 } else { 1 };
+        team_index * 64 * 64 + mv.from.index() * 64 + mv.to.index()
+    }
+    fn counter_index(previous: Move) -> usize {
+        previous.from.index() * 64 + previous.to.index()
+    }
+    fn score_move(
+        &self,
+        game: &Game,
+        mv: Move,
+        hash_move: Option<Move>,
+        ply: usize,
+        previous: Option<Move>,
+    ) -> i32 {
+        if self.config.hash_move && hash_move == Some(mv) {
+            return HASH_MOVE_SCORE;
+        }
+        if self.config.mvv_lva && (game.is_capture(mv) || mv.promotion.is_some()) {
+            // most valuable victim first, least valuable attacker breaks ties
+            let victim = game.captured_piece(mv).map_or(0, piece_value);
+            let promotion = mv.promotion.map_or(0, piece_value);
+            let attacker = piece_value(game.get_piece(mv.from).unwrap().piece_type());
+            return CAPTURE_SCORE + (victim + promotion) * 10 - attacker / 10;
+        }
+        if self.config.killers {
+            if let Some(killers) = self.killers.get(ply) {
+                if killers[0] == Some(mv) {
+                    return FIRST_KILLER_SCORE;
+                }
+                if killers[1] == Some(mv) {
+                    return SECOND_KILLER_SCORE;
+                }
+            }
+        }
+        if self.config.counter_moves {
+            if let Some(previous) = previous {
+                if self.counter_moves[MoveOrderer::counter_index(previous)] == Some(mv) {
+                    return COUNTER_MOVE_SCORE;
+                }
+            }
+        }
+        if self.config.history {
+            return self.history[MoveOrderer::history_index(game, mv)];
+        }
+        0
+    }
+    pub fn order_moves(
+        &self,
+        game: &Game,
+        moves: &mut [Move],
+        hash_move: Option<Move>,
+        ply: usize,
+        previous: Option<Move>,
+    ) {
+        // stable sort keeps generator order for equal scores, so runs are reproducible
+        let mut scored: Vec<(i32, Move)> = moves
+            .iter()
+            .map(|mv| (self.score_move(game, *mv, hash_move, ply, previous), *mv))
+            .collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        for (slot, (_, mv)) in moves.iter_mut().zip(scored) {
+            *slot = mv;
+        }
+    }
+    // called when `mv` caused a beta cutoff; captures are already ordered well by MVV-LVA
+    pub fn record_cutoff(
+        &mut self,
+        game: &Game,
+        mv: Move,
+        ply: usize,
+        depth: u32,
+        previous: Option<Move>,
+    ) {
+        if game.is_capture(mv) || mv.promotion.is_some() {
+            return;
+        }
+        if self.config.killers {
+            if self.killers.len() <= ply {
+                self.killers.resize(ply + 1, [None, None]);
+            }
+            let killers = &mut self.killers[ply];
+            if killers[0] != Some(mv) {
+                killers[1] = killers[0];
+                killers[0] = Some(mv);
+            }
+        }
+        if self.config.history {
+            let index = MoveOrderer::history_index(game, mv);
+            self.history[index] += (depth * depth) as i32;
+            if self.history[index] >= HISTORY_MAX {
+                self.history.iter_mut().for_each(|score| *score /= 2);
+            }
+        }
+        if self.config.counter_moves {
+            if let Some(previous) = previous {
+                self.counter_moves[MoveOrderer::counter_index(previous)] = Some(mv);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves_of(game: &Game) -> Vec<Move> {
+        game.legal_moves()
+    }
+
+    #[test]
+    fn hash_move_comes_first() {
+        let game = Game::init();
+        let hash_move = Move::from_coords("g2g3").unwrap();
+        let mut moves = moves_of(&game);
+        MoveOrderer::new(OrderingConfig::all()).order_moves(
+            &game,
+            &mut moves,
+            Some(hash_move),
+            0,
+            None,
+        );
+        assert_eq!(moves[0], hash_move);
+    }
+
+    #[test]
+    fn most_valuable_victim_is_tried_first() {
+        // the knight can take a pawn or the queen
+        let game = Game::from_fen("4k3/8/2p1q3/8/3N4/8/8/7K w - - 0 1").unwrap();
+        let mut moves = moves_of(&game);
+        MoveOrderer::new(OrderingConfig::all()).order_moves(&game, &mut moves, None, 0, None);
+        assert_eq!(moves[0], Move::from_coords("d4e6").unwrap());
+        assert_eq!(moves[1], Move::from_coords("d4c6").unwrap());
+    }
+
+    #[test]
+    fn killer_and_counter_moves_are_remembered() {
+        let game = Game::init();
+        let killer = Move::from_coords("b1a3").unwrap();
+        let counter = Move::from_coords("h2h3").unwrap();
+        let previous = Move::from_coords("e7e5").unwrap();
+        let mut orderer = MoveOrderer::new(OrderingConfig::all());
+        orderer.record_cutoff(&game, killer, 2, 3, None);
+        orderer.record_cutoff(&game, counter, 5, 3, Some(previous));
+
+        let mut moves = moves_of(&game);
+        orderer.order_moves(&game, &mut moves, None, 2, None);
+        assert_eq!(moves[0], killer);
+        let mut moves = moves_of(&game);
+        orderer.order_moves(&game, &mut moves, None, 4, Some(previous));
+        assert_eq!(moves[0], counter);
+    }
+
+    #[test]
+    fn no_ordering_keeps_generator_order() {
+        let game = Game::init();
+        let mut moves = moves_of(&game);
+        let generated = moves.clone();
+        let mut orderer = MoveOrderer::new(OrderingConfig::none());
+        orderer.record_cutoff(&game, generated[5], 0, 4, None);
+        orderer.order_moves(&game, &mut moves, Some(generated[3]), 0, None);
+        assert_eq!(moves, generated);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::clock::Clock;
+use crate::eval::{
+    self, capture_gain, piece_value, EvalParams, KING_ATTACK, KING_SHIELD, MATERIAL,
+    MATE_THRESHOLD, MOBILITY, PIECE_SQUARE,
+};
+use crate::external_engine::EngineLimit;
+use crate::moves::Move;
+use crate::ordering::OrderingConfig;
+use crate::player::{clock_limit, Action, Player};
+use crate::record::GameRecord;
+use crate::search::Searcher;
+use crate::Game;
+use crate::PieceType::*;
+use std::sync::Arc;
+
+// how many of the best root moves a bot weighs against its taste
+const CANDIDATE_LINES: usize = 4;
+// gambits are an opening matter
+const GAMBIT_PLIES: usize = 20;
+
+// a playing style: weights for the evaluation as percentages of the usual ones, and a taste
+// for some kinds of moves that may cost up to `margin` centipawns against the best one
+pub struct Personality {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pawn: i32,
+    pieces: i32,
+    mobility: i32,
+    king_shield: i32,
+    king_attack: i32,
+    margin: i32,
+    taste: fn(&Game, Move, usize) -> i32,
+    // takes a draw when its own score is at most this
+    draw_threshold: Option<i32>,
+}
+
+pub const PERSONALITIES: [Personality; 4] = [
+    Personality {
+        key: "attacker",
+        name: "Attila",
+        description: "an aggressive attacker who values open lines and king hunts over material \
+                      and never turns down a chance to give check",
+        pawn: 100,
+        pieces: 100,
+        mobility: 200,
+        king_shield: 100,
+        king_attack: 400,
+        margin: 60,
+        taste: attacking_taste,
+        draw_threshold: None,
+    },
+    Personality {
+        key: "defender",
+        name: "Petra",
+        description: "a solid defender who keeps the king sheltered, trades pieces whenever \
+                      possible and is happy to split the point",
+        pawn: 100,
+        pieces: 100,
+        mobility: 50,
+        king_shield: 300,
+        king_attack: 150,
+        margin: 40,
+        taste: solid_taste,
+        draw_threshold: Some(20),
+    },
+    Personality {
+        key: "glutton",
+        name: "Gus",
+        description: "a material-hungry grabber who takes whatever is on offer and worries \
+                      about the consequences later",
+        pawn: 130,
+        pieces: 130,
+        mobility: 50,
+        king_shield: 100,
+        king_attack: 50,
+        margin: 100,
+        taste: hungry_taste,
+        draw_threshold: Some(-100),
+    },
+    Personality {
+        key: "gambiteer",
+        name: "Morphy Jr.",
+        description: "a romantic gambiteer who gives up pawns in the opening for development \
+                      and the initiative",
+        pawn: 70,
+        pieces: 100,
+        mobility: 200,
+        king_shield: 100,
+        king_attack: 200,
+        margin: 80,
+        taste: gambit_taste,
+        draw_threshold: None,
+    },
+];
+
+fn gives_check(game: &Game, mv: Move) -> bool {
+    let mut after = *game;
+    after.make_legal_move(mv);
+    after.in_check()
+}
+
+fn attacking_taste(game: &Game, mv: Move, _ply: usize) -> i32 {
+    let mut enemy = *game;
+    enemy.switch_turn();
+    let king = game.find_king(enemy.turn());
+    let near_king =
+        mv.to.rank().abs_diff(king.rank()) <= 2 && mv.to.file().abs_diff(king.file()) <= 2;
+    let check_bonus = if gives_check(game, mv) { 40 } else { 0 };
+    check_bonus + if near_king { 15 } else { 0 }
+}
+
+fn solid_taste(game: &Game, mv: Move, _ply: usize) -> i32 {
+    let mover = game.get_piece(mv.from).unwrap();
+    // trading down takes the sting out of the opponent's play
+    let trade = game
+        .captured_piece(mv)
+        .is_some_and(|captured| piece_value(captured) >= piece_value(mover.piece_type()));
+    // pawns in front of the king stay put
+    let king = game.find_king(game.turn());
+    let loosens_shelter = mover.is_pawn() && mv.from.file().abs_diff(king.file()) <= 1;
+    let trade_bonus = if trade { 25 } else { 0 };
+    trade_bonus - if loosens_shelter { 30 } else { 0 }
+}
+
+fn hungry_taste(game: &Game, mv: Move, _ply: usize) -> i32 {
+    capture_gain(game, mv) / 2
+}
+
+fn gambit_taste(game: &Game, mv: Move, ply: usize) -> i32 {
+    if ply >= GAMBIT_PLIES {
+        return 0;
+    }
+    let mover = game.get_piece(mv.from).unwrap();
+    let mut enemy = *game;
+    enemy.switch_turn();
+    let back_rank = if game.is_white_turn() { 0 } else { 7 };
+    if mover.is_pawn() && game.is_attacked_by(mv.to, enemy.turn()) {
+        50
+    } else if matches!(mover.piece_type(), Knight | Bishop) && mv.from.rank() == back_rank {
+        15
+    } else {
+        0
+    }
+}
+
+impl Personality {
+    pub fn find(key: &str) -> Result<&'static Personality, String> {
+        PERSONALITIES
+            .iter()
+            .find(|personality| personality.key == key)
+            .ok_or_else(|| {
+                let keys: Vec<&str> = PERSONALITIES
+                    .iter()
+                    .map(|personality| personality.key)
+                    .collect();
+                format!(
+                    "Error: Unknown bot '{key}', choose from {}",
+                    keys.join(", ")
+                )
+            })
+    }
+    // the installed weights with this style's emphasis on top
+    fn eval_params(&self) -> EvalParams {
+        let mut params = eval::params().clone();
+        let scale = |weights: &mut [i32], percent: i32| {
+            for weight in weights {
+                *weight = *weight * percent / 100;
+            }
+        };
+        scale(&mut params.weights[MATERIAL..MATERIAL + 1], self.pawn);
+        scale(&mut params.weights[MATERIAL + 1..PIECE_SQUARE], self.pieces);
+        scale(&mut params.weights[MOBILITY..KING_SHIELD], self.mobility);
+        scale(
+            &mut params.weights[KING_SHIELD..KING_ATTACK],
+            self.king_shield,
+        );
+        scale(&mut params.weights[KING_ATTACK..], self.king_attack);
+        params
+    }
+}
+
+// a bot playing one of the personalities with the built-in search
+pub struct PersonalityPlayer {
+    personality: &'static Personality,
+    searcher: Searcher,
+    limit: EngineLimit,
+    clock: Option<Clock>,
+    last_score: Option<i32>,
+}
+
+impl PersonalityPlayer {
+    pub fn new(personality: &'static Personality, limit: EngineLimit) -> PersonalityPlayer {
+        let mut searcher = Searcher::new(OrderingConfig::all());
+        searcher.set_eval_params(Some(Arc::new(personality.eval_params())));
+        searcher.set_multi_pv(CANDIDATE_LINES);
+        PersonalityPlayer {
+            personality,
+            searcher,
+            limit,
+            clock: None,
+            last_score: None,
+        }
+    }
+}
+
+impl Player for PersonalityPlayer {
+    fn name(&self) -> &str {
+        self.personality.name
+    }
+    fn description(&self) -> Option<&str> {
+        Some(self.personality.description)
+    }
+    fn choose_move(&mut self, game: &Game, record: &mut GameRecord) -> Result<Action, String> {
+        let limit = clock_limit(self.limit, self.clock.as_ref());
+        let result = self
+            .searcher
+            .search_with(game, limit.search_limits(game.turn()), |_| {});
+        let ply = record.moves.len();
+        let taste = self.personality.taste;
+        // a forced mate is played as found, otherwise taste picks among the near-best lines
+        let choice = result
+            .lines
+            .iter()
+            .filter(|line| {
+                result.score.abs() < MATE_THRESHOLD
+                    && line.score >= result.score - self.personality.margin
+            })
+            .filter_map(|line| Some((*line.pv.first()?, line.score)))
+            .max_by_key(|(mv, score)| score + taste(game, *mv, ply));
+        self.last_score = Some(result.score);
+        match choice.map(|(mv, _)| mv).or(result.best_move) {
+            Some(mv) => Ok(Action::Move(mv)),
+            None => Ok(Action::Resign),
+        }
+    }
+    fn set_clock(&mut self, clock: &Clock) {
+        self.clock = Some(*clock);
+    }
+    fn accept_draw(&mut self, _game: &Game) -> bool {
+        self.personality
+            .draw_threshold
+            .zip(self.last_score)
+            .is_some_and(|(threshold, score)| score <= threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(fen: &str) -> Game {
+        Game::from_fen(fen).unwrap()
+    }
+
+    fn coords(text: &str) -> Move {
+        Move::from_coords(text).unwrap()
+    }
+
+    fn player(key: &str) -> PersonalityPlayer {
+        PersonalityPlayer::new(Personality::find(key).unwrap(), EngineLimit::Depth(2))
+    }
+
+    fn play(player: &mut PersonalityPlayer, game: &Game) -> Move {
+        match player.choose_move(game, &mut GameRecord::new()) {
+            Ok(Action::Move(mv)) => mv,
+            _ => panic!("no move"),
+        }
+    }
+
+    #[test]
+    fn finds_every_personality_by_key() {
+        for personality in &PERSONALITIES {
+            assert_eq!(
+                Personality::find(personality.key).unwrap().name,
+                personality.name
+            );
+        }
+        assert_eq!(
+            Personality::find("pacifist").err().unwrap(),
+            "Error: Unknown bot 'pacifist', choose from attacker, defender, glutton, gambiteer"
+        );
+    }
+
+    #[test]
+    fn scales_the_installed_weights() {
+        let usual = &eval::params().weights;
+        let glutton = Personality::find("glutton").unwrap().eval_params().weights;
+        assert_eq!(glutton[MATERIAL], usual[MATERIAL] * 130 / 100);
+        assert_eq!(glutton[MATERIAL + 4], usual[MATERIAL + 4] * 130 / 100);
+        assert_eq!(glutton[PIECE_SQUARE + 10], usual[PIECE_SQUARE + 10]);
+        let attacker = Personality::find("attacker").unwrap().eval_params().weights;
+        assert_eq!(attacker[KING_ATTACK], usual[KING_ATTACK] * 4);
+        assert_eq!(attacker[MOBILITY], usual[MOBILITY] * 2);
+    }
+
+    #[test]
+    fn tastes_favour_their_kind_of_move() {
+        let game = position("4k3/8/8/3q4/8/8/3R4/R3K3 w - - 0 1");
+        assert_eq!(attacking_taste(&game, coords("a1a8"), 0), 40);
+        assert_eq!(attacking_taste(&game, coords("d2d7"), 0), 15);
+        assert_eq!(hungry_taste(&game, coords("d2d5"), 0), 450);
+        assert_eq!(solid_taste(&game, coords("d2d5"), 0), 25);
+
+        let game = position("4k3/8/8/8/8/8/3PPP2/4K3 w - - 0 1");
+        assert_eq!(solid_taste(&game, coords("e2e4"), 0), -30);
+
+        let game = position("rnbqkbnr/ppp1pppp/8/3p4/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(gambit_taste(&game, coords("e2e4"), 2), 50);
+        assert_eq!(gambit_taste(&game, coords("e2e4"), GAMBIT_PLIES), 0);
+        assert_eq!(gambit_taste(&game, coords("g1f3"), 2), 15);
+        assert_eq!(gambit_taste(&game, coords("a2a3"), 2), 0);
+    }
+
+    #[test]
+    fn plays_a_forced_mate_whatever_its_taste() {
+        let game = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        for personality in &PERSONALITIES {
+            let mut bot = player(personality.key);
+            assert_eq!(play(&mut bot, &game), coords("a1a8"), "{}", personality.key);
+        }
+    }
+
+    #[test]
+    fn glutton_grabs_material() {
+        let game = position("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+        assert_eq!(play(&mut player("glutton"), &game), coords("d2d5"));
+    }
+
+    #[test]
+    fn draw_answers_follow_the_threshold() {
+        let behind = position("3qk3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let level = position("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let mut attacker = player("attacker");
+        play(&mut attacker, &behind);
+        assert!(!attacker.accept_draw(&behind));
+        let mut glutton = player("glutton");
+        assert!(!glutton.accept_draw(&behind));
+        play(&mut glutton, &behind);
+        assert!(glutton.accept_draw(&behind));
+        play(&mut glutton, &level);
+        assert!(!glutton.accept_draw(&level));
+        let mut defender = player("defender");
+        play(&mut defender, &level);
+        assert!(defender.accept_draw(&level));
+    }
+}
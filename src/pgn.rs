@@ -0,0 +1,328 @@
+use crate::moves::Move;
+use crate::Game;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unknown,
+}
+
+impl GameResult {
+    pub fn token(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+    fn from_token(token: &str) -> Option<GameResult> {
+        match token {
+            "1-0" => Some(GameResult::WhiteWins),
+            "0-1" => Some(GameResult::BlackWins),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unknown),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub san_moves: Vec<String>,
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn start_position(&self) -> Result<Game, String> {
+        match self.tag("FEN") {
+            Some(fen) => Game::from_fen(fen),
+            None => Ok(Game::init()),
+        }
+    }
+    // replays the main line, failing on the first move that does not parse
+    pub fn moves(&self) -> Result<Vec<Move>, String> {
+        let mut game = self.start_position()?;
+        let mut moves = vec![];
+        for san in &self.san_moves {
+            let mv = game.parse_san(san)?;
+            game.make_legal_move(mv);
+            moves.push(mv);
+        }
+        Ok(moves)
+    }
+}
+
+// movetext in SAN with move numbers, wrapped the way most PGN tools write it
+pub fn format_pgn(
+    tags: &[(String, String)],
+    start: &Game,
+    moves: &[Move],
+    result: GameResult,
+) -> String {
+    let mut pgn = String::new();
+    for (name, value) in tags {
+        pgn.push_str(&format!("[{name} \"{}\"]\n", value.replace('"', "\\\"")));
+    }
+    pgn.push('\n');
+
+    let mut tokens = vec![];
+    let mut position = *start;
+    let mut move_number = 1;
+    for (index, san) in start.san_line(moves).into_iter().enumerate() {
+        if position.is_white_turn() {
+            tokens.push(format!("{move_number}."));
+        } else if index == 0 {
+            tokens.push(format!("{move_number}..."));
+        }
+        tokens.push(san);
+        if position.is_black_turn() {
+            move_number += 1;
+        }
+        position.make_legal_move(moves[index]);
+    }
+    tokens.push(result.token().to_string());
+
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + token.len() >= 80 {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push_str("\n\n");
+    pgn
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(' ')?;
+    // only the outer quotes, a value may end in an escaped one
+    let value = value.trim();
+    let value = value.strip_prefix('"').unwrap_or(value);
+    let value = value
+        .strip_suffix('"')
+        .unwrap_or(value)
+        .replace("\\\"", "\"");
+    Some((name.to_string(), value))
+}
+
+fn push_token(current: &mut String, tokens: &mut Vec<String>) {
+    if !current.is_empty() {
+        tokens.push(std::mem::take(current));
+    }
+}
+
+// splits movetext into SAN tokens, dropping comments, variations, NAGs and move numbers
+fn movetext_tokens(movetext: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut comment = false;
+    let mut variation_depth = 0;
+    for c in movetext.chars() {
+        if comment {
+            comment = c != '}';
+            continue;
+        }
+        match c {
+            '{' => {
+                push_token(&mut current, &mut tokens);
+                comment = true;
+            }
+            '(' => {
+                push_token(&mut current, &mut tokens);
+                variation_depth += 1;
+            }
+            ')' => {
+                current.clear();
+                variation_depth -= 1;
+            }
+            _ if variation_depth > 0 => {}
+            c if c.is_whitespace() => push_token(&mut current, &mut tokens),
+            _ => current.push(c),
+        }
+    }
+    push_token(&mut current, &mut tokens);
+    tokens
+        .into_iter()
+        .filter(|token| !token.starts_with('$'))
+        // "12." and "12..." on their own, or glued to the move as in "12.e4"
+        .map(|token| match GameResult::from_token(&token) {
+            Some(_) => token,
+            None => token
+                .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+                .to_string(),
+        })
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+fn finish_game(tags: Vec<(String, String)>, movetext: &str) -> PgnGame {
+    let mut san_moves = movetext_tokens(movetext);
+    let mut result = tags
+        .iter()
+        .find(|(tag, _)| tag == "Result")
+        .and_then(|(_, value)| GameResult::from_token(value))
+        .unwrap_or(GameResult::Unknown);
+    if let Some(token) = san_moves.last() {
+        if let Some(terminator) = GameResult::from_token(token) {
+            result = terminator;
+            san_moves.pop();
+        }
+    }
+    PgnGame {
+        tags,
+        san_moves,
+        result,
+    }
+}
+
+// every game in a PGN collection, in file order
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games = vec![];
+    let mut tags = vec![];
+    let mut movetext = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('%') {
+            continue;
+        }
+        if line.starts_with('[') {
+            // a tag after movetext means the previous game had no result terminator
+            if !movetext.trim().is_empty() {
+                games.push(finish_game(std::mem::take(&mut tags), &movetext));
+                movetext.clear();
+            }
+            if let Some(tag) = parse_tag(line) {
+                tags.push(tag);
+            }
+            continue;
+        }
+        movetext.push_str(line);
+        movetext.push('\n');
+        let ends_game = line
+            .split_whitespace()
+            .last()
+            .is_some_and(|token| GameResult::from_token(token).is_some());
+        if ends_game {
+            games.push(finish_game(std::mem::take(&mut tags), &movetext));
+            movetext.clear();
+        }
+    }
+    if !movetext.trim().is_empty() || !tags.is_empty() {
+        games.push(finish_game(tags, &movetext));
+    }
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLLECTION: &str = r#"[Event "Test"]
+[White "Alice \"A\" Smith"]
+[Black "Bob"]
+[Result "1-0"]
+
+1. e4 {best by test} e5 2.Nf3 (2. f4 exf4 3. Nf3) Nc6 $1 3. Bb5 a6!? 1-0
+
+[Event "Second"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]
+
+1... Kd7 2. e4 *
+
+[Event "No terminator"]
+
+1. d4 d5
+[Event "Fourth"]
+[Result "1/2-1/2"]
+
+1. c4
+"#;
+
+    #[test]
+    fn parses_a_collection() {
+        let games = parse_pgn(COLLECTION);
+        assert_eq!(games.len(), 4);
+
+        let first = &games[0];
+        assert_eq!(first.tag("White"), Some("Alice \"A\" Smith"));
+        assert_eq!(first.result, GameResult::WhiteWins);
+        assert_eq!(first.san_moves, ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6!?"]);
+        assert_eq!(first.moves().unwrap().len(), 6);
+
+        let second = &games[1];
+        assert_eq!(second.result, GameResult::Unknown);
+        assert_eq!(second.san_moves, ["Kd7", "e4"]);
+        assert_eq!(
+            second.start_position().unwrap().fen(),
+            "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"
+        );
+        assert!(second.moves().is_ok());
+
+        assert_eq!(games[2].san_moves, ["d4", "d5"]);
+        assert_eq!(games[2].result, GameResult::Unknown);
+        // without a terminator the Result tag decides
+        assert_eq!(games[3].result, GameResult::Draw);
+    }
+
+    #[test]
+    fn illegal_moves_fail_the_replay() {
+        let games = parse_pgn("1. e4 e5 2. Ke3 1-0\n");
+        assert!(games[0].moves().is_err());
+    }
+
+    #[test]
+    fn formatted_games_parse_back() {
+        let tags = vec![
+            ("Event".to_string(), "Club \"Open\"".to_string()),
+            ("Result".to_string(), "0-1".to_string()),
+        ];
+        let start = Game::init();
+        let moves = parse_pgn("1. f3 e5 2. g4 Qh4# 0-1")[0].moves().unwrap();
+        let text = format_pgn(&tags, &start, &moves, GameResult::BlackWins);
+        assert_eq!(
+            text,
+            "[Event \"Club \\\"Open\\\"\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n\n"
+        );
+        let parsed = &parse_pgn(&text)[0];
+        assert_eq!(parsed.tags, tags);
+        assert_eq!(parsed.moves().unwrap(), moves);
+        assert_eq!(parsed.result, GameResult::BlackWins);
+    }
+
+    #[test]
+    fn black_to_move_starts_with_an_ellipsis_and_long_lines_wrap() {
+        let start = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        let mut game = start;
+        let mut moves = vec![];
+        for coords in ["e8d7", "a1a2", "d7e8", "a2a1"].iter().cycle().take(40) {
+            let mv = game.find_move(coords).unwrap();
+            game.make_legal_move(mv);
+            moves.push(mv);
+        }
+        let tags = [("FEN".to_string(), start.fen())];
+        let text = format_pgn(&tags, &start, &moves, GameResult::Draw);
+        let movetext: Vec<&str> = text.trim().lines().skip(2).collect();
+        assert!(movetext[0].starts_with("1... Kd7 2. Ra2 Ke8 3. Ra1"));
+        assert!(movetext.len() > 2);
+        assert!(movetext.iter().all(|line| line.len() < 80));
+        assert_eq!(parse_pgn(&text)[0].moves().unwrap(), moves);
+    }
+}
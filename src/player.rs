@@ -0,0 +1,1004 @@
+use crate::analysis;
+use crate::book::OpeningBook;
+use crate::clock::{Clock, TimeControl};
+use crate::eval::{capture_gain, piece_value};
+use crate::external_engine::EngineLimit;
+use crate::hint;
+use crate::moves::{Move, Outcome, PositionHistory};
+use crate::personality::{Personality, PersonalityPlayer};
+use crate::random::Rng;
+use crate::record::GameRecord;
+use crate::retrograde::{self, EndgameTables};
+use crate::theme;
+use crate::tournament::{parse_number, parse_settings, Contestant};
+use crate::tui::{Tui, TuiPlayer};
+use crate::view::{SharedPerspective, View};
+use crate::PieceType::{self, *};
+use crate::Team::{self, *};
+use crate::{Game, Highlight, Square};
+use colored::Colorize;
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+// engines only offer draws in long, level games, and not again straight after a refusal
+const DRAW_OFFER_PLY: usize = 80;
+const DRAW_OFFER_SCORE: i32 = 10;
+const DRAW_OFFER_INTERVAL: usize = 20;
+// how often a human waiting to type looks at their clock
+const TICK: Duration = Duration::from_millis(200);
+
+pub enum Action {
+    Move(Move),
+    OfferDraw,
+    Resign,
+    // the player's clock ran out while it was still thinking
+    OutOfTime,
+}
+
+pub enum GameEnd {
+    Checkmate { winner: Team },
+    Stalemate,
+    InsufficientMaterial,
+    Repetition,
+    FiftyMoves,
+    Resigned { loser: Team },
+    // a draw when the opponent has nothing left to mate with
+    OutOfTime { loser: Team, drawn: bool },
+    DrawAgreed,
+}
+
+// one side of a game; the game loop asks it for moves and answers, whoever is behind it
+pub trait Player {
+    fn name(&self) -> &str;
+    // shown when the game starts
+    fn description(&self) -> Option<&str> {
+        None
+    }
+    // whether someone sits behind this player, who doesn't need to be told it is thinking
+    fn interactive(&self) -> bool {
+        false
+    }
+    // called before `choose_move` in games with a clock, which is running by then
+    fn set_clock(&mut self, _clock: &Clock) {}
+    // an error forfeits the game
+    fn choose_move(&mut self, game: &Game, record: &mut GameRecord) -> Result<Action, String>;
+    // asked when the chosen move takes a pawn to the last rank without naming a piece
+    fn choose_promotion(&mut self, _game: &Game) -> PieceType {
+        Queen
+    }
+    // `game` is the position the move was played from; players that keep their own copy of
+    // the game, such as a remote opponent, follow along here
+    fn opponent_moved(&mut self, _game: &Game, _mv: Move) {}
+    fn accept_draw(&mut self, _game: &Game) -> bool {
+        false
+    }
+}
+
+// with a clock, bots play on it instead of the fixed limit
+pub fn clock_limit(limit: EngineLimit, clock: Option<&Clock>) -> EngineLimit {
+    match clock {
+        Some(clock) => EngineLimit::Clock {
+            white_time: clock.remaining(White),
+            black_time: clock.remaining(Black),
+            white_increment: clock.increment(),
+            black_increment: clock.increment(),
+        },
+        None => limit,
+    }
+}
+
+pub fn team_name(team: Team) -> &'static str {
+    theme::theme().team_name(team)
+}
+
+// material for `team` minus the opponent's, kings left out
+fn material_balance(game: &Game, team: Team) -> i32 {
+    game.board
+        .iter()
+        .flatten()
+        .flatten()
+        .filter(|piece| !piece.is_king())
+        .map(|piece| {
+            let value = piece_value(piece.piece_type());
+            if piece.team() == team {
+                value
+            } else {
+                -value
+            }
+        })
+        .sum()
+}
+
+// what a human can consult from the move prompt
+pub struct Assistance {
+    pub book: Option<Rc<OpeningBook>>,
+    pub endgame_tables: Option<Arc<EndgameTables>>,
+}
+
+impl Assistance {
+    pub fn hint(&self, game: &Game) -> Result<String, String> {
+        hint::suggest_move(game, self.endgame_tables.as_ref())
+            .ok_or_else(|| "No hint available".to_string())
+    }
+    pub fn endgame_report(&self, game: &Game) -> Result<String, String> {
+        let Some(tables) = &self.endgame_tables else {
+            return Err("No endgame tables loaded, start with --tablebases <dir>".to_string());
+        };
+        retrograde::describe(tables, game)
+            .ok_or_else(|| "The loaded endgame tables do not cover this position".to_string())
+    }
+    pub fn book_report(&self, game: &Game) -> Result<String, String> {
+        let Some(book) = &self.book else {
+            return Err("No opening book loaded, start with --book <path>".to_string());
+        };
+        let moves = book.moves(game);
+        if moves.is_empty() {
+            return Ok("No book moves for this position".to_string());
+        }
+        let total: u32 = moves.iter().map(|book_move| book_move.weight as u32).sum();
+        let lines: Vec<String> = moves
+            .iter()
+            .map(|book_move| {
+                format!(
+                    "{} ({}) {:.0}%",
+                    game.san(book_move.mv),
+                    book_move.mv,
+                    book_move.weight as f64 * 100.0 / total.max(1) as f64
+                )
+            })
+            .collect();
+        Ok(format!("Book moves: {}", lines.join(", ")))
+    }
+}
+
+// a player at the terminal, typing moves and commands
+pub struct HumanPlayer {
+    team: Team,
+    assistance: Rc<Assistance>,
+    clock: Option<Clock>,
+    perspective: SharedPerspective,
+    input: Lines,
+}
+
+fn parse_coords(input: &str) -> Result<(Square, Square), String> {
+    let coords: Vec<&str> = input.split(" ").collect();
+    if coords.len() != 2 {
+        return Err("Error: Must input 2 coordinates".to_string());
+    }
+    let start_sq = Square::from_coords(coords[0]);
+    let target_sq = Square::from_coords(coords[1]);
+
+    let (start_sq, target_sq) = match (start_sq, target_sq) {
+        (Ok(start_sq), Ok(target_sq)) => (start_sq, target_sq),
+        _ => return Err("Invalid Coordinates".to_string()),
+    };
+
+    Ok((start_sq, target_sq))
+}
+
+type Lines = Arc<Mutex<Receiver<Result<String, String>>>>;
+
+// lines typed at the terminal, read on a thread of their own so that a player waiting for one
+// still notices their flag fall; started once, as every console player shares stdin
+fn stdin_lines() -> Lines {
+    static LINES: OnceLock<Lines> = OnceLock::new();
+    LINES
+        .get_or_init(|| {
+            let (sender, lines) = mpsc::channel();
+            thread::spawn(move || {
+                for line in std::io::stdin().lines() {
+                    let line = line
+                        .map(|line| line.trim().to_string())
+                        .map_err(|err| format!("Error reading input: {}", err));
+                    let failed = line.is_err();
+                    if sender.send(line).is_err() || failed {
+                        break;
+                    }
+                }
+            });
+            Arc::new(Mutex::new(lines))
+        })
+        .clone()
+}
+
+impl HumanPlayer {
+    pub fn new(
+        team: Team,
+        assistance: Rc<Assistance>,
+        perspective: SharedPerspective,
+    ) -> HumanPlayer {
+        HumanPlayer::with_input(team, assistance, perspective, stdin_lines())
+    }
+    fn with_input(
+        team: Team,
+        assistance: Rc<Assistance>,
+        perspective: SharedPerspective,
+        input: Lines,
+    ) -> HumanPlayer {
+        HumanPlayer {
+            team,
+            assistance,
+            clock: None,
+            perspective,
+            input,
+        }
+    }
+    // the next line typed, or `None` once `clock` shows the player's flag has fallen; the end
+    // of input counts as quitting rather than an empty move forever
+    fn read_input(&self, clock: Option<&Clock>) -> Option<Result<String, String>> {
+        let lines = self.input.lock().unwrap();
+        loop {
+            match lines.recv_timeout(TICK) {
+                Ok(line) => return Some(line),
+                Err(RecvTimeoutError::Disconnected) => return Some(Ok("q".to_string())),
+                Err(RecvTimeoutError::Timeout) => {
+                    if clock.is_some_and(|clock| clock.flag_fallen().is_some()) {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Player for HumanPlayer {
+    fn name(&self) -> &str {
+        team_name(self.team)
+    }
+    fn interactive(&self) -> bool {
+        true
+    }
+    fn choose_move(&mut self, game: &Game, record: &mut GameRecord) -> Result<Action, String> {
+        let mut error_message: Option<String> = None;
+        let mut info_message: Option<String> = None;
+        // the game loop has just shown the board, later prompts show it again
+        let mut first_prompt = true;
+        // a piece picked up by typing its square alone, shown with where it can go
+        let mut selected: Option<Square> = None;
+        loop {
+            if self
+                .clock
+                .as_ref()
+                .is_some_and(|clock| clock.flag_fallen().is_some())
+            {
+                return Ok(Action::OutOfTime);
+            }
+            if !first_prompt {
+                let mut highlight = Highlight::position(game, record.moves.last().copied());
+                if let Some(square) = selected {
+                    highlight = highlight.select(game, square);
+                }
+                game.display_board(
+                    self.clock.as_ref(),
+                    &highlight,
+                    self.perspective.get().bottom(game),
+                );
+                if let Some(error_message) = &error_message {
+                    println!("{}\n", error_message.red().bold());
+                }
+                if let Some(info_message) = info_message.take() {
+                    println!("{}\n", info_message.green());
+                }
+            }
+            first_prompt = false;
+            println!(
+                "{}, your turn! Enter the coordinate of the piece you want to move followed by the coordinate of the target square, or just the piece's to see its moves (or \"hint\" / \"analyze\" / \"book\" / \"endgame\" / \"flip\" / \"draw\" / \"q\")",
+                self.name()
+            );
+
+            let input = match self.read_input(self.clock.as_ref()) {
+                Some(Ok(input)) => input,
+                Some(Err(err)) => {
+                    error_message = Some(err);
+                    continue;
+                }
+                None => return Ok(Action::OutOfTime),
+            };
+            let report = match input.as_str() {
+                "q" => return Ok(Action::Resign),
+                "draw" => return Ok(Action::OfferDraw),
+                "hint" => self
+                    .assistance
+                    .hint(game)
+                    .inspect(|_| record.add_hint(self.team)),
+                "endgame" => self.assistance.endgame_report(game),
+                "book" => self.assistance.book_report(game),
+                "flip" => {
+                    let mut perspective = self.perspective.get();
+                    perspective.flip();
+                    self.perspective.set(perspective);
+                    Ok(String::new())
+                }
+                _ => match analysis::parse_analyze_command(&input) {
+                    Some(analysis) => analysis.map(|(time_limit, lines)| {
+                        analysis::analyze(
+                            game,
+                            time_limit,
+                            lines,
+                            self.assistance.endgame_tables.as_ref(),
+                        );
+                        String::new()
+                    }),
+                    None => {
+                        let own_piece = |square: Square| {
+                            game.get_piece(square)
+                                .is_some_and(|piece| piece.team() == game.turn())
+                        };
+                        let coords = match Square::from_coords(&input) {
+                            // with a piece picked up, any other square is where it goes
+                            Ok(square) if selected.is_some() && !own_piece(square) => {
+                                Ok((selected.unwrap(), square))
+                            }
+                            Ok(square) if !own_piece(square) => {
+                                Err("Error: Choose one of your own pieces".to_string())
+                            }
+                            Ok(square) if game.get_legal_moves(square).is_empty() => {
+                                Err("Error: That piece has no legal moves".to_string())
+                            }
+                            Ok(square) => {
+                                selected = Some(square);
+                                error_message = None;
+                                continue;
+                            }
+                            Err(_) => parse_coords(&input),
+                        };
+                        selected = None;
+                        let (start_sq, target_sq) = match coords {
+                            Ok(coords) => coords,
+                            Err(err) => {
+                                error_message = Some(err);
+                                continue;
+                            }
+                        };
+                        match game.try_move(Move::new(start_sq, target_sq)) {
+                            Ok(mv) => return Ok(Action::Move(mv)),
+                            Err(err) => Err(err),
+                        }
+                    }
+                },
+            };
+            match report {
+                Ok(report) => {
+                    info_message = Some(report).filter(|report| !report.is_empty());
+                    error_message = None;
+                }
+                Err(err) => error_message = Some(err),
+            }
+        }
+    }
+    fn set_clock(&mut self, clock: &Clock) {
+        self.clock = Some(*clock);
+    }
+    fn choose_promotion(&mut self, _game: &Game) -> PieceType {
+        loop {
+            println!("Congrats! You got a pawn to the last rank. Which piece would you like:\na) Queen\nb) Rook\nc) Bishop\nd) Knight");
+            let input = match self.read_input(self.clock.as_ref()) {
+                Some(Ok(input)) => input,
+                Some(Err(err)) => {
+                    println!("{}", err);
+                    continue;
+                }
+                // the game loop finds the fallen flag whatever the piece
+                None => return Queen,
+            };
+            match &input[..] {
+                // "q" is what the end of input reads as, so a queen it is
+                "a" | "q" => return Queen,
+                "b" => return Rook,
+                "c" => return Bishop,
+                "d" => return Knight,
+                _ => println!("{}", "Input Error: Pick from the listed options".red()),
+            }
+        }
+    }
+    fn accept_draw(&mut self, _game: &Game) -> bool {
+        loop {
+            println!("{}, do you accept the draw? (y/n)", self.name());
+            // the clock that runs is the opponent's, so this waits for an answer
+            match self.read_input(None) {
+                Some(Ok(answer)) if matches!(answer.as_str(), "y" | "yes") => return true,
+                Some(Ok(answer)) if matches!(answer.as_str(), "n" | "no" | "q") => return false,
+                Some(Ok(_)) => println!("{}", "Input Error: Answer y or n".red()),
+                Some(Err(err)) => println!("{}", err),
+                None => return false,
+            }
+        }
+    }
+}
+
+// plays any legal move, and takes a draw on a coin flip
+pub struct RandomPlayer {
+    name: String,
+    rng: Rng,
+}
+
+impl Player for RandomPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn choose_move(&mut self, game: &Game, _record: &mut GameRecord) -> Result<Action, String> {
+        Ok(self
+            .rng
+            .choose(&game.legal_moves())
+            .map_or(Action::Resign, Action::Move))
+    }
+    fn accept_draw(&mut self, _game: &Game) -> bool {
+        self.rng.below(2) == 0
+    }
+}
+
+// grabs the most material it can this move, playing at random when nothing hangs, and takes
+// a draw only when behind
+pub struct GreedyPlayer {
+    name: String,
+    rng: Rng,
+}
+
+impl Player for GreedyPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    fn choose_move(&mut self, game: &Game, _record: &mut GameRecord) -> Result<Action, String> {
+        let moves = game.legal_moves();
+        let best_gain = moves
+            .iter()
+            .map(|mv| capture_gain(game, *mv))
+            .max()
+            .unwrap_or(0);
+        let best: Vec<Move> = moves
+            .into_iter()
+            .filter(|mv| capture_gain(game, *mv) == best_gain)
+            .collect();
+        Ok(self.rng.choose(&best).map_or(Action::Resign, Action::Move))
+    }
+    fn accept_draw(&mut self, game: &Game) -> bool {
+        let mut own_turn = *game;
+        own_turn.switch_turn();
+        material_balance(game, own_turn.turn()) < 0
+    }
+}
+
+// the built-in engine, the MCTS bot or a UCI program, playing from the opening book first
+pub struct EnginePlayer {
+    contestant: Contestant,
+    book: Option<Rc<OpeningBook>>,
+    limit: EngineLimit,
+    clock: Option<Clock>,
+    // its last score from its own side
+    last_score: Option<i32>,
+    // the move held back while a draw offer is answered
+    pending: Option<Move>,
+    last_offer: Option<usize>,
+}
+
+impl EnginePlayer {
+    pub fn new(
+        contestant: Contestant,
+        book: Option<Rc<OpeningBook>>,
+        limit: EngineLimit,
+    ) -> EnginePlayer {
+        EnginePlayer {
+            contestant,
+            book,
+            limit,
+            clock: None,
+            last_score: None,
+            pending: None,
+            last_offer: None,
+        }
+    }
+}
+
+impl Player for EnginePlayer {
+    fn name(&self) -> &str {
+        self.contestant.name()
+    }
+    fn choose_move(&mut self, game: &Game, record: &mut GameRecord) -> Result<Action, String> {
+        if let Some(mv) = self.pending.take() {
+            return Ok(Action::Move(mv));
+        }
+        if let Some(mv) = self.book.as_ref().and_then(|book| book.choose(game)) {
+            return Ok(Action::Move(mv));
+        }
+        let limit = clock_limit(self.limit, self.clock.as_ref());
+        let (mv, score) = self.contestant.play(&record.moves, game, limit)?;
+        self.last_score = score;
+        let Some(mv) = mv else {
+            return Ok(Action::Resign);
+        };
+        let ply = record.moves.len();
+        if ply >= DRAW_OFFER_PLY
+            && score.is_some_and(|score| score.abs() <= DRAW_OFFER_SCORE)
+            && self
+                .last_offer
+                .is_none_or(|offer| ply >= offer + DRAW_OFFER_INTERVAL)
+        {
+            self.last_offer = Some(ply);
+            self.pending = Some(mv);
+            return Ok(Action::OfferDraw);
+        }
+        Ok(Action::Move(mv))
+    }
+    fn set_clock(&mut self, clock: &Clock) {
+        self.clock = Some(*clock);
+    }
+    fn accept_draw(&mut self, _game: &Game) -> bool {
+        self.last_score.is_some_and(|score| score <= 0)
+    }
+}
+
+// `human`, `random[:seed=n]`, `greedy[:seed=n]`, `bot:<personality>` or any engine spec
+// `--match` takes; the random and greedy bots also take `name`
+pub fn player_from_spec(
+    spec: &str,
+    team: Team,
+    assistance: &Rc<Assistance>,
+    limit: EngineLimit,
+    tui: Option<&Tui>,
+    perspective: &SharedPerspective,
+) -> Result<Box<dyn Player>, String> {
+    let (kind, settings) = spec.split_once(':').unwrap_or((spec, ""));
+    match kind {
+        "human" if settings.is_empty() => match tui {
+            Some(tui) => Ok(Box::new(TuiPlayer::new(
+                team,
+                tui.clone(),
+                assistance.clone(),
+            ))),
+            None => Ok(Box::new(HumanPlayer::new(
+                team,
+                assistance.clone(),
+                perspective.clone(),
+            ))),
+        },
+        "bot" => Ok(Box::new(PersonalityPlayer::new(
+            Personality::find(settings)?,
+            limit,
+        ))),
+        "random" | "greedy" => {
+            let mut name = kind.to_string();
+            let mut rng = Rng::new();
+            for (key, value) in parse_settings(settings)? {
+                match key {
+                    "name" => name = value.to_string(),
+                    "seed" => rng = Rng::with_seed(parse_number(key, value)?),
+                    _ => return Err(format!("Error: Unknown {kind} setting '{key}'")),
+                }
+            }
+            if kind == "random" {
+                Ok(Box::new(RandomPlayer { name, rng }))
+            } else {
+                Ok(Box::new(GreedyPlayer { name, rng }))
+            }
+        }
+        _ => Ok(Box::new(EnginePlayer::new(
+            Contestant::from_spec(spec)?,
+            assistance.book.clone(),
+            limit,
+        ))),
+    }
+}
+
+fn last_rank(team: Team) -> usize {
+    match team {
+        White => 7,
+        Black => 0,
+    }
+}
+
+// the side to move lost on time, or drew when the opponent has nothing left to mate with
+fn flag_fall(game: &Game, name: &str, view: &mut dyn View) -> GameEnd {
+    view.message(&format!("{name} ran out of time"));
+    let mut opponent = *game;
+    opponent.switch_turn();
+    GameEnd::OutOfTime {
+        loser: game.turn(),
+        drawn: !game.has_mating_material(opponent.turn()),
+    }
+}
+
+// plays a game from the starting position, `players` being blue then red; whatever either
+// player is, every move goes through the same checks
+pub fn play_game(
+    players: &mut [Box<dyn Player>; 2],
+    record: &mut GameRecord,
+    time_control: Option<TimeControl>,
+    view: &mut dyn View,
+) -> (Game, GameEnd) {
+    let mut game = Game::init();
+    let mut history = PositionHistory::new(&game);
+    let mut clock = time_control.map(Clock::new);
+    let mut status: Option<String> = None;
+    loop {
+        view.show_position(&game, record, clock.as_ref());
+        if let Some(status) = status.take() {
+            view.warning(&status);
+        }
+        let (side, other) = if game.is_white_turn() { (0, 1) } else { (1, 0) };
+        let resigned = GameEnd::Resigned { loser: game.turn() };
+
+        if let Some(clock) = &mut clock {
+            clock.start(game.turn());
+            players[side].set_clock(clock);
+        }
+        if !players[side].interactive() {
+            view.message(&format!("{} is thinking...", players[side].name()));
+        }
+
+        let mut mv = loop {
+            let action = players[side].choose_move(&game, record);
+            // a flag that fell while the player was thinking ends the game whatever it chose
+            if clock
+                .as_ref()
+                .is_some_and(|clock| clock.flag_fallen().is_some())
+            {
+                return (game, flag_fall(&game, players[side].name(), view));
+            }
+            match action {
+                Ok(Action::Move(mv)) => break mv,
+                Ok(Action::OfferDraw) => {
+                    view.message(&format!("{} offers a draw", players[side].name()));
+                    if players[other].accept_draw(&game) {
+                        return (game, GameEnd::DrawAgreed);
+                    }
+                    view.message(&format!("{} declines the draw", players[other].name()));
+                }
+                Ok(Action::Resign) => {
+                    view.message(&format!("{} resigns", players[side].name()));
+                    return (game, resigned);
+                }
+                Ok(Action::OutOfTime) => {
+                    return (game, flag_fall(&game, players[side].name(), view))
+                }
+                Err(err) => {
+                    view.warning(&err);
+                    view.message(&format!("{} forfeits the game", players[side].name()));
+                    return (game, resigned);
+                }
+            }
+        };
+        let promoting = game.get_piece(mv.from).is_some_and(|piece| piece.is_pawn())
+            && mv.to.rank() == last_rank(game.turn());
+        if promoting && mv.promotion.is_none() {
+            mv.promotion = Some(players[side].choose_promotion(&game));
+        }
+        if !game.legal_moves().contains(&mv) {
+            view.message(&format!(
+                "{} played the illegal move {} and forfeits the game",
+                players[side].name(),
+                mv
+            ));
+            return (game, resigned);
+        }
+
+        if let Some(clock) = &mut clock {
+            match clock.stop() {
+                Some(remaining) => record.add_clock_time(remaining),
+                None => return (game, flag_fall(&game, players[side].name(), view)),
+            }
+        }
+
+        view.message(&format!("{} plays {}", players[side].name(), game.san(mv)));
+        let before = game;
+        history.make_move(&mut game, mv);
+        record.add_move(mv);
+        players[other].opponent_moved(&before, mv);
+
+        match game.outcome() {
+            Some(Outcome::Checkmate { winner }) => return (game, GameEnd::Checkmate { winner }),
+            Some(Outcome::Stalemate) => return (game, GameEnd::Stalemate),
+            None if game.insufficient_material() => return (game, GameEnd::InsufficientMaterial),
+            None if history.threefold() => return (game, GameEnd::Repetition),
+            None if history.fifty_moves() => return (game, GameEnd::FiftyMoves),
+            None => {}
+        }
+        if game.in_check() {
+            status = Some(format!("{}, you're in check!", team_name(game.turn())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ClockMode;
+    use crate::view::{Orientation, Perspective};
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    // plays the given moves in order, then resigns
+    struct ScriptedPlayer {
+        moves: Vec<Move>,
+    }
+
+    impl ScriptedPlayer {
+        fn boxed(moves: &[&str]) -> Box<dyn Player> {
+            let moves = moves
+                .iter()
+                .rev()
+                .map(|coords| Move::from_coords(coords).unwrap())
+                .collect();
+            Box::new(ScriptedPlayer { moves })
+        }
+    }
+
+    impl Player for ScriptedPlayer {
+        fn name(&self) -> &str {
+            "script"
+        }
+        fn choose_move(
+            &mut self,
+            _game: &Game,
+            _record: &mut GameRecord,
+        ) -> Result<Action, String> {
+            Ok(self.moves.pop().map_or(Action::Resign, Action::Move))
+        }
+    }
+
+    // thinks for a while before every move, to run a clock down
+    struct SlowPlayer {
+        think: Duration,
+    }
+
+    impl Player for SlowPlayer {
+        fn name(&self) -> &str {
+            "slow"
+        }
+        fn choose_move(&mut self, game: &Game, _record: &mut GameRecord) -> Result<Action, String> {
+            std::thread::sleep(self.think);
+            Ok(Action::Move(game.legal_moves()[0]))
+        }
+    }
+
+    #[derive(Default)]
+    struct QuietView {
+        messages: Vec<String>,
+    }
+
+    impl View for QuietView {
+        fn show_position(&mut self, _game: &Game, _record: &GameRecord, _clock: Option<&Clock>) {}
+        fn message(&mut self, text: &str) {
+            self.messages.push(text.to_string());
+        }
+        fn warning(&mut self, text: &str) {
+            self.messages.push(text.to_string());
+        }
+        fn game_over(&mut self, _game: &Game, _record: &GameRecord, _lines: &[String]) {}
+    }
+
+    fn spec(spec: &str) -> Result<Box<dyn Player>, String> {
+        let assistance = Rc::new(Assistance {
+            book: None,
+            endgame_tables: None,
+        });
+        let perspective = Rc::new(Cell::new(Perspective::new(
+            Orientation::White,
+            [true, false],
+        )));
+        let limit = EngineLimit::Depth(1);
+        player_from_spec(spec, White, &assistance, limit, None, &perspective)
+    }
+
+    fn chosen(player: &mut Box<dyn Player>, fen: &str) -> Move {
+        let game = Game::from_fen(fen).unwrap();
+        match player.choose_move(&game, &mut GameRecord::new()) {
+            Ok(Action::Move(mv)) => mv,
+            _ => panic!("no move from {fen}"),
+        }
+    }
+
+    #[test]
+    fn counts_material_from_either_side() {
+        assert_eq!(material_balance(&Game::init(), White), 0);
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(material_balance(&game, White), piece_value(Queen));
+        assert_eq!(material_balance(&game, Black), -piece_value(Queen));
+    }
+
+    #[test]
+    fn bots_play_on_the_clock_when_there_is_one() {
+        let limit = EngineLimit::Depth(3);
+        assert!(matches!(clock_limit(limit, None), EngineLimit::Depth(3)));
+        let control = TimeControl::parse("1+2", ClockMode::Fischer).unwrap();
+        let limit = clock_limit(limit, Some(&Clock::new(control)));
+        let EngineLimit::Clock {
+            white_time,
+            black_increment,
+            ..
+        } = limit
+        else {
+            panic!("expected a clock limit");
+        };
+        assert_eq!(white_time, Duration::from_secs(60));
+        assert_eq!(black_increment, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn builds_players_from_specs() {
+        let random = spec("random:seed=3,name=Rando").unwrap();
+        assert_eq!(random.name(), "Rando");
+        assert!(!random.interactive());
+        assert_eq!(spec("greedy").unwrap().name(), "greedy");
+        assert!(spec("human").unwrap().interactive());
+        assert_eq!(
+            spec("random:colour=blue").err().unwrap(),
+            "Error: Unknown random setting 'colour'"
+        );
+        assert!(spec("random:seed").is_err());
+        assert!(spec("bot:nobody").is_err());
+    }
+
+    #[test]
+    fn greedy_takes_the_biggest_capture() {
+        let mut greedy = spec("greedy:seed=5").unwrap();
+        let fen = "4k3/8/8/3q4/8/3n4/3R4/4K3 w - - 0 1";
+        assert_eq!(chosen(&mut greedy, fen), Move::from_coords("d2d3").unwrap());
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(
+            chosen(&mut greedy, &game.fen()),
+            Move::from_coords("d1d5").unwrap()
+        );
+        // only takes a draw when behind
+        let behind = Game::from_fen("3qk3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        let ahead = Game::from_fen("3qk3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(greedy.accept_draw(&behind));
+        assert!(!greedy.accept_draw(&ahead));
+    }
+
+    #[test]
+    fn random_players_repeat_with_a_seed() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1";
+        let game = Game::from_fen(fen).unwrap();
+        let mut first = spec("random:seed=11").unwrap();
+        let mut second = spec("random:seed=11").unwrap();
+        for _ in 0..5 {
+            let mv = chosen(&mut first, fen);
+            assert!(game.legal_moves().contains(&mv));
+            assert_eq!(chosen(&mut second, fen), mv);
+        }
+    }
+
+    #[test]
+    fn plays_a_game_to_checkmate() {
+        let mut players = [
+            ScriptedPlayer::boxed(&["f2f3", "g2g4"]),
+            ScriptedPlayer::boxed(&["e7e5", "d8h4"]),
+        ];
+        let mut record = GameRecord::new();
+        let mut view = QuietView::default();
+        let (game, end) = play_game(&mut players, &mut record, None, &mut view);
+        assert!(matches!(end, GameEnd::Checkmate { winner: Black }));
+        assert_eq!(record.moves.len(), 4);
+        assert!(game.in_check());
+        assert!(view.messages.contains(&"script plays Qh4#".to_string()));
+    }
+
+    #[test]
+    fn repeating_the_position_three_times_draws() {
+        let shuffle = |out: &str, back: &str| {
+            let line = [out, back, out, back];
+            ScriptedPlayer::boxed(&line)
+        };
+        let mut players = [shuffle("g1f3", "f3g1"), shuffle("g8f6", "f6g8")];
+        let mut record = GameRecord::new();
+        let mut view = QuietView::default();
+        let (game, end) = play_game(&mut players, &mut record, None, &mut view);
+        assert!(matches!(end, GameEnd::Repetition));
+        assert_eq!(record.moves.len(), 8);
+        assert_eq!(game.hash(), Game::init().hash());
+    }
+
+    #[test]
+    fn illegal_moves_and_resignations_lose() {
+        let mut view = QuietView::default();
+        let mut players = [ScriptedPlayer::boxed(&["e2e5"]), ScriptedPlayer::boxed(&[])];
+        let (_, end) = play_game(&mut players, &mut GameRecord::new(), None, &mut view);
+        assert!(matches!(end, GameEnd::Resigned { loser: White }));
+        assert!(view
+            .messages
+            .iter()
+            .any(|message| message.contains("illegal move e2e5")));
+
+        let mut players = [ScriptedPlayer::boxed(&["e2e4"]), ScriptedPlayer::boxed(&[])];
+        let (_, end) = play_game(&mut players, &mut GameRecord::new(), None, &mut view);
+        assert!(matches!(end, GameEnd::Resigned { loser: Black }));
+    }
+
+    #[test]
+    fn flag_falls_end_the_game() {
+        let control = TimeControl::parse("0.0005", ClockMode::Fischer).unwrap();
+        let mut view = QuietView::default();
+        let mut players: [Box<dyn Player>; 2] = [
+            Box::new(SlowPlayer {
+                think: Duration::from_millis(50),
+            }),
+            ScriptedPlayer::boxed(&[]),
+        ];
+        let (_, end) = play_game(
+            &mut players,
+            &mut GameRecord::new(),
+            Some(control),
+            &mut view,
+        );
+        assert!(matches!(
+            end,
+            GameEnd::OutOfTime {
+                loser: White,
+                drawn: false
+            }
+        ));
+        assert!(view.messages.contains(&"slow ran out of time".to_string()));
+    }
+
+    #[test]
+    fn losing_on_time_to_a_bare_king_draws() {
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        let end = flag_fall(&game, "Blue", &mut QuietView::default());
+        assert!(matches!(
+            end,
+            GameEnd::OutOfTime {
+                loser: White,
+                drawn: true
+            }
+        ));
+    }
+
+    // a console human reading these lines; the sender keeps the input open until dropped
+    fn typist(lines: &[&str]) -> (HumanPlayer, mpsc::Sender<Result<String, String>>) {
+        let (sender, receiver) = mpsc::channel();
+        for line in lines {
+            sender.send(Ok(line.to_string())).unwrap();
+        }
+        let assistance = Rc::new(Assistance {
+            book: None,
+            endgame_tables: None,
+        });
+        let perspective = Rc::new(Cell::new(Perspective::new(
+            Orientation::White,
+            [true, false],
+        )));
+        let input = Arc::new(Mutex::new(receiver));
+        let player = HumanPlayer::with_input(White, assistance, perspective, input);
+        (player, sender)
+    }
+
+    #[test]
+    fn humans_type_moves_and_quit_at_the_end_of_input() {
+        let (mut player, sender) = typist(&["e2 e5", "e2", "e4"]);
+        drop(sender);
+        let game = Game::init();
+        let mut record = GameRecord::new();
+        assert!(matches!(
+            player.choose_move(&game, &mut record),
+            Ok(Action::Move(mv)) if mv == Move::from_coords("e2e4").unwrap()
+        ));
+        assert!(matches!(
+            player.choose_move(&game, &mut record),
+            Ok(Action::Resign)
+        ));
+    }
+
+    #[test]
+    fn waiting_humans_lose_on_time() {
+        let (player, _sender) = typist(&[]);
+        let control = TimeControl::parse("0.0005", ClockMode::Fischer).unwrap();
+        let mut players: [Box<dyn Player>; 2] = [Box::new(player), ScriptedPlayer::boxed(&[])];
+        let mut view = QuietView::default();
+        let (_, end) = play_game(
+            &mut players,
+            &mut GameRecord::new(),
+            Some(control),
+            &mut view,
+        );
+        assert!(matches!(
+            end,
+            GameEnd::OutOfTime {
+                loser: White,
+                drawn: false
+            }
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// xorshift64, plenty for picking moves; seeded from the clock unless a seed is given
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new() -> Rng {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+        Rng::with_seed(seed)
+    }
+    pub fn with_seed(seed: u64) -> Rng {
+        Rng { state: seed | 1 }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+    // uniform in 0..bound, bound must be positive
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+    pub fn choose<T: Copy>(&mut self, items: &[T]) -> Option<T> {
+        if items.is_empty() {
+            return None;
+        }
+        Some(items[self.below(items.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_repeat_their_sequence() {
+        let mut first = Rng::with_seed(42);
+        let mut second = Rng::with_seed(42);
+        let mut other = Rng::with_seed(44);
+        let sequence: Vec<u64> = (0..8).map(|_| first.next_u64()).collect();
+        assert_eq!(
+            sequence,
+            (0..8).map(|_| second.next_u64()).collect::<Vec<u64>>()
+        );
+        assert_ne!(
+            sequence,
+            (0..8).map(|_| other.next_u64()).collect::<Vec<u64>>()
+        );
+    }
+
+    #[test]
+    fn a_zero_seed_still_moves() {
+        let mut rng = Rng::with_seed(0);
+        assert_ne!(rng.next_u64(), 0);
+        assert_ne!(rng.next_u64(), rng.next_u64());
+    }
+
+    #[test]
+    fn below_covers_the_whole_range() {
+        let mut rng = Rng::with_seed(7);
+        let mut counts = [0; 6];
+        for _ in 0..6000 {
+            counts[rng.below(6)] += 1;
+        }
+        assert!(
+            counts.iter().all(|count| (800..1200).contains(count)),
+            "{counts:?}"
+        );
+        assert_eq!(rng.choose::<u8>(&[]), None);
+        assert_eq!(rng.choose(&['x']), Some('x'));
+    }
+}
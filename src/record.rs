@@ -0,0 +1,87 @@
+use crate::clock::format_time;
+use crate::moves::Move;
+use crate::player::team_name;
+use crate::Team::{self, *};
+use std::time::Duration;
+
+// everything worth keeping about a game once it is over
+#[derive(Debug, Clone, Default)]
+pub struct GameRecord {
+    pub moves: Vec<Move>,
+    pub white_hints: u32,
+    pub black_hints: u32,
+    // with a clock, the mover's time left after each move
+    pub clock_times: Vec<Duration>,
+}
+
+impl GameRecord {
+    pub fn new() -> GameRecord {
+        GameRecord::default()
+    }
+    pub fn add_move(&mut self, mv: Move) {
+        self.moves.push(mv);
+    }
+    pub fn add_clock_time(&mut self, remaining: Duration) {
+        self.clock_times.push(remaining);
+    }
+    pub fn add_hint(&mut self, team: Team) {
+        match team {
+            White => self.white_hints += 1,
+            Black => self.black_hints += 1,
+        }
+    }
+    pub fn summary(&self) -> String {
+        let moves: Vec<String> = self
+            .moves
+            .iter()
+            .enumerate()
+            .map(|(index, mv)| match self.clock_times.get(index) {
+                Some(time) => format!("{mv} ({})", format_time(*time)),
+                None => mv.to_string(),
+            })
+            .collect();
+        format!(
+            "Moves: {}\nHints used: {} {}, {} {}",
+            if moves.is_empty() {
+                "none".to_string()
+            } else {
+                moves.join(" ")
+            },
+            team_name(White),
+            self.white_hints,
+            team_name(Black),
+            self.black_hints
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_lists_moves_and_hints() {
+        let mut record = GameRecord::new();
+        assert_eq!(
+            record.summary(),
+            format!(
+                "Moves: none\nHints used: {} 0, {} 0",
+                team_name(White),
+                team_name(Black)
+            )
+        );
+        record.add_move(Move::from_coords("e2e4").unwrap());
+        record.add_move(Move::from_coords("e7e5").unwrap());
+        record.add_hint(White);
+        record.add_hint(White);
+        record.add_hint(Black);
+        assert_eq!(
+            record.summary(),
+            format!(
+                "Moves: e2e4 e7e5\nHints used: {} 2, {} 1",
+                team_name(White),
+                team_name(Black)
+            )
+        );
+    }
+}
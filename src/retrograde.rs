@@ -0,0 +1,719 @@
+use crate::moves::Move;
+use crate::PieceType::{self, *};
+use crate::Team::{self, *};
+use crate::{Game, Piece, Square};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+pub const TABLE_NAMES: [&str; 4] = ["KQK", "KRK", "KPK", "KBNK"];
+
+const MAGIC: &[u8; 4] = b"CCTB";
+
+// an entry is the distance to mate in plies plus one, from the stronger side's point of view
+const DRAW: u8 = 0;
+const INVALID: u8 = u8::MAX;
+
+// the result for the side to move, with distances in plies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EndgameResult {
+    Win(u32),
+    Loss(u32),
+    Draw,
+}
+
+impl EndgameResult {
+    // quicker wins and slower losses rank higher
+    fn rank(self) -> i64 {
+        match self {
+            EndgameResult::Win(plies) => 1000 - plies as i64,
+            EndgameResult::Loss(plies) => -1000 + plies as i64,
+            EndgameResult::Draw => 0,
+        }
+    }
+}
+
+// the order pieces appear in table names
+fn piece_order(piece_type: PieceType) -> usize {
+    match piece_type {
+        King => 0,
+        Queen => 1,
+        Rook => 2,
+        Bishop => 3,
+        Knight => 4,
+        Pawn => 5,
+    }
+}
+
+fn piece_type_from_letter(letter: char) -> Option<PieceType> {
+    match letter {
+        'Q' => Some(Queen),
+        'R' => Some(Rook),
+        'B' => Some(Bishop),
+        'N' => Some(Knight),
+        'P' => Some(Pawn),
+        _ => None,
+    }
+}
+
+// "KBNK" -> [Bishop, Knight]: the pieces the stronger (white) side has beside its king
+fn parse_name(name: &str) -> Option<Vec<PieceType>> {
+    let inner = name.strip_prefix('K')?.strip_suffix('K')?;
+    inner.chars().map(piece_type_from_letter).collect()
+}
+
+// pawnless tables put the white king in the a1-d1-d4 triangle, pawn tables on files a-d
+fn in_king_region(square: (usize, usize), has_pawn: bool) -> bool {
+    let (rank, file) = square;
+    if has_pawn {
+        file <= 3
+    } else {
+        file <= 3 && rank <= file
+    }
+}
+
+fn king_region(has_pawn: bool) -> Vec<Square> {
+    (0..64)
+        .map(|index| Square::new(index / 8, index % 8))
+        .filter(|square| in_king_region((square.rank(), square.file()), has_pawn))
+        .collect()
+}
+
+// the board symmetries that keep the rules intact: all eight without pawns, only the
+// left-right mirror with them
+fn transform(square: Square, symmetry: usize) -> Square {
+    let (mut rank, mut file) = (square.rank(), square.file());
+    if symmetry & 4 != 0 {
+        (rank, file) = (file, rank);
+    }
+    if symmetry & 2 != 0 {
+        rank = 7 - rank;
+    }
+    if symmetry & 1 != 0 {
+        file = 7 - file;
+    }
+    Square::new(rank, file)
+}
+
+pub struct EndgameTable {
+    name: String,
+    // white's pieces beside the king, in index order; black only has its king
+    pieces: Vec<PieceType>,
+    has_pawn: bool,
+    region: Vec<Square>,
+    values: Vec<u8>,
+}
+
+impl EndgameTable {
+    fn empty(name: &str) -> Result<EndgameTable, String> {
+        let pieces = parse_name(name)
+            .filter(|pieces| !pieces.is_empty())
+            .ok_or_else(|| format!("Error: Unknown endgame {name}"))?;
+        let has_pawn = pieces.contains(&Pawn);
+        let region = king_region(has_pawn);
+        let size = 2 * region.len() * 64usize.pow(pieces.len() as u32 + 1);
+        Ok(EndgameTable {
+            name: name.to_string(),
+            pieces,
+            has_pawn,
+            region,
+            values: vec![INVALID; size],
+        })
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    // squares are ordered white king, white pieces, black king
+    fn raw_index(&self, squares: &[Square], black_to_move: bool) -> Option<usize> {
+        let king_slot = self
+            .region
+            .iter()
+            .position(|square| *square == squares[0])?;
+        let mut index = black_to_move as usize * self.region.len() + king_slot;
+        for square in &squares[1..] {
+            index = index * 64 + square.index();
+        }
+        Some(index)
+    }
+    fn decode(&self, mut index: usize) -> (Vec<Square>, bool) {
+        let mut squares = vec![];
+        for _ in 0..=self.pieces.len() {
+            squares.push(Square::new(index % 64 / 8, index % 8));
+            index /= 64;
+        }
+        squares.push(self.region[index % self.region.len()]);
+        squares.reverse();
+        (squares, index >= self.region.len())
+    }
+    // the smallest index among the symmetric copies of a position, so each has exactly one
+    fn canonical_index(&self, squares: &[Square], black_to_move: bool) -> Option<usize> {
+        let symmetries: &[usize] = if self.has_pawn {
+            &[0, 1]
+        } else {
+            &[0, 1, 2, 3, 4, 5, 6, 7]
+        };
+        symmetries
+            .iter()
+            .filter_map(|symmetry| {
+                let transformed: Vec<Square> = squares
+                    .iter()
+                    .map(|square| transform(*square, *symmetry))
+                    .collect();
+                self.raw_index(&transformed, black_to_move)
+            })
+            .min()
+    }
+    fn build(&self, squares: &[Square], black_to_move: bool) -> Option<Game> {
+        for (index, square) in squares.iter().enumerate() {
+            if squares[..index].contains(square) {
+                return None;
+            }
+        }
+        let mut game = Game::init();
+        game.board = [[None; 8]; 8];
+        game.white_king_moved = true;
+        game.black_king_moved = true;
+        game.en_passant_pawn = None;
+        game.turn = if black_to_move { Black } else { White };
+        let white_pieces = std::iter::once(King).chain(self.pieces.iter().copied());
+        for (square, piece_type) in squares.iter().zip(white_pieces) {
+            if piece_type == Pawn && (square.rank() == 0 || square.rank() == 7) {
+                return None;
+            }
+            game.board[square.rank()][square.file()] = Some(Piece::new(piece_type, White));
+        }
+        let black_king = squares[squares.len() - 1];
+        game.board[black_king.rank()][black_king.file()] = Some(Piece::new(King, Black));
+        // the side that just moved can never be left in check
+        let mut previous = game;
+        previous.switch_turn();
+        (!previous.in_check()).then_some(game)
+    }
+    fn squares_of(&self, game: &Game) -> Vec<Square> {
+        let mut squares = vec![game.find_king(White)];
+        for piece_type in &self.pieces {
+            let square = game
+                .find_team_pieces(White)
+                .into_iter()
+                .find(|square| game.get_piece(*square).unwrap().piece_type() == *piece_type)
+                .unwrap();
+            squares.push(square);
+        }
+        squares.push(game.find_king(Black));
+        squares
+    }
+    fn lookup(&self, game: &Game) -> Option<EndgameResult> {
+        let index = self.canonical_index(&self.squares_of(game), game.is_black_turn())?;
+        match self.values[index] {
+            INVALID => None,
+            DRAW => Some(EndgameResult::Draw),
+            value if game.is_white_turn() => Some(EndgameResult::Win(value as u32 - 1)),
+            value => Some(EndgameResult::Loss(value as u32 - 1)),
+        }
+    }
+
+    // retrograde analysis: mates first, then alternately the white positions that can reach a
+    // loss for black and the black positions whose every move reaches a white win
+    fn generate(name: &str, known: &EndgameTables) -> Result<EndgameTable, String> {
+        let mut table = EndgameTable::empty(name)?;
+        let mut frontier = vec![];
+        // promotions leave the table, so their results come from the smaller tables up front
+        let mut promotions: Vec<(u32, usize)> = vec![];
+        for index in 0..table.values.len() {
+            let (squares, black_to_move) = table.decode(index);
+            if table.canonical_index(&squares, black_to_move) != Some(index) {
+                continue;
+            }
+            let Some(game) = table.build(&squares, black_to_move) else {
+                continue;
+            };
+            table.values[index] = DRAW;
+            if black_to_move {
+                if game.in_check() && game.legal_moves().is_empty() {
+                    table.values[index] = 1;
+                    frontier.push(index);
+                }
+            } else if table.has_pawn {
+                let fastest = game
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|mv| mv.promotion.is_some())
+                    .filter_map(|mv| {
+                        let mut after = game;
+                        after.make_legal_move(mv);
+                        match known.probe(&after)? {
+                            EndgameResult::Loss(plies) => Some(plies + 1),
+                            _ => None,
+                        }
+                    })
+                    .min();
+                if let Some(plies) = fastest {
+                    promotions.push((plies, index));
+                }
+            }
+        }
+
+        let mut ply: u32 = 0;
+        while !frontier.is_empty() || promotions.iter().any(|(plies, _)| *plies > ply) {
+            ply += 1;
+            if ply + 1 >= INVALID as u32 {
+                return Err(format!("Error: {name} mates are too long to store"));
+            }
+            let mut next = vec![];
+            if ply % 2 == 1 {
+                for index in &frontier {
+                    for previous in table.predecessors(*index) {
+                        if table.values[previous] == DRAW {
+                            table.values[previous] = ply as u8 + 1;
+                            next.push(previous);
+                        }
+                    }
+                }
+                for (plies, index) in &promotions {
+                    if *plies == ply && table.values[*index] == DRAW {
+                        table.values[*index] = ply as u8 + 1;
+                        next.push(*index);
+                    }
+                }
+            } else {
+                for index in &frontier {
+                    for previous in table.predecessors(*index) {
+                        if table.values[previous] == DRAW && table.is_lost(previous) {
+                            table.values[previous] = ply as u8 + 1;
+                            next.push(previous);
+                        }
+                    }
+                }
+            }
+            frontier = next;
+        }
+        Ok(table)
+    }
+    // positions one move earlier, with the other side to move
+    fn predecessors(&self, index: usize) -> Vec<usize> {
+        let (squares, black_to_move) = self.decode(index);
+        let game = self.build(&squares, black_to_move).unwrap();
+        let mover = if black_to_move { White } else { Black };
+        let mut previous = vec![];
+        for (slot, square) in squares.iter().enumerate() {
+            let piece = game.get_piece(*square).unwrap();
+            if piece.team() != mover {
+                continue;
+            }
+            // every piece but a pawn moves the same way backwards as forwards
+            let origins: Vec<Square> = if piece.is_pawn() {
+                let mut origins = vec![];
+                let behind = Square::new(square.rank() - 1, square.file());
+                if square.rank() >= 2 && game.get_piece(behind).is_none() {
+                    origins.push(behind);
+                    let start = Square::new(1, square.file());
+                    if square.rank() == 3 && game.get_piece(start).is_none() {
+                        origins.push(start);
+                    }
+                }
+                origins
+            } else {
+                game.get_possible_moves(mover, *square)
+                    .into_iter()
+                    .filter(|origin| game.get_piece(*origin).is_none())
+                    .collect()
+            };
+            for origin in origins {
+                let mut before = squares.clone();
+                before[slot] = origin;
+                if let Some(before_index) = self.canonical_index(&before, !black_to_move) {
+                    if self.values[before_index] != INVALID {
+                        previous.push(before_index);
+                    }
+                }
+            }
+        }
+        previous
+    }
+    // black to move and every reply, none of them captures, walks into a known white win
+    fn is_lost(&self, index: usize) -> bool {
+        let (squares, black_to_move) = self.decode(index);
+        let game = self.build(&squares, black_to_move).unwrap();
+        let moves = game.legal_moves();
+        !moves.is_empty()
+            && moves.iter().all(|mv| {
+                let mut after = squares.clone();
+                *after.last_mut().unwrap() = mv.to;
+                !game.is_capture(*mv)
+                    && self
+                        .canonical_index(&after, false)
+                        .is_some_and(|after_index| {
+                            !matches!(self.values[after_index], DRAW | INVALID)
+                        })
+            })
+    }
+    fn longest_mate(&self) -> u32 {
+        self.values
+            .iter()
+            .filter(|value| **value != INVALID)
+            .map(|value| *value as u32)
+            .max()
+            .unwrap_or(0)
+            .saturating_sub(1)
+    }
+
+    fn save(&self, directory: &Path) -> Result<(), String> {
+        let path = directory.join(format!("{}.tb", self.name));
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&self.values);
+        fs::write(&path, bytes)
+            .map_err(|err| format!("Error: Could not write {}: {err}", path.display()))
+    }
+    fn load(directory: &Path, name: &str) -> Result<Option<EndgameTable>, String> {
+        let path = directory.join(format!("{name}.tb"));
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes = fs::read(&path)
+            .map_err(|err| format!("Error: Could not read {}: {err}", path.display()))?;
+        let mut table = EndgameTable::empty(name)?;
+        match bytes.strip_prefix(MAGIC) {
+            Some(values) if values.len() == table.values.len() => {
+                table.values = values.to_vec();
+                Ok(Some(table))
+            }
+            _ => Err(format!("Error: {} is not a {name} table", path.display())),
+        }
+    }
+}
+
+// mirrors the board top to bottom and swaps the colours, so black's win reads as white's
+fn flip_colors(game: &Game) -> Game {
+    let mut flipped = *game;
+    for rank in 0..8 {
+        for file in 0..8 {
+            flipped.board[7 - rank][file] = game.board[rank][file].map(|piece| {
+                let team = if piece.team() == White { Black } else { White };
+                Piece::new(piece.piece_type(), team)
+            });
+        }
+    }
+    flipped.en_passant_pawn = None;
+    flipped.switch_turn();
+    flipped
+}
+
+fn extra_pieces(game: &Game, team: Team) -> Vec<PieceType> {
+    let mut pieces: Vec<PieceType> = game
+        .find_team_pieces(team)
+        .into_iter()
+        .map(|square| game.get_piece(square).unwrap().piece_type())
+        .filter(|piece_type| *piece_type != King)
+        .collect();
+    pieces.sort_by_key(|piece_type| piece_order(*piece_type));
+    pieces
+}
+
+// the tables have no castling, which only matters while a king and rook sit at home
+fn may_castle(game: &Game) -> bool {
+    let home = |rank: usize, file: usize, piece_type: PieceType, team: Team| {
+        game.get_piece(Square::new(rank, file)) == Some(Piece::new(piece_type, team))
+    };
+    let white = !game.white_king_moved
+        && home(0, 4, King, White)
+        && ((!game.white_rook_a_moved && home(0, 0, Rook, White))
+            || (!game.white_rook_h_moved && home(0, 7, Rook, White)));
+    let black = !game.black_king_moved
+        && home(7, 4, King, Black)
+        && ((!game.black_rook_a_moved && home(7, 0, Rook, Black))
+            || (!game.black_rook_h_moved && home(7, 7, Rook, Black)));
+    white || black
+}
+
+#[derive(Default)]
+pub struct EndgameTables {
+    tables: Vec<EndgameTable>,
+}
+
+impl EndgameTables {
+    // every known table present in the directory
+    pub fn load(directory: &str) -> Result<EndgameTables, String> {
+        let mut tables = EndgameTables::default();
+        for name in TABLE_NAMES {
+            if let Some(table) = EndgameTable::load(Path::new(directory), name)? {
+                tables.tables.push(table);
+            }
+        }
+        if tables.tables.is_empty() {
+            return Err(format!("Error: No endgame tables found in {directory}"));
+        }
+        Ok(tables)
+    }
+    pub fn names(&self) -> Vec<&str> {
+        self.tables.iter().map(|table| table.name()).collect()
+    }
+    // exact result for the side to move, or `None` when no table covers the position
+    pub fn probe(&self, game: &Game) -> Option<EndgameResult> {
+        let white = extra_pieces(game, White);
+        let black = extra_pieces(game, Black);
+        if white.len() + black.len() > 2 {
+            return None;
+        }
+        let (strong, position) = match (white.is_empty(), black.is_empty()) {
+            (true, true) => return Some(EndgameResult::Draw),
+            (false, true) => (white, *game),
+            (true, false) => (black, flip_colors(game)),
+            (false, false) => return None,
+        };
+        // a lone minor piece cannot mate
+        if strong == [Bishop] || strong == [Knight] {
+            return Some(EndgameResult::Draw);
+        }
+        if may_castle(game) {
+            return None;
+        }
+        self.tables
+            .iter()
+            .find(|table| table.pieces == strong)?
+            .lookup(&position)
+    }
+    // the move that keeps the best result, e.g. the fastest mate or the longest defence
+    pub fn best_move(&self, game: &Game) -> Option<(Move, EndgameResult)> {
+        self.probe(game)?;
+        game.legal_moves()
+            .into_iter()
+            .filter_map(|mv| {
+                let mut after = *game;
+                after.make_legal_move(mv);
+                let result = match self.probe(&after)? {
+                    EndgameResult::Win(plies) => EndgameResult::Loss(plies + 1),
+                    EndgameResult::Loss(plies) => EndgameResult::Win(plies + 1),
+                    EndgameResult::Draw => EndgameResult::Draw,
+                };
+                Some((mv, result))
+            })
+            .max_by_key(|(_, result)| result.rank())
+    }
+}
+
+// `--generate-tables <dir> [KQK KRK KPK KBNK]`
+pub fn run_generate(args: &[String]) -> Result<(), String> {
+    let Some((directory, names)) = args.split_first() else {
+        return Err("Usage: --generate-tables <dir> [KQK KRK KPK KBNK]".to_string());
+    };
+    let mut wanted: Vec<&str> = names.iter().map(|name| name.as_str()).collect();
+    if wanted.is_empty() {
+        wanted = TABLE_NAMES.to_vec();
+    }
+    if let Some(unknown) = wanted.iter().find(|name| !TABLE_NAMES.contains(name)) {
+        return Err(format!(
+            "Error: Unknown endgame {unknown}, choose from {}",
+            TABLE_NAMES.join(" ")
+        ));
+    }
+    // pawn endings promote into the queen and rook tables, so those come first
+    if wanted.contains(&"KPK") {
+        wanted.extend(["KQK", "KRK"]);
+    }
+    fs::create_dir_all(directory)
+        .map_err(|err| format!("Error: Could not create {directory}: {err}"))?;
+
+    let mut tables = EndgameTables::default();
+    for name in TABLE_NAMES.iter().filter(|name| wanted.contains(name)) {
+        let start = Instant::now();
+        let table = EndgameTable::generate(name, &tables)?;
+        table.save(Path::new(directory))?;
+        let positions = table
+            .values
+            .iter()
+            .filter(|value| **value != INVALID)
+            .count();
+        let wins = table
+            .values
+            .iter()
+            .filter(|value| !matches!(**value, DRAW | INVALID))
+            .count();
+        println!(
+            "{name}: {positions} positions, {wins} decisive, longest mate {} moves ({:.1}s)",
+            table.longest_mate().div_ceil(2),
+            start.elapsed().as_secs_f64()
+        );
+        tables.tables.push(table);
+    }
+    println!("Wrote tables to {directory}");
+    Ok(())
+}
+
+// the side to move's result as a line for the `endgame` command
+pub fn describe(tables: &EndgameTables, game: &Game) -> Option<String> {
+    let result = tables.probe(game)?;
+    let side = |white: bool| crate::player::team_name(if white { White } else { Black });
+    let verdict = match result {
+        EndgameResult::Draw => "Draw with best play".to_string(),
+        EndgameResult::Win(plies) => format!(
+            "{} mates in {}",
+            side(game.is_white_turn()),
+            plies.div_ceil(2)
+        ),
+        EndgameResult::Loss(plies) => {
+            format!("{} mates in {}", side(game.is_black_turn()), plies / 2)
+        }
+    };
+    Some(match tables.best_move(game) {
+        Some((mv, _)) => format!("{verdict}, best move {} ({mv})", game.san(mv)),
+        None => verdict,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    // generated once per test run, pawn tables promote into the queen and rook ones
+    fn tables() -> &'static EndgameTables {
+        static TABLES: OnceLock<EndgameTables> = OnceLock::new();
+        TABLES.get_or_init(|| {
+            let directory = std::env::temp_dir().join(format!("tables-{}", std::process::id()));
+            let directory = directory.to_str().unwrap().to_string();
+            run_generate(&[directory.clone(), "KPK".to_string()]).unwrap();
+            let tables = EndgameTables::load(&directory).unwrap();
+            fs::remove_dir_all(&directory).unwrap();
+            tables
+        })
+    }
+
+    fn probe(fen: &str) -> Option<EndgameResult> {
+        tables().probe(&Game::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn loads_the_generated_tables() {
+        assert_eq!(tables().names(), ["KQK", "KRK", "KPK"]);
+    }
+
+    #[test]
+    fn finds_mates_in_one() {
+        assert_eq!(
+            probe("k7/8/1K6/8/8/8/8/3Q4 w - - 0 1"),
+            Some(EndgameResult::Win(1))
+        );
+        assert_eq!(
+            probe("k7/8/1K6/8/8/8/8/7R w - - 0 1"),
+            Some(EndgameResult::Win(1))
+        );
+        assert_eq!(
+            probe("k6Q/8/1K6/8/8/8/8/8 b - - 0 1"),
+            Some(EndgameResult::Loss(0))
+        );
+    }
+
+    #[test]
+    fn reads_black_wins_from_flipped_tables() {
+        assert_eq!(
+            probe("K7/8/1k6/8/8/8/8/3q4 b - - 0 1"),
+            Some(EndgameResult::Win(1))
+        );
+        assert_eq!(
+            probe("K7/8/1k6/8/8/8/8/7r w - - 0 1"),
+            probe("k7/8/1K6/8/8/8/8/7R b - - 0 1")
+        );
+    }
+
+    #[test]
+    fn draws_stalemates_captures_and_lone_kings() {
+        assert_eq!(
+            probe("k7/8/8/8/8/8/8/K7 w - - 0 1"),
+            Some(EndgameResult::Draw)
+        );
+        assert_eq!(
+            probe("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"),
+            Some(EndgameResult::Draw)
+        );
+        assert_eq!(
+            probe("k7/1Q6/8/8/8/8/8/7K b - - 0 1"),
+            Some(EndgameResult::Draw)
+        );
+        assert_eq!(
+            probe("k7/8/8/8/8/8/8/K5B1 w - - 0 1"),
+            Some(EndgameResult::Draw)
+        );
+    }
+
+    #[test]
+    fn separates_won_and_drawn_pawn_endings() {
+        assert!(matches!(
+            probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"),
+            Some(EndgameResult::Win(_))
+        ));
+        assert!(matches!(
+            probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"),
+            Some(EndgameResult::Loss(_))
+        ));
+        assert_eq!(
+            probe("k7/8/8/8/8/8/P7/K7 w - - 0 1"),
+            Some(EndgameResult::Draw)
+        );
+    }
+
+    #[test]
+    fn leaves_uncovered_positions_alone() {
+        assert_eq!(probe("k7/8/8/8/8/8/8/KQR5 w - - 0 1"), None);
+        assert_eq!(probe("k7/8/8/8/8/8/8/K5BN w - - 0 1"), None);
+        assert_eq!(probe("r3k3/8/8/8/8/8/8/4K3 b q - 0 1"), None);
+    }
+
+    #[test]
+    fn picks_the_fastest_mate() {
+        let game = Game::from_fen("k7/8/1K6/8/8/8/8/3Q4 w - - 0 1").unwrap();
+        let (mv, result) = tables().best_move(&game).unwrap();
+        assert_eq!(result, EndgameResult::Win(1));
+        let mut after = game;
+        after.make_legal_move(mv);
+        assert!(after.in_check() && after.legal_moves().is_empty());
+        let line = describe(tables(), &game).unwrap();
+        assert!(line.starts_with("Blue mates in 1, best move Q"), "{line}");
+    }
+
+    // a minute in release and far longer in debug, run with `cargo test --release -- --ignored`
+    #[test]
+    #[ignore]
+    fn bishop_and_knight_mates() {
+        let directory = std::env::temp_dir().join(format!("kbnk-tables-{}", std::process::id()));
+        let directory = directory.to_str().unwrap().to_string();
+        run_generate(&[directory.clone(), "KBNK".to_string()]).unwrap();
+        let tables = EndgameTables::load(&directory).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(tables.names(), ["KBNK"]);
+        // the longest bishop and knight mate takes 33 moves, 66 plies
+        assert_eq!(tables.tables[0].longest_mate(), 66);
+        let probe = |fen| tables.probe(&Game::from_fen(fen).unwrap());
+        assert_eq!(
+            probe("k5B1/3N4/1K6/8/8/8/8/8 w - - 0 1"),
+            Some(EndgameResult::Win(1))
+        );
+        assert_eq!(
+            probe("k5B1/3N4/1K6/8/8/8/8/8 b - - 0 1"),
+            Some(EndgameResult::Draw)
+        );
+        // taking the loose knight leaves a lone bishop
+        assert_eq!(
+            probe("k7/1N6/8/8/8/8/B7/K7 b - - 0 1"),
+            Some(EndgameResult::Draw)
+        );
+        assert!(matches!(
+            probe("k7/1N6/8/8/8/8/B7/K7 w - - 0 1"),
+            Some(EndgameResult::Win(_))
+        ));
+        assert!(matches!(
+            probe("8/8/8/3k4/8/8/8/KBN5 b - - 0 1"),
+            Some(EndgameResult::Loss(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_foreign_files() {
+        let directory = std::env::temp_dir().join(format!("bad-tables-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("KQK.tb"), b"CCTB1234").unwrap();
+        let loaded = EndgameTables::load(directory.to_str().unwrap());
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(loaded.is_err());
+        assert!(run_generate(&["unused".to_string(), "KXK".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::moves::{Move, Outcome};
+use crate::PieceType::{self, *};
+use crate::{Game, Square};
+
+fn piece_letter(piece_type: PieceType) -> &'static str {
+    match piece_type {
+        King => "K",
+        Queen => "Q",
+        Rook => "R",
+        Bishop => "B",
+        Knight => "N",
+        Pawn => "",
+    }
+}
+
+impl Game {
+    // standard algebraic notation for a legal move, e.g. "Nbd7", "exd5", "e8=Q+", "O-O"
+    pub fn san(&self, mv: Move) -> String {
+        let mut san = self.san_without_check(mv);
+        let mut after = *self;
+        after.make_legal_move(mv);
+        if let Some(Outcome::Checkmate { .. }) = after.outcome() {
+            san.push('#');
+        } else if after.in_check() {
+            san.push('+');
+        }
+        san
+    }
+    fn san_without_check(&self, mv: Move) -> String {
+        self.san_among(mv, &self.legal_moves())
+    }
+    // the position's legal moves tell whether the move needs a file or rank to be unambiguous
+    fn san_among(&self, mv: Move, legal: &[Move]) -> String {
+        let piece = self.get_piece(mv.from).unwrap();
+        if piece.is_king() && mv.from.file_diff(mv.to) > 1 {
+            if mv.to.file() == 6 { "O-O" } else { "O-O-O" }.to_string()
+        } else {
+            let mut san = piece_letter(piece.piece_type()).to_string();
+            let coords = mv.from.to_coords();
+            let (file, rank) = coords.split_at(1);
+            if piece.is_pawn() {
+                if self.is_capture(mv) {
+                    san.push_str(file);
+                }
+            } else {
+                // name the file, then the rank, then both, until the move is unambiguous
+                let rivals: Vec<&Move> = legal
+                    .iter()
+                    .filter(|other| {
+                        other.to == mv.to
+                            && other.from != mv.from
+                            && self.get_piece(other.from) == Some(piece)
+                    })
+                    .collect();
+                if !rivals.is_empty() {
+                    if rivals
+                        .iter()
+                        .all(|other| other.from.file() != mv.from.file())
+                    {
+                        san.push_str(file);
+                    } else if rivals
+                        .iter()
+                        .all(|other| other.from.rank() != mv.from.rank())
+                    {
+                        san.push_str(rank);
+                    } else {
+                        san.push_str(&coords);
+                    }
+                }
+            }
+            if self.is_capture(mv) {
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_coords());
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push_str(piece_letter(promotion));
+            }
+            san
+        }
+    }
+    // reads SAN as found in PGN files, tolerating check marks, annotations, "0-0" and "e8Q"
+    pub fn parse_san(&self, san: &str) -> Result<Move, String> {
+        let mut san = san.trim_end_matches(['+', '#', '!', '?']).replace('0', "O");
+        if san.ends_with(['Q', 'R', 'B', 'N']) && !san.contains('=') && san.len() > 2 {
+            san.insert(san.len() - 1, '=');
+        }
+        // only moves with the named piece, square and promotion are written out to compare
+        let (body, promotion) = san.split_once('=').unwrap_or((&san, ""));
+        let piece_type = [King, Queen, Rook, Bishop, Knight]
+            .into_iter()
+            .find(|piece_type| body.starts_with(piece_letter(*piece_type)))
+            .unwrap_or(Pawn);
+        let to = body
+            .get(body.len().saturating_sub(2)..)
+            .and_then(|coords| Square::from_coords(coords).ok());
+        let legal = self.legal_moves();
+        legal
+            .iter()
+            .copied()
+            .filter(|mv| {
+                let piece = self.get_piece(mv.from).unwrap();
+                if body.starts_with("O-O") {
+                    piece.is_king() && mv.from.file_diff(mv.to) > 1
+                } else {
+                    Some(mv.to) == to
+                        && piece.piece_type() == piece_type
+                        && mv.promotion.map_or("", piece_letter) == promotion
+                }
+            })
+            .find(|mv| self.san_among(*mv, &legal) == san)
+            .ok_or_else(|| format!("Error: Illegal or unknown move {san}"))
+    }
+    // SAN for a sequence of moves starting from this position
+    pub fn san_line(&self, moves: &[Move]) -> Vec<String> {
+        let mut position = *self;
+        let mut line = vec![];
+        for mv in moves {
+            line.push(position.san(*mv));
+            position.make_legal_move(*mv);
+        }
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::moves::Move;
+    use crate::Game;
+
+    fn position(fen: &str) -> Game {
+        Game::from_fen(fen).unwrap()
+    }
+
+    fn san(game: &Game, coords: &str) -> String {
+        game.san(Move::from_coords(coords).unwrap())
+    }
+
+    #[test]
+    fn pieces_pawns_and_captures() {
+        let game = Game::init();
+        assert_eq!(san(&game, "e2e4"), "e4");
+        assert_eq!(san(&game, "g1f3"), "Nf3");
+        let game = position("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(san(&game, "e4d5"), "exd5");
+        let game = position("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(san(&game, "e5d6"), "exd6");
+    }
+
+    #[test]
+    fn ambiguous_moves_name_file_then_rank_then_square() {
+        let game = position("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1");
+        assert_eq!(san(&game, "b1d2"), "Nbd2");
+        let game = position("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1");
+        assert_eq!(san(&game, "a1a3"), "R1a3");
+        let game = position("4k3/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1");
+        assert_eq!(san(&game, "a4d4"), "Qa4d4");
+    }
+
+    #[test]
+    fn castling_promotion_check_and_mate() {
+        let game = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(san(&game, "e1g1"), "O-O");
+        assert_eq!(san(&game, "e1c1"), "O-O-O");
+        let game = position("k7/4P3/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(san(&game, "e7e8q"), "e8=Q+");
+        assert_eq!(san(&game, "e7e8n"), "e8=N");
+        let game = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        assert_eq!(san(&game, "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn parses_san_as_written_in_pgn() {
+        let castles = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let promotes = position("3n3k/4P3/8/8/8/8/8/4K3 w - - 0 1");
+        let knights = position("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1");
+        for (game, text, coords) in [
+            (knights, "Nbd2", "b1d2"),
+            (knights, "Nfd2", "f1d2"),
+            (castles, "O-O", "e1g1"),
+            (castles, "0-0-0", "e1c1"),
+            (castles, "Rxa8+", "a1a8"),
+            (promotes, "exd8=Q", "e7d8q"),
+            (promotes, "exd8Q!?", "e7d8q"),
+            (promotes, "e8=N", "e7e8n"),
+        ] {
+            assert_eq!(
+                game.parse_san(text),
+                Ok(Move::from_coords(coords).unwrap()),
+                "{text}"
+            );
+        }
+        let game = promotes;
+        assert!(game.parse_san("Nf3").is_err());
+        assert!(game.parse_san("e8").is_err());
+        assert!(game.parse_san("exd8=K").is_err());
+        assert!(knights.parse_san("Nd2").is_err());
+    }
+
+    #[test]
+    fn san_line_follows_the_moves() {
+        let moves: Vec<Move> = ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5"]
+            .iter()
+            .map(|coords| Move::from_coords(coords).unwrap())
+            .collect();
+        assert_eq!(
+            Game::init().san_line(&moves),
+            ["e4", "e5", "Nf3", "Nc6", "Bb5"]
+        );
+    }
+}
@@ -0,0 +1,698 @@
+use crate::eval::{evaluate, evaluate_with, EvalParams, MATE_SCORE, MATE_THRESHOLD};
+use crate::moves::Move;
+use crate::nnue::{Accumulator, Network};
+use crate::ordering::{MoveOrderer, OrderingConfig};
+use crate::retrograde::{EndgameResult, EndgameTables};
+use crate::table::{Bound, TableEntry, TranspositionTable};
+use crate::Game;
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const INFINITY: i32 = MATE_SCORE + 1;
+const MAX_DEPTH: u32 = 64;
+pub const MAX_THREADS: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchStats {
+    pub nodes: u64,
+    pub quiescence_nodes: u64,
+    pub table_hits: u64,
+    pub beta_cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    pub tablebase_hits: u64,
+}
+
+impl SearchStats {
+    // share of cutoffs found on the first move tried, the usual measure of ordering quality
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        if self.beta_cutoffs == 0 {
+            return 0.0;
+        }
+        self.first_move_cutoffs as f64 / self.beta_cutoffs as f64
+    }
+}
+
+impl AddAssign for SearchStats {
+    fn add_assign(&mut self, other: SearchStats) {
+        self.nodes += other.nodes;
+        self.quiescence_nodes += other.quiescence_nodes;
+        self.table_hits += other.table_hits;
+        self.beta_cutoffs += other.beta_cutoffs;
+        self.first_move_cutoffs += other.first_move_cutoffs;
+        self.tablebase_hits += other.tablebase_hits;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub pv: Vec<Move>,
+    // the best root moves with their lines, best first; more than one only with MultiPV
+    pub lines: Vec<PvLine>,
+    pub stats: SearchStats,
+    pub time: Duration,
+    // one entry per search thread, the main thread first
+    pub threads: Vec<ThreadStats>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PvLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadStats {
+    pub nodes: u64,
+    pub depth: u32,
+    pub time: Duration,
+}
+
+impl SearchResult {
+    pub fn nodes_per_second(&self) -> u64 {
+        let millis = self.time.as_millis().max(1) as u64;
+        self.stats.nodes * 1000 / millis
+    }
+}
+
+// mate scores are stored relative to the node so they stay valid at any ply
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score > MATE_THRESHOLD {
+        score + ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score > MATE_THRESHOLD {
+        score - ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+// what bounds a search; with nothing set it runs until the stop flag is raised
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+}
+
+// spend a slice of the remaining time, keeping a reserve so we never flag
+pub fn allocate_time(
+    time_left: Duration,
+    increment: Duration,
+    moves_to_go: Option<u32>,
+) -> Duration {
+    let moves_to_go = moves_to_go.unwrap_or(30).max(1);
+    let budget = time_left / moves_to_go + increment * 3 / 4;
+    budget.min(time_left / 2).max(Duration::from_millis(10))
+}
+
+// one thread's share of a search: its own move ordering and counters over the shared table
+struct SearchWorker {
+    orderer: MoveOrderer,
+    table: Arc<TranspositionTable>,
+    tablebases: Option<Arc<EndgameTables>>,
+    network: Option<Arc<Network>>,
+    params: Option<Arc<EvalParams>>,
+    // the network's accumulator for each ply of the current line
+    accumulators: Vec<Accumulator>,
+    stats: SearchStats,
+    stop: Arc<AtomicBool>,
+    // raised by the main thread when it is done, so helper threads wind down with it
+    finished: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    aborted: bool,
+    multi_pv: usize,
+    // root moves already taken by earlier lines of this iteration
+    excluded: Vec<Move>,
+    root_best: Option<Move>,
+}
+
+impl SearchWorker {
+    fn new(
+        config: OrderingConfig,
+        table: Arc<TranspositionTable>,
+        tablebases: Option<Arc<EndgameTables>>,
+        network: Option<Arc<Network>>,
+        params: Option<Arc<EvalParams>>,
+        stop: Arc<AtomicBool>,
+        finished: Arc<AtomicBool>,
+    ) -> SearchWorker {
+        SearchWorker {
+            orderer: MoveOrderer::new(config),
+            table,
+            tablebases,
+            network,
+            params,
+            accumulators: vec![],
+            stats: SearchStats::default(),
+            stop,
+            finished,
+            deadline: None,
+            node_limit: None,
+            aborted: false,
+            multi_pv: 1,
+            excluded: vec![],
+            root_best: None,
+        }
+    }
+    // iterative deepening, reporting every completed iteration and returning the last one;
+    // an iteration cut short by the limits or the stop flag is thrown away
+    fn iterate(
+        &mut self,
+        game: &Game,
+        limits: SearchLimits,
+        start: Instant,
+        first_depth: u32,
+        mut report: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.stats = SearchStats::default();
+        self.deadline = limits.movetime.map(|movetime| start + movetime);
+        self.node_limit = limits.nodes;
+        self.aborted = false;
+        if let Some(network) = &self.network {
+            self.accumulators = vec![network.refresh(game)];
+        }
+
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            pv: vec![],
+            lines: vec![],
+            stats: self.stats,
+            time: Duration::ZERO,
+            threads: vec![],
+        };
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let line_count = self.multi_pv.min(game.legal_moves().len()).max(1);
+        for depth in first_depth.min(max_depth)..=max_depth {
+            // each further line searches the root again without the moves already listed
+            self.excluded.clear();
+            let mut lines = vec![];
+            let mut best_score = None;
+            while lines.len() < line_count {
+                self.root_best = None;
+                let score = self.negamax(game, depth, 0, -INFINITY, INFINITY, None);
+                if self.aborted {
+                    break;
+                }
+                best_score.get_or_insert(score);
+                let Some(root_move) = self.root_best else {
+                    break;
+                };
+                let mut child = *game;
+                child.make_legal_move(root_move);
+                let mut pv = vec![root_move];
+                pv.extend(self.principal_variation(&child, depth - 1));
+                lines.push(PvLine { score, pv });
+                self.excluded.push(root_move);
+            }
+            if self.aborted {
+                break;
+            }
+            let pv = lines.first().map_or(vec![], |line| line.pv.clone());
+            result = SearchResult {
+                best_move: pv.first().copied(),
+                score: best_score.unwrap_or(0),
+                depth,
+                pv,
+                lines,
+                stats: self.stats,
+                time: start.elapsed(),
+                threads: vec![],
+            };
+            report(&result);
+            // no point searching deeper once a forced mate is proven
+            if result.score.abs() > MATE_THRESHOLD {
+                break;
+            }
+        }
+        result.stats = self.stats;
+        result.time = start.elapsed();
+        result
+    }
+    fn should_stop(&mut self) -> bool {
+        if !self.aborted && self.stats.nodes.is_multiple_of(1024) {
+            self.aborted = self.stop.load(Ordering::Relaxed)
+                || self.finished.load(Ordering::Relaxed)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+                || self
+                    .node_limit
+                    .is_some_and(|limit| self.stats.nodes >= limit);
+        }
+        self.aborted
+    }
+    // brings the network's accumulator for `ply` up to date after moving from `parent`
+    fn enter(&mut self, parent: &Game, child: &Game, ply: usize) {
+        let Some(network) = &self.network else {
+            return;
+        };
+        if self.accumulators.len() <= ply {
+            self.accumulators.resize_with(ply + 1, Accumulator::default);
+        }
+        let (previous, next) = self.accumulators.split_at_mut(ply);
+        network.update(&previous[ply - 1], &mut next[0], parent, child);
+    }
+    fn static_eval(&self, game: &Game, ply: usize) -> i32 {
+        match &self.network {
+            Some(network) => network.evaluate(&self.accumulators[ply], game.turn()),
+            None => match &self.params {
+                Some(params) => evaluate_with(game, params),
+                None => evaluate(game),
+            },
+        }
+    }
+    fn negamax(
+        &mut self,
+        game: &Game,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        previous: Option<Move>,
+    ) -> i32 {
+        // below the root an endgame table gives the exact result, so there is nothing to search
+        if ply > 0 {
+            if let Some(result) = self
+                .tablebases
+                .as_ref()
+                .and_then(|tables| tables.probe(game))
+            {
+                self.stats.tablebase_hits += 1;
+                return match result {
+                    EndgameResult::Win(plies) => MATE_SCORE - ply as i32 - plies as i32,
+                    EndgameResult::Loss(plies) => -MATE_SCORE + ply as i32 + plies as i32,
+                    EndgameResult::Draw => 0,
+                };
+            }
+        }
+        if depth == 0 {
+            return self.quiescence(game, ply, alpha, beta);
+        }
+        self.stats.nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let hash = game.hash();
+        let mut hash_move = None;
+        if let Some(entry) = self.table.probe(hash) {
+            self.stats.table_hits += 1;
+            hash_move = entry.best_move;
+            if ply > 0 && entry.depth >= depth {
+                let score = score_from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut moves = game.legal_moves();
+        if moves.is_empty() {
+            return if game.in_check() {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+        if ply == 0 {
+            moves.retain(|mv| !self.excluded.contains(mv));
+        }
+        self.orderer
+            .order_moves(game, &mut moves, hash_move, ply, previous);
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for (index, mv) in moves.iter().enumerate() {
+            let mut child = *game;
+            child.make_legal_move(*mv);
+            self.enter(game, &child, ply + 1);
+            let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha, Some(*mv));
+            if self.aborted {
+                return 0;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(*mv);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                self.stats.beta_cutoffs += 1;
+                if index == 0 {
+                    self.stats.first_move_cutoffs += 1;
+                }
+                self.orderer.record_cutoff(game, *mv, ply, depth, previous);
+                break;
+            }
+        }
+
+        if ply == 0 {
+            self.root_best = best_move;
+            // the root entry must keep the real best move, not the best of the remaining ones
+            if !self.excluded.is_empty() {
+                return best_score;
+            }
+        }
+        let bound = if best_score <= original_alpha {
+            Bound::Upper
+        } else if best_score >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(TableEntry {
+            hash,
+            depth,
+            score: score_to_table(best_score, ply),
+            bound,
+            best_move,
+        });
+        best_score
+    }
+    fn quiescence(&mut self, game: &Game, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.stats.nodes += 1;
+        self.stats.quiescence_nodes += 1;
+        if self.should_stop() {
+            return 0;
+        }
+
+        let stand_pat = self.static_eval(game, ply);
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = game
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| game.is_capture(*mv) || mv.promotion.is_some())
+            .collect();
+        self.orderer.order_moves(game, &mut moves, None, 0, None);
+
+        for mv in moves {
+            let mut child = *game;
+            child.make_legal_move(mv);
+            self.enter(game, &child, ply + 1);
+            let score = -self.quiescence(&child, ply + 1, -beta, -alpha);
+            if self.aborted {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+    // follows best moves through the table, stopping on a miss or a repeated position
+    fn principal_variation(&self, game: &Game, max_length: u32) -> Vec<Move> {
+        let mut pv = vec![];
+        let mut seen = vec![];
+        let mut position = *game;
+        while pv.len() < max_length as usize {
+            let hash = position.hash();
+            if seen.contains(&hash) {
+                break;
+            }
+            seen.push(hash);
+            let Some(mv) = self.table.probe(hash).and_then(|entry| entry.best_move) else {
+                break;
+            };
+            if position.make_move(mv).is_err() {
+                break;
+            }
+            pv.push(mv);
+        }
+        pv
+    }
+}
+
+// Lazy SMP: every thread runs its own iterative deepening over the shared table, so helpers
+// fill it with results the main thread then hits; with one thread it is a plain search
+pub struct Searcher {
+    main: SearchWorker,
+    threads: usize,
+}
+
+impl Searcher {
+    pub fn new(config: OrderingConfig) -> Searcher {
+        Searcher {
+            main: SearchWorker::new(
+                config,
+                Arc::new(TranspositionTable::new()),
+                None,
+                None,
+                None,
+                Arc::new(AtomicBool::new(false)),
+                Arc::new(AtomicBool::new(false)),
+            ),
+            threads: 1,
+        }
+    }
+    // clears everything learned from earlier searches, e.g. for a new game
+    pub fn clear(&mut self) {
+        self.main.orderer = MoveOrderer::new(self.main.orderer.config());
+        self.main.table.clear();
+    }
+    pub fn set_tablebases(&mut self, tablebases: Option<Arc<EndgameTables>>) {
+        self.main.tablebases = tablebases;
+    }
+    // evaluate with this network instead of the handcrafted evaluation
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.main.network = network;
+    }
+    // evaluate with these weights instead of the installed ones
+    pub fn set_eval_params(&mut self, params: Option<Arc<EvalParams>>) {
+        self.main.params = params;
+    }
+    // how many root moves each iteration ranks, for MultiPV analysis
+    pub fn set_multi_pv(&mut self, lines: usize) {
+        self.main.multi_pv = lines.max(1);
+    }
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.clamp(1, MAX_THREADS);
+    }
+    // raising this flag from another thread ends the search at the next check
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.main.stop.clone()
+    }
+    pub fn search(&mut self, game: &Game, max_depth: u32) -> SearchResult {
+        self.search_with(game, SearchLimits::depth(max_depth), |_| {})
+    }
+    // the main thread reports its iterations and decides the result; helpers only
+    // contribute through the table and their node counts
+    pub fn search_with(
+        &mut self,
+        game: &Game,
+        limits: SearchLimits,
+        report: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let start = Instant::now();
+        let finished = Arc::new(AtomicBool::new(false));
+        let config = self.main.orderer.config();
+        let table = self.main.table.clone();
+        let tablebases = self.main.tablebases.clone();
+        let network = self.main.network.clone();
+        let params = self.main.params.clone();
+        let stop = self.main.stop.clone();
+        let main = &mut self.main;
+
+        let (mut result, helpers) = thread::scope(|scope| {
+            let helpers: Vec<_> = (1..self.threads)
+                .map(|id| {
+                    let mut helper = SearchWorker::new(
+                        config,
+                        table.clone(),
+                        tablebases.clone(),
+                        network.clone(),
+                        params.clone(),
+                        stop.clone(),
+                        finished.clone(),
+                    );
+                    // half the helpers start one ply deeper so threads spread over depths
+                    let first_depth = 1 + (id % 2) as u32;
+                    scope.spawn(move || helper.iterate(game, limits, start, first_depth, |_| {}))
+                })
+                .collect();
+            let result = main.iterate(game, limits, start, 1, report);
+            finished.store(true, Ordering::Relaxed);
+            let helpers: Vec<SearchResult> = helpers
+                .into_iter()
+                .map(|helper| helper.join().unwrap())
+                .collect();
+            (result, helpers)
+        });
+
+        result.threads = std::iter::once(&result)
+            .chain(&helpers)
+            .map(|thread_result| ThreadStats {
+                nodes: thread_result.stats.nodes,
+                depth: thread_result.depth,
+                time: thread_result.time,
+            })
+            .collect();
+        for helper in helpers {
+            result.stats += helper.stats;
+        }
+        result.time = start.elapsed();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(fen: &str, depth: u32) -> SearchResult {
+        Searcher::new(OrderingConfig::all()).search(&Game::from_fen(fen).unwrap(), depth)
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(result.best_move, Some(Move::from_coords("a1a8").unwrap()));
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn wins_a_hanging_queen() {
+        let result = search("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+        assert_eq!(result.best_move, Some(Move::from_coords("d2d5").unwrap()));
+        assert!(result.score > 300);
+    }
+
+    #[test]
+    fn mate_scores_are_stored_relative_to_the_node() {
+        let mate = MATE_SCORE - 7;
+        assert_eq!(score_from_table(score_to_table(mate, 4), 4), mate);
+        assert_eq!(score_from_table(score_to_table(-mate, 4), 4), -mate);
+        // the same mate found from a node two plies further is two plies closer
+        assert_eq!(score_from_table(score_to_table(mate, 4), 6), mate - 2);
+        assert_eq!(score_to_table(120, 9), 120);
+    }
+
+    #[test]
+    fn time_allocation_keeps_a_reserve() {
+        let budget = allocate_time(Duration::from_secs(60), Duration::ZERO, None);
+        assert_eq!(budget, Duration::from_secs(2));
+        let budget = allocate_time(Duration::from_secs(60), Duration::ZERO, Some(1));
+        assert_eq!(budget, Duration::from_secs(30));
+        let budget = allocate_time(Duration::from_millis(4), Duration::ZERO, None);
+        assert_eq!(budget, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn one_thread_is_deterministic() {
+        let game = Game::from_fen(crate::bench::MIDDLEGAME_FEN).unwrap();
+        let default = Searcher::new(OrderingConfig::all()).search(&game, 3);
+        let mut searcher = Searcher::new(OrderingConfig::all());
+        searcher.set_threads(1);
+        let single = searcher.search(&game, 3);
+        // the table remembers the first search, so start the second one afresh
+        searcher.clear();
+        let again = searcher.search(&game, 3);
+        assert_eq!(single.threads.len(), 1);
+        for result in [&default, &again] {
+            assert_eq!(result.best_move, single.best_move);
+            assert_eq!(result.score, single.score);
+            assert_eq!(result.pv, single.pv);
+            assert_eq!(result.stats.nodes, single.stats.nodes);
+        }
+    }
+
+    #[test]
+    fn threads_agree_on_forced_lines() {
+        // a mate in one and a won queen have one clear answer at any thread count
+        for (fen, depth) in [
+            ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3),
+            ("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 3),
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            let single = Searcher::new(OrderingConfig::all()).search(&game, depth);
+            let mut searcher = Searcher::new(OrderingConfig::all());
+            searcher.set_threads(4);
+            let multi = searcher.search(&game, depth);
+            assert_eq!(multi.threads.len(), 4);
+            assert_eq!(multi.best_move, single.best_move, "{fen}");
+        }
+    }
+
+    #[test]
+    fn multi_threaded_search_plays_a_legal_move() {
+        let game = Game::from_fen(crate::bench::MIDDLEGAME_FEN).unwrap();
+        let mut searcher = Searcher::new(OrderingConfig::all());
+        searcher.set_threads(3);
+        let multi = searcher.search(&game, 3);
+        assert!(game.legal_moves().contains(&multi.best_move.unwrap()));
+        assert_eq!(multi.depth, 3);
+        assert_eq!(multi.threads.len(), 3);
+        assert!(multi
+            .threads
+            .iter()
+            .all(|thread| thread.nodes > 0 && thread.depth > 0));
+        assert_eq!(
+            multi.stats.nodes,
+            multi.threads.iter().map(|thread| thread.nodes).sum::<u64>()
+        );
+    }
+
+    #[test]
+    fn multi_pv_ranks_distinct_root_moves() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let single = Searcher::new(OrderingConfig::all()).search(&game, 3);
+        let mut searcher = Searcher::new(OrderingConfig::all());
+        searcher.set_multi_pv(3);
+        let result = searcher.search(&game, 3);
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.best_move, single.best_move);
+        assert_eq!(result.lines[0].score, single.score);
+        assert_eq!(result.lines[0].pv, result.pv);
+        let first_moves: Vec<Move> = result.lines.iter().map(|line| line.pv[0]).collect();
+        assert!(first_moves[1..].iter().all(|mv| *mv != first_moves[0]));
+        assert_ne!(first_moves[1], first_moves[2]);
+        assert!(result
+            .lines
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+    }
+
+    #[test]
+    fn multi_pv_stops_at_the_legal_moves() {
+        let game = Game::from_fen("k7/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(OrderingConfig::all());
+        searcher.set_multi_pv(10);
+        assert_eq!(searcher.search(&game, 2).lines.len(), 3);
+    }
+}
@@ -0,0 +1,193 @@
+use crate::moves::Move;
+use crate::PieceType::*;
+use crate::Square;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const TABLE_SIZE: usize = 1 << 18;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TableEntry {
+    pub hash: u64,
+    pub depth: u32,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+// entries are packed into one word as score:16 depth:8 bound:2 move:16
+fn pack_move(mv: Option<Move>) -> u64 {
+    let Some(mv) = mv else { return 0 };
+    let promotion = match mv.promotion {
+        None => 0,
+        Some(Queen) => 1,
+        Some(Rook) => 2,
+        Some(Bishop) => 3,
+        Some(_) => 4,
+    };
+    1 | (mv.from.index() as u64) << 1 | (mv.to.index() as u64) << 7 | promotion << 13
+}
+
+fn unpack_move(bits: u64) -> Option<Move> {
+    if bits & 1 == 0 {
+        return None;
+    }
+    let square = |index: u64| Square::new(index as usize / 8, index as usize % 8);
+    let from = square((bits >> 1) & 63);
+    let to = square((bits >> 7) & 63);
+    Some(match (bits >> 13) & 7 {
+        1 => Move::with_promotion(from, to, Queen),
+        2 => Move::with_promotion(from, to, Rook),
+        3 => Move::with_promotion(from, to, Bishop),
+        4 => Move::with_promotion(from, to, Knight),
+        _ => Move::new(from, to),
+    })
+}
+
+fn pack(entry: &TableEntry) -> u64 {
+    let bound = match entry.bound {
+        Bound::Exact => 0,
+        Bound::Lower => 1,
+        Bound::Upper => 2,
+    };
+    (entry.score as i16 as u16 as u64)
+        | (entry.depth.min(255) as u64) << 16
+        | bound << 24
+        | pack_move(entry.best_move) << 26
+}
+
+fn unpack(hash: u64, data: u64) -> TableEntry {
+    TableEntry {
+        hash,
+        score: data as u16 as i16 as i32,
+        depth: ((data >> 16) & 255) as u32,
+        bound: match (data >> 24) & 3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        },
+        best_move: unpack_move(data >> 26),
+    }
+}
+
+// shared between search threads without locks: each slot keeps `hash ^ data` next to
+// `data`, so an entry torn by two concurrent writers simply fails the hash check
+pub struct TranspositionTable {
+    entries: Vec<[AtomicU64; 2]>,
+}
+
+impl TranspositionTable {
+    pub fn new() -> TranspositionTable {
+        TranspositionTable {
+            entries: (0..TABLE_SIZE)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+        }
+    }
+    pub fn clear(&self) {
+        for slot in &self.entries {
+            slot[0].store(0, Ordering::Relaxed);
+            slot[1].store(0, Ordering::Relaxed);
+        }
+    }
+    pub fn probe(&self, hash: u64) -> Option<TableEntry> {
+        let slot = &self.entries[hash as usize % TABLE_SIZE];
+        let data = slot[1].load(Ordering::Relaxed);
+        let check = slot[0].load(Ordering::Relaxed);
+        // stored entries always have a depth, so an empty slot never has data
+        (data != 0 && check ^ data == hash).then(|| unpack(hash, data))
+    }
+    pub fn store(&self, entry: TableEntry) {
+        let slot = &self.entries[entry.hash as usize % TABLE_SIZE];
+        // depth-preferred replacement, but always let a new position in
+        if let Some(old) = self.probe(entry.hash) {
+            if old.depth > entry.depth {
+                return;
+            }
+        }
+        let data = pack(&entry);
+        slot[1].store(data, Ordering::Relaxed);
+        slot[0].store(entry.hash ^ data, Ordering::Relaxed);
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> TranspositionTable {
+        TranspositionTable::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(hash: u64, depth: u32, score: i32) -> TableEntry {
+        TableEntry {
+            hash,
+            depth,
+            score,
+            bound: Bound::Lower,
+            best_move: Some(Move::new(Square::new(1, 4), Square::new(3, 4))),
+        }
+    }
+
+    #[test]
+    fn entries_survive_packing() {
+        let promotion = Move::with_promotion(Square::new(6, 0), Square::new(7, 1), Knight);
+        for (score, bound, best_move) in [
+            (-29990, Bound::Upper, Some(promotion)),
+            (29990, Bound::Exact, None),
+            (
+                0,
+                Bound::Lower,
+                Some(Move::new(Square::new(7, 7), Square::new(0, 0))),
+            ),
+        ] {
+            let stored = TableEntry {
+                hash: 42,
+                depth: 12,
+                score,
+                bound,
+                best_move,
+            };
+            let loaded = unpack(42, pack(&stored));
+            assert_eq!(loaded.score, score);
+            assert_eq!(loaded.depth, 12);
+            assert_eq!(loaded.bound, bound);
+            assert_eq!(loaded.best_move, best_move);
+        }
+    }
+
+    #[test]
+    fn deeper_entries_are_kept() {
+        let table = TranspositionTable::new();
+        table.store(entry(7, 5, 100));
+        table.store(entry(7, 3, -100));
+        assert_eq!(table.probe(7).unwrap().score, 100);
+        table.store(entry(7, 6, -50));
+        assert_eq!(table.probe(7).unwrap().score, -50);
+        // a different position in the same slot always gets in
+        let other = 7 + TABLE_SIZE as u64;
+        table.store(entry(other, 1, 30));
+        assert_eq!(table.probe(other).unwrap().score, 30);
+        assert!(table.probe(7).is_none());
+    }
+
+    #[test]
+    fn torn_and_cleared_entries_miss() {
+        let table = TranspositionTable::new();
+        table.store(entry(9, 4, 20));
+        // half of another write lands in the slot
+        table.entries[9][1].store(pack(&entry(9, 8, 75)), Ordering::Relaxed);
+        assert!(table.probe(9).is_none());
+        table.store(entry(9, 4, 20));
+        table.clear();
+        assert!(table.probe(9).is_none());
+    }
+}
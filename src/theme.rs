@@ -0,0 +1,329 @@
+use crate::tournament::parse_settings;
+use crate::PieceType::*;
+use crate::Team::{self, *};
+use crate::{Piece, Square};
+use colored::control::SHOULD_COLORIZE;
+use std::sync::OnceLock;
+
+// the checkered board's squares as RGB
+const LIGHT_SQUARE: (u8, u8, u8) = (240, 217, 181);
+const DARK_SQUARE: (u8, u8, u8) = (181, 136, 99);
+
+// highlighted squares, the same on the console and the full-screen board, in order of
+// precedence when a square has several
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Mark {
+    Cursor,
+    Selected,
+    Capture,
+    Target,
+    Check,
+    LastMove,
+}
+
+impl Mark {
+    pub fn strongest(marks: impl IntoIterator<Item = Option<Mark>>) -> Option<Mark> {
+        marks.into_iter().flatten().min()
+    }
+    // the square's background as RGB
+    pub fn color(self) -> (u8, u8, u8) {
+        match self {
+            Mark::Cursor => (205, 170, 50),
+            Mark::Selected => (90, 160, 70),
+            Mark::Capture => (215, 120, 40),
+            Mark::Target => (120, 120, 120),
+            Mark::Check => (200, 40, 40),
+            Mark::LastMove => (70, 140, 170),
+        }
+    }
+    // what stands in for the background without color; the cursor is reversed instead
+    pub fn brackets(self) -> (char, char) {
+        match self {
+            Mark::Cursor | Mark::Target => (' ', ' '),
+            Mark::Selected => ('(', ')'),
+            Mark::Capture => ('*', '*'),
+            Mark::Check => ('!', '!'),
+            Mark::LastMove => ('[', ']'),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PieceSet {
+    Filled,
+    Outline,
+    // KQRBNP for White, kqrbnp for Black
+    Letters,
+}
+
+// how the board and the sides are shown
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    // White and Black rather than the game's own Blue and Red
+    pub classic_names: bool,
+    pub pieces: PieceSet,
+    // light and dark squares get backgrounds of their own
+    pub checkered: bool,
+    // no color anywhere, as NO_COLOR asks for
+    pub monochrome: bool,
+}
+
+const THEMES: [(&str, Theme); 4] = [
+    (
+        "default",
+        Theme {
+            classic_names: false,
+            pieces: PieceSet::Filled,
+            checkered: false,
+            monochrome: false,
+        },
+    ),
+    (
+        "classic",
+        Theme {
+            classic_names: true,
+            pieces: PieceSet::Outline,
+            checkered: true,
+            monochrome: false,
+        },
+    ),
+    (
+        "letters",
+        Theme {
+            classic_names: true,
+            pieces: PieceSet::Letters,
+            checkered: false,
+            monochrome: false,
+        },
+    ),
+    (
+        "mono",
+        Theme {
+            classic_names: true,
+            pieces: PieceSet::Filled,
+            checkered: false,
+            monochrome: true,
+        },
+    ),
+];
+
+fn parse_switch(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "yes" => Ok(true),
+        "off" | "no" => Ok(false),
+        _ => Err(format!("Error: {key} needs on or off")),
+    }
+}
+
+impl Theme {
+    // `<theme>[:<key>=<value>,...]`, e.g. `classic` or `default:pieces=letters,checkered=on`
+    pub fn parse(spec: &str) -> Result<Theme, String> {
+        let (name, settings) = spec.split_once(':').unwrap_or((spec, ""));
+        let mut theme = THEMES
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, theme)| *theme)
+            .ok_or_else(|| {
+                let names: Vec<&str> = THEMES.iter().map(|(key, _)| *key).collect();
+                format!(
+                    "Error: Unknown theme '{name}', choose from {}",
+                    names.join(", ")
+                )
+            })?;
+        for (key, value) in parse_settings(settings)? {
+            match key {
+                "names" => {
+                    theme.classic_names = match value {
+                        "classic" => true,
+                        "colors" => false,
+                        _ => return Err("Error: names needs classic or colors".to_string()),
+                    }
+                }
+                "pieces" => {
+                    theme.pieces = match value {
+                        "filled" => PieceSet::Filled,
+                        "outline" => PieceSet::Outline,
+                        "letters" => PieceSet::Letters,
+                        _ => {
+                            return Err("Error: pieces needs filled, outline or letters".to_string())
+                        }
+                    }
+                }
+                "checkered" => theme.checkered = parse_switch(key, value)?,
+                "monochrome" => theme.monochrome = parse_switch(key, value)?,
+                _ => return Err(format!("Error: Unknown theme setting '{key}'")),
+            }
+        }
+        Ok(theme)
+    }
+    pub fn team_name(&self, team: Team) -> &'static str {
+        match (team, self.classic_names) {
+            (White, false) => "Blue",
+            (Black, false) => "Red",
+            (White, true) => "White",
+            (Black, true) => "Black",
+        }
+    }
+    // whether the output gets colors at all, e.g. not when piped
+    pub fn colors(&self) -> bool {
+        !self.monochrome && SHOULD_COLORIZE.should_colorize()
+    }
+    // pieces are told apart by color only with the Blue and Red sides
+    pub fn team_colors(&self) -> bool {
+        !self.classic_names && self.colors()
+    }
+    // without team colors glyphs tell the sides apart: outline White, filled Black
+    pub fn glyph(&self, piece: Piece) -> &'static str {
+        let outline = match self.pieces {
+            PieceSet::Letters => {
+                return match (piece.team(), piece.piece_type()) {
+                    (White, King) => "K",
+                    (White, Queen) => "Q",
+                    (White, Rook) => "R",
+                    (White, Bishop) => "B",
+                    (White, Knight) => "N",
+                    (White, Pawn) => "P",
+                    (Black, King) => "k",
+                    (Black, Queen) => "q",
+                    (Black, Rook) => "r",
+                    (Black, Bishop) => "b",
+                    (Black, Knight) => "n",
+                    (Black, Pawn) => "p",
+                }
+            }
+            _ if !self.team_colors() => piece.team() == White,
+            pieces => pieces == PieceSet::Outline,
+        };
+        match (piece.piece_type(), outline) {
+            (King, false) => "♚",
+            (Queen, false) => "♛",
+            (Rook, false) => "♜",
+            (Bishop, false) => "♝",
+            (Knight, false) => "♞",
+            (Pawn, false) => "♟",
+            (King, true) => "♔",
+            (Queen, true) => "♕",
+            (Rook, true) => "♖",
+            (Bishop, true) => "♗",
+            (Knight, true) => "♘",
+            (Pawn, true) => "♙",
+        }
+    }
+    pub fn square_color(square: Square) -> (u8, u8, u8) {
+        if (square.rank() + square.file()) % 2 == 1 {
+            LIGHT_SQUARE
+        } else {
+            DARK_SQUARE
+        }
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+// installs the theme for the rest of the run; NO_COLOR makes any theme monochrome
+pub fn set_theme(mut theme: Theme) -> Result<(), String> {
+    theme.monochrome |= no_color();
+    THEME
+        .set(theme)
+        .map_err(|_| "Error: The theme is already set".to_string())
+}
+
+pub fn theme() -> &'static Theme {
+    THEME.get_or_init(|| Theme {
+        monochrome: no_color(),
+        ..THEMES[0].1
+    })
+}
+
+fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_themes_and_settings() {
+        let theme = Theme::parse("classic").unwrap();
+        assert!(theme.classic_names && theme.checkered && !theme.monochrome);
+        assert_eq!(theme.pieces, PieceSet::Outline);
+
+        let theme = Theme::parse("default:pieces=letters,checkered=on,names=classic").unwrap();
+        assert!(theme.classic_names && theme.checkered);
+        assert_eq!(theme.pieces, PieceSet::Letters);
+
+        assert!(Theme::parse("mono").unwrap().monochrome);
+        assert!(Theme::parse("default:monochrome=yes").unwrap().monochrome);
+        assert!(!Theme::parse("mono:monochrome=off").unwrap().monochrome);
+    }
+
+    #[test]
+    fn parse_rejects_bad_specs() {
+        assert_eq!(
+            Theme::parse("fancy").unwrap_err(),
+            "Error: Unknown theme 'fancy', choose from default, classic, letters, mono"
+        );
+        assert_eq!(
+            Theme::parse("default:checkered=maybe").unwrap_err(),
+            "Error: checkered needs on or off"
+        );
+        assert_eq!(
+            Theme::parse("default:pieces=wooden").unwrap_err(),
+            "Error: pieces needs filled, outline or letters"
+        );
+        assert_eq!(
+            Theme::parse("default:names=fancy").unwrap_err(),
+            "Error: names needs classic or colors"
+        );
+        assert_eq!(
+            Theme::parse("default:sound=on").unwrap_err(),
+            "Error: Unknown theme setting 'sound'"
+        );
+        assert!(Theme::parse("default:checkered").is_err());
+    }
+
+    #[test]
+    fn team_names() {
+        let default = Theme::parse("default").unwrap();
+        assert_eq!(default.team_name(White), "Blue");
+        assert_eq!(default.team_name(Black), "Red");
+        let classic = Theme::parse("classic").unwrap();
+        assert_eq!(classic.team_name(White), "White");
+        assert_eq!(classic.team_name(Black), "Black");
+    }
+
+    #[test]
+    fn glyphs_tell_the_sides_apart_without_team_colors() {
+        let letters = Theme::parse("letters").unwrap();
+        assert_eq!(letters.glyph(Piece::new(Knight, White)), "N");
+        assert_eq!(letters.glyph(Piece::new(Knight, Black)), "n");
+
+        let mono = Theme::parse("mono").unwrap();
+        assert!(!mono.colors() && !mono.team_colors());
+        assert_eq!(mono.glyph(Piece::new(Queen, White)), "♕");
+        assert_eq!(mono.glyph(Piece::new(Queen, Black)), "♛");
+    }
+
+    #[test]
+    fn square_colors_alternate() {
+        assert_eq!(Theme::square_color(Square::new(0, 0)), DARK_SQUARE);
+        assert_eq!(Theme::square_color(Square::new(0, 7)), LIGHT_SQUARE);
+        assert_eq!(Theme::square_color(Square::new(7, 7)), DARK_SQUARE);
+    }
+
+    #[test]
+    fn strongest_mark_wins() {
+        assert_eq!(Mark::strongest([None, None]), None);
+        assert_eq!(
+            Mark::strongest([None, Some(Mark::LastMove), Some(Mark::Capture)]),
+            Some(Mark::Capture)
+        );
+        assert_eq!(
+            Mark::strongest([Some(Mark::Check), Some(Mark::Selected)]),
+            Some(Mark::Selected)
+        );
+        assert_eq!(Mark::Capture.brackets(), ('*', '*'));
+        assert_eq!(Mark::LastMove.brackets(), ('[', ']'));
+    }
+}
@@ -0,0 +1,728 @@
+use crate::bench::BENCH_LINES;
+use crate::book::OpeningBook;
+use crate::external_engine::{EngineLimit, ExternalEngine};
+use crate::mcts::{Mcts, MctsConfig, Playout};
+use crate::moves::{Move, Outcome, PositionHistory};
+use crate::nnue::Network;
+use crate::ordering::OrderingConfig;
+use crate::pgn::{self, GameResult};
+use crate::random::Rng;
+use crate::retrograde::EndgameTables;
+use crate::search::Searcher;
+use crate::Game;
+use crate::Team::*;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_GAMES: u32 = 10;
+const DEFAULT_MOVETIME: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_PLIES: usize = 300;
+// a side is resigned for once it has seen itself this far behind for several moves running
+const RESIGN_SCORE: i32 = 1000;
+const RESIGN_MOVES: u32 = 3;
+// quiet positions late in the game are called drawn once both sides agree for a while
+const DRAW_SCORE: i32 = 10;
+const DRAW_PLIES: u32 = 8;
+const DRAW_MIN_PLY: usize = 80;
+const SPRT_ALPHA: f64 = 0.05;
+const SPRT_BETA: f64 = 0.05;
+// floor for a game's score variance, so a clean sweep still moves the LLR
+const SPRT_MIN_VARIANCE: f64 = 0.01;
+
+// a starting position: an optional FEN and the moves played from it
+struct Opening {
+    fen: Option<String>,
+    moves: Vec<Move>,
+}
+
+impl Opening {
+    fn from_line(line: &str) -> Result<Opening, String> {
+        if line.contains('/') {
+            // EPD lines carry operations after the fourth field, which are ignored
+            let fen = Game::from_fen(line)?.fen();
+            return Ok(Opening {
+                fen: Some(fen),
+                moves: vec![],
+            });
+        }
+        let mut game = Game::init();
+        let mut moves = vec![];
+        for coords in line.split_whitespace() {
+            let mv = game.find_move(coords)?;
+            game.make_legal_move(mv);
+            moves.push(mv);
+        }
+        Ok(Opening { fen: None, moves })
+    }
+    fn start_position(&self) -> Game {
+        match &self.fen {
+            Some(fen) => Game::from_fen(fen).unwrap(),
+            None => Game::init(),
+        }
+    }
+}
+
+// one line per opening, either a FEN/EPD or UCI moves from the start position
+fn load_openings(path: Option<&str>) -> Result<Vec<Opening>, String> {
+    let lines: Vec<String> = match path {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|err| format!("Error: Could not read {path}: {err}"))?
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect(),
+        None => BENCH_LINES.iter().map(|line| line.to_string()).collect(),
+    };
+    let openings = lines
+        .iter()
+        .map(|line| Opening::from_line(line).map_err(|err| format!("{err} in opening \"{line}\"")))
+        .collect::<Result<Vec<Opening>, String>>()?;
+    if openings.is_empty() {
+        return Err("Error: No openings to play".to_string());
+    }
+    Ok(openings)
+}
+
+// a side in the match: the built-in engine with its own settings, the MCTS bot, or a UCI
+// program
+pub enum Contestant {
+    Builtin {
+        name: String,
+        searcher: Box<Searcher>,
+        book: Option<OpeningBook>,
+    },
+    Mcts {
+        name: String,
+        mcts: Mcts,
+    },
+    Uci(ExternalEngine),
+}
+
+// `key=value` pairs separated by commas
+pub fn parse_settings(settings: &str) -> Result<Vec<(&str, &str)>, String> {
+    settings
+        .split(',')
+        .filter(|setting| !setting.is_empty())
+        .map(|setting| {
+            setting
+                .split_once('=')
+                .ok_or_else(|| format!("Error: Expected key=value, got '{setting}'"))
+        })
+        .collect()
+}
+
+pub fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Error: {key} needs a number"))
+}
+
+fn parse_ordering(value: &str) -> Result<OrderingConfig, String> {
+    match value {
+        "all" => return Ok(OrderingConfig::all()),
+        "none" => return Ok(OrderingConfig::none()),
+        _ => {}
+    }
+    let mut config = OrderingConfig::none();
+    for heuristic in value.split('+') {
+        match heuristic {
+            "hash" => config.hash_move = true,
+            "mvv-lva" => config.mvv_lva = true,
+            "killers" => config.killers = true,
+            "history" => config.history = true,
+            "counter-moves" => config.counter_moves = true,
+            other => return Err(format!("Error: Unknown ordering heuristic '{other}'")),
+        }
+    }
+    Ok(config)
+}
+
+impl Contestant {
+    // `uci:<command>`, `engine[:key=value,...]` with keys name, threads, ordering, book,
+    // tablebases and nnue (ordering is all, none or heuristics joined by '+'), or
+    // `mcts[:key=value,...]` with keys name, iterations, time (ms), playout (random or light),
+    // exploration and seed
+    pub fn from_spec(spec: &str) -> Result<Contestant, String> {
+        if let Some(command) = spec.strip_prefix("uci:") {
+            return ExternalEngine::spawn(command).map(Contestant::Uci);
+        }
+        let (kind, settings) = spec.split_once(':').unwrap_or((spec, ""));
+        let settings = parse_settings(settings)?;
+        let name = settings
+            .iter()
+            .find(|(key, _)| *key == "name")
+            .map_or(spec.to_string(), |(_, name)| name.to_string());
+        match kind {
+            "engine" => Contestant::engine_from_settings(name, &settings),
+            "mcts" => Contestant::mcts_from_settings(name, &settings),
+            _ => Err(format!("Error: Unknown player '{spec}'")),
+        }
+    }
+    fn engine_from_settings(name: String, settings: &[(&str, &str)]) -> Result<Contestant, String> {
+        let mut ordering = OrderingConfig::all();
+        let mut threads = 1;
+        let mut book = None;
+        let mut tablebases = None;
+        let mut network = None;
+        for (key, value) in settings {
+            match *key {
+                "name" => {}
+                "threads" => threads = parse_number(key, value)?,
+                "ordering" => ordering = parse_ordering(value)?,
+                "book" => book = Some(OpeningBook::open(value)?),
+                "tablebases" => tablebases = Some(Arc::new(EndgameTables::load(value)?)),
+                "nnue" => network = Some(Arc::new(Network::load(value)?)),
+                _ => return Err(format!("Error: Unknown engine setting '{key}'")),
+            }
+        }
+        let mut searcher = Searcher::new(ordering);
+        searcher.set_threads(threads);
+        searcher.set_tablebases(tablebases);
+        searcher.set_network(network);
+        Ok(Contestant::Builtin {
+            name,
+            searcher: Box::new(searcher),
+            book,
+        })
+    }
+    fn mcts_from_settings(name: String, settings: &[(&str, &str)]) -> Result<Contestant, String> {
+        let mut config = MctsConfig::default();
+        let mut rng = Rng::new();
+        for (key, value) in settings {
+            match *key {
+                "name" => {}
+                "iterations" => config.iterations = Some(parse_number(key, value)?),
+                "time" => config.time = Some(Duration::from_millis(parse_number(key, value)?)),
+                "playout" => {
+                    config.playout = match *value {
+                        "random" => Playout::Random,
+                        "light" => Playout::Light,
+                        _ => return Err(format!("Error: Unknown playout '{value}'")),
+                    }
+                }
+                "exploration" => config.exploration = parse_number(key, value)?,
+                "seed" => rng = Rng::with_seed(parse_number(key, value)?),
+                _ => return Err(format!("Error: Unknown mcts setting '{key}'")),
+            }
+        }
+        Ok(Contestant::Mcts {
+            name,
+            mcts: Mcts::new(config, rng),
+        })
+    }
+    pub fn name(&self) -> &str {
+        match self {
+            Contestant::Builtin { name, .. } | Contestant::Mcts { name, .. } => name,
+            Contestant::Uci(engine) => engine.name(),
+        }
+    }
+    pub fn new_game(&mut self, start_fen: Option<&str>) -> Result<(), String> {
+        match self {
+            Contestant::Builtin { searcher, .. } => {
+                searcher.clear();
+                Ok(())
+            }
+            Contestant::Mcts { .. } => Ok(()),
+            Contestant::Uci(engine) => engine.new_game(start_fen),
+        }
+    }
+    // the chosen move and, when the player reported one, its score for the side to move
+    pub fn play(
+        &mut self,
+        moves: &[Move],
+        game: &Game,
+        limit: EngineLimit,
+    ) -> Result<(Option<Move>, Option<i32>), String> {
+        match self {
+            Contestant::Builtin { searcher, book, .. } => {
+                if let Some(mv) = book.as_ref().and_then(|book| book.choose(game)) {
+                    return Ok((Some(mv), None));
+                }
+                let result = searcher.search_with(game, limit.search_limits(game.turn()), |_| {});
+                let mv = result
+                    .best_move
+                    .or_else(|| game.legal_moves().first().copied());
+                Ok((mv, Some(result.score)))
+            }
+            Contestant::Mcts { mcts, .. } => {
+                let result = mcts.search(game, limit.movetime(game.turn()));
+                Ok((result.best_move, Some(result.score())))
+            }
+            Contestant::Uci(engine) => {
+                let mv = engine.best_move(moves, game, limit)?;
+                Ok((mv, engine.last_score()))
+            }
+        }
+    }
+}
+
+struct PlayedGame {
+    start: Game,
+    moves: Vec<Move>,
+    result: GameResult,
+    termination: String,
+}
+
+struct MatchSettings {
+    games: u32,
+    limit: EngineLimit,
+    max_plies: usize,
+    pgn_path: Option<String>,
+    sprt: Option<(f64, f64)>,
+}
+
+fn play_game(
+    white: &mut Contestant,
+    black: &mut Contestant,
+    opening: &Opening,
+    settings: &MatchSettings,
+) -> Result<PlayedGame, String> {
+    let start = opening.start_position();
+    white.new_game(opening.fen.as_deref())?;
+    black.new_game(opening.fen.as_deref())?;
+
+    let mut game = start;
+    let mut moves = vec![];
+    let mut history = PositionHistory::new(&game);
+    for mv in &opening.moves {
+        history.make_move(&mut game, *mv);
+        moves.push(*mv);
+    }
+
+    let mut losing_moves = [0, 0];
+    let mut last_scores = [None, None];
+    let mut quiet_plies = 0;
+    let finish = |result: GameResult, termination: &str, moves: Vec<Move>| PlayedGame {
+        start,
+        moves,
+        result,
+        termination: termination.to_string(),
+    };
+    let win_for = |team| {
+        if team == White {
+            GameResult::WhiteWins
+        } else {
+            GameResult::BlackWins
+        }
+    };
+    let loss_for = |team| {
+        if team == White {
+            GameResult::BlackWins
+        } else {
+            GameResult::WhiteWins
+        }
+    };
+    loop {
+        match game.outcome() {
+            Some(Outcome::Checkmate { winner }) => {
+                return Ok(finish(win_for(winner), "checkmate", moves))
+            }
+            Some(Outcome::Stalemate) => return Ok(finish(GameResult::Draw, "stalemate", moves)),
+            None => {}
+        }
+        if game.insufficient_material() {
+            return Ok(finish(GameResult::Draw, "insufficient material", moves));
+        }
+        if history.threefold() {
+            return Ok(finish(GameResult::Draw, "threefold repetition", moves));
+        }
+        if history.fifty_moves() {
+            return Ok(finish(GameResult::Draw, "fifty-move rule", moves));
+        }
+        if moves.len() >= settings.max_plies {
+            return Ok(finish(
+                GameResult::Draw,
+                "adjudicated: maximum length",
+                moves,
+            ));
+        }
+
+        let mover = game.turn();
+        let side = if mover == White { 0 } else { 1 };
+        let player = if mover == White {
+            &mut *white
+        } else {
+            &mut *black
+        };
+        let (mv, score) = match player.play(&moves, &game, settings.limit) {
+            Ok((Some(mv), score)) => (mv, score),
+            Ok((None, _)) => return Ok(finish(loss_for(mover), "forfeit: no move", moves)),
+            Err(err) => {
+                return Ok(finish(
+                    loss_for(mover),
+                    &format!("forfeit: {}", err.trim_start_matches("Error: ")),
+                    moves,
+                ))
+            }
+        };
+        history.make_move(&mut game, mv);
+        moves.push(mv);
+
+        last_scores[side] = score;
+        losing_moves[side] = match score {
+            Some(score) if score <= -RESIGN_SCORE => losing_moves[side] + 1,
+            _ => 0,
+        };
+        let opponent_agrees = last_scores[1 - side].is_some_and(|score| score >= RESIGN_SCORE);
+        if losing_moves[side] >= RESIGN_MOVES && opponent_agrees {
+            return Ok(finish(loss_for(mover), "adjudicated: resignation", moves));
+        }
+        quiet_plies = match score {
+            Some(score) if score.abs() <= DRAW_SCORE => quiet_plies + 1,
+            _ => 0,
+        };
+        if quiet_plies >= DRAW_PLIES && moves.len() >= DRAW_MIN_PLY {
+            return Ok(finish(GameResult::Draw, "adjudicated: draw", moves));
+        }
+    }
+}
+
+// Elo difference for an expected score, e.g. 0.64 -> +100
+fn elo_from_score(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Tally {
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+    fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+    // variance of a single game's score
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        let games = self.games() as f64;
+        (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+    // the Elo estimate with a 95% error bar
+    fn elo(&self) -> String {
+        let score = self.score();
+        if score <= 0.0 || score >= 1.0 {
+            return format!("{}inf", if score <= 0.0 { "-" } else { "+" });
+        }
+        let deviation = (self.variance() / self.games() as f64).sqrt();
+        let bound = |score: f64| elo_from_score(score.clamp(1e-6, 1.0 - 1e-6));
+        let margin = (bound(score + 1.96 * deviation) - bound(score - 1.96 * deviation)) / 2.0;
+        // adding zero turns a -0.0 estimate into 0.0
+        format!("{:+.1} +/- {:.1}", elo_from_score(score) + 0.0, margin)
+    }
+    // log-likelihood ratio of the normal approximation to the GSPRT
+    fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        let variance = self.variance().max(SPRT_MIN_VARIANCE);
+        let (score0, score1) = (score_from_elo(elo0), score_from_elo(elo1));
+        self.games() as f64 * (score1 - score0) * (2.0 * self.score() - score0 - score1)
+            / (2.0 * variance)
+    }
+}
+
+fn sprt_bounds() -> (f64, f64) {
+    (
+        (SPRT_BETA / (1.0 - SPRT_ALPHA)).ln(),
+        ((1.0 - SPRT_BETA) / SPRT_ALPHA).ln(),
+    )
+}
+
+// today's date as PGN wants it, e.g. "2024.03.09"
+fn pgn_date() -> String {
+    let Ok(since_epoch) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+        return "????.??.??".to_string();
+    };
+    // civil-from-days, counting eras of 400 years from 0000-03-01
+    let days = (since_epoch.as_secs() / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}.{month:02}.{day:02}")
+}
+
+fn pgn_tags(
+    round: u32,
+    white: &str,
+    black: &str,
+    opening: &Opening,
+    played: &PlayedGame,
+) -> Vec<(String, String)> {
+    let mut tags = vec![
+        ("Event", "chess-cli-game match".to_string()),
+        ("Site", "local".to_string()),
+        ("Date", pgn_date()),
+        ("Round", round.to_string()),
+        ("White", white.to_string()),
+        ("Black", black.to_string()),
+        ("Result", played.result.token().to_string()),
+    ];
+    if let Some(fen) = &opening.fen {
+        tags.push(("SetUp", "1".to_string()));
+        tags.push(("FEN", fen.clone()));
+    }
+    tags.push(("PlyCount", played.moves.len().to_string()));
+    tags.push(("Termination", played.termination.clone()));
+    tags.into_iter()
+        .map(|(name, value)| (name.to_string(), value))
+        .collect()
+}
+
+fn parse_match_args(
+    args: &[String],
+) -> Result<(String, String, Option<String>, MatchSettings), String> {
+    let mut player_a = "engine".to_string();
+    let mut player_b = "engine".to_string();
+    let mut openings = None;
+    let mut settings = MatchSettings {
+        games: DEFAULT_GAMES,
+        limit: EngineLimit::MoveTime(DEFAULT_MOVETIME),
+        max_plies: DEFAULT_MAX_PLIES,
+        pgn_path: None,
+        sprt: None,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Error: {arg} needs a value"))
+        };
+        let number = |value: &String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("Error: {arg} needs a number"))
+        };
+        match arg.as_str() {
+            "--a" => player_a = value()?.clone(),
+            "--b" => player_b = value()?.clone(),
+            "--games" => settings.games = number(value()?)? as u32,
+            "--openings" => openings = Some(value()?.clone()),
+            "--movetime" => {
+                settings.limit = EngineLimit::MoveTime(Duration::from_millis(number(value()?)?))
+            }
+            "--depth" => settings.limit = EngineLimit::Depth(number(value()?)? as u32),
+            "--max-plies" => settings.max_plies = number(value()?)? as usize,
+            "--pgn" => settings.pgn_path = Some(value()?.clone()),
+            "--sprt" => {
+                let elo0 = value()?.parse::<f64>();
+                let elo1 = value()?.parse::<f64>();
+                match (elo0, elo1) {
+                    (Ok(elo0), Ok(elo1)) if elo0 < elo1 => settings.sprt = Some((elo0, elo1)),
+                    _ => {
+                        return Err("Error: --sprt needs <elo0> <elo1> with elo0 < elo1".to_string())
+                    }
+                }
+            }
+            _ => return Err(format!("Unknown option: {arg}")),
+        }
+    }
+    if settings.games == 0 {
+        return Err("Error: --games must be at least 1".to_string());
+    }
+    Ok((player_a, player_b, openings, settings))
+}
+
+// `--match --a <player> --b <player> [--games n] [--openings file] [--movetime ms | --depth n]
+//  [--max-plies n] [--pgn file] [--sprt elo0 elo1]`; scores are from player A's side
+pub fn run_match(args: &[String]) -> Result<(), String> {
+    let (spec_a, spec_b, openings_path, settings) = parse_match_args(args)?;
+    let openings = load_openings(openings_path.as_deref())?;
+    let mut player_a = Contestant::from_spec(&spec_a)?;
+    let mut player_b = Contestant::from_spec(&spec_b)?;
+    if let Some(path) = &settings.pgn_path {
+        fs::write(path, "").map_err(|err| format!("Error: Could not write {path}: {err}"))?;
+    }
+    println!(
+        "{} vs {}: {} games, {} openings",
+        player_a.name(),
+        player_b.name(),
+        settings.games,
+        openings.len()
+    );
+
+    let mut tally = Tally::default();
+    for round in 1..=settings.games {
+        // each opening is played twice so both players get both colours
+        let index = round as usize - 1;
+        let opening = &openings[index / 2 % openings.len()];
+        let a_is_white = index.is_multiple_of(2);
+        let (white, black) = if a_is_white {
+            (&mut player_a, &mut player_b)
+        } else {
+            (&mut player_b, &mut player_a)
+        };
+        let played = play_game(white, black, opening, &settings)?;
+        let tags = pgn_tags(round, white.name(), black.name(), opening, &played);
+
+        match (played.result, a_is_white) {
+            (GameResult::WhiteWins, true) | (GameResult::BlackWins, false) => tally.wins += 1,
+            (GameResult::WhiteWins, false) | (GameResult::BlackWins, true) => tally.losses += 1,
+            _ => tally.draws += 1,
+        }
+        println!(
+            "Game {round}/{}: {} - {} {} ({}), score {}-{}-{}",
+            settings.games,
+            white.name(),
+            black.name(),
+            played.result.token(),
+            played.termination,
+            tally.wins,
+            tally.losses,
+            tally.draws
+        );
+        if let Some(path) = &settings.pgn_path {
+            let text = pgn::format_pgn(&tags, &played.start, &played.moves, played.result);
+            OpenOptions::new()
+                .append(true)
+                .open(path)
+                .and_then(|mut file| file.write_all(text.as_bytes()))
+                .map_err(|err| format!("Error: Could not write {path}: {err}"))?;
+        }
+        if let Some((elo0, elo1)) = settings.sprt {
+            let (lower, upper) = sprt_bounds();
+            let llr = tally.llr(elo0, elo1);
+            if llr <= lower || llr >= upper {
+                break;
+            }
+        }
+    }
+
+    println!(
+        "\nScore of {} vs {}: {} - {} - {} [{:.3}] {}",
+        player_a.name(),
+        player_b.name(),
+        tally.wins,
+        tally.losses,
+        tally.draws,
+        tally.score(),
+        tally.games()
+    );
+    println!("Elo difference: {}", tally.elo());
+    if let Some((elo0, elo1)) = settings.sprt {
+        let (lower, upper) = sprt_bounds();
+        let llr = tally.llr(elo0, elo1);
+        let verdict = if llr >= upper {
+            "H1 accepted"
+        } else if llr <= lower {
+            "H0 accepted"
+        } else {
+            "inconclusive"
+        };
+        println!(
+            "SPRT: elo0={elo0} elo1={elo1} alpha={SPRT_ALPHA} beta={SPRT_BETA}: LLR {llr:.2} ({lower:.2}, {upper:.2}) {verdict}"
+        );
+    }
+    if let Some(path) = &settings.pgn_path {
+        println!("Games saved to {path}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(wins: u32, draws: u32, losses: u32) -> Tally {
+        Tally {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn converts_between_elo_and_score() {
+        assert_eq!(elo_from_score(0.5), 0.0);
+        assert!((score_from_elo(400.0) - 10.0 / 11.0).abs() < 1e-9);
+        for elo in [-300.0, -25.0, 0.0, 80.0, 500.0] {
+            assert!((elo_from_score(score_from_elo(elo)) - elo).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn reports_elo_with_an_error_bar() {
+        assert_eq!(tally(5, 0, 5).elo(), "+0.0 +/- 251.8");
+        assert!(tally(60, 20, 20).elo().starts_with("+147.2 +/- "));
+        assert_eq!(tally(3, 0, 0).elo(), "+inf");
+        assert_eq!(tally(0, 1, 0).variance(), 0.0);
+    }
+
+    #[test]
+    fn sprt_accepts_a_clean_sweep() {
+        let (lower, upper) = sprt_bounds();
+        assert!(tally(100, 0, 0).llr(0.0, 5.0) >= upper);
+        assert!(tally(0, 0, 100).llr(0.0, 5.0) <= lower);
+        // a single game is never enough
+        let llr = tally(1, 0, 0).llr(0.0, 5.0);
+        assert!(lower < llr && llr < upper);
+        assert_eq!(tally(0, 0, 0).llr(0.0, 5.0), 0.0);
+    }
+
+    #[test]
+    fn sprt_leans_towards_the_closer_hypothesis() {
+        assert!(tally(300, 400, 300).llr(0.0, 10.0) < 0.0);
+        assert!(tally(400, 400, 200).llr(0.0, 10.0) > 0.0);
+        assert!(sprt_bounds().0 < 0.0 && sprt_bounds().1 > 0.0);
+    }
+
+    #[test]
+    fn parses_match_options() {
+        let (a, b, openings, settings) = parse_match_args(&args(
+            "--a random --b engine:depth=2 --games 4 --depth 3 --openings book.epd --sprt -5 5",
+        ))
+        .unwrap();
+        assert_eq!((a.as_str(), b.as_str()), ("random", "engine:depth=2"));
+        assert_eq!(openings.as_deref(), Some("book.epd"));
+        assert_eq!(settings.games, 4);
+        assert!(matches!(settings.limit, EngineLimit::Depth(3)));
+        assert_eq!(settings.sprt, Some((-5.0, 5.0)));
+
+        let (_, _, _, settings) = parse_match_args(&[]).unwrap();
+        assert_eq!(settings.games, DEFAULT_GAMES);
+        assert!(matches!(
+            settings.limit,
+            EngineLimit::MoveTime(DEFAULT_MOVETIME)
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_match_options() {
+        for line in [
+            "--games 0",
+            "--games many",
+            "--sprt 5 0",
+            "--movetime",
+            "--colour white",
+        ] {
+            assert!(parse_match_args(&args(line)).is_err(), "{line}");
+        }
+    }
+}
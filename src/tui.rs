@@ -0,0 +1,765 @@
+use crate::clock::{format_time, Clock};
+use crate::moves::Move;
+use crate::player::{team_name, Action, Assistance, Player};
+use crate::record::GameRecord;
+use crate::theme::{self, Mark, Theme};
+use crate::view::{Perspective, View};
+use crate::PieceType::{self, *};
+use crate::Team::{self, *};
+use crate::{Game, Square};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::style::{Color, PrintStyledContent, StyledContent, Stylize};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::cell::RefCell;
+use std::io::{self, stdout, Write};
+use std::rc::Rc;
+use std::time::Duration;
+
+// the board takes the left of the screen, the panels start here
+const PANEL_COLUMN: u16 = 48;
+const MOVE_ROWS: usize = 8;
+const MESSAGE_ROWS: usize = 6;
+// where `board_lines` puts the squares: the first rank row sits under a blank line and the
+// top border, after the rank label; each square is a bar and four cells wide, two rows high
+const BOARD_TOP: u16 = 2;
+const BOARD_LEFT: u16 = 3;
+const SQUARE_WIDTH: u16 = 5;
+const SQUARE_HEIGHT: u16 = 2;
+// waiting for a key times out this often so a running clock keeps ticking on screen
+const TICK: Duration = Duration::from_millis(200);
+const HELP: &str =
+    "arrows/hjkl move  enter or click pick  drag move  esc cancel  ? hint  b book  e endgame  f flip  d draw  q resign";
+
+struct Message {
+    text: String,
+    warning: bool,
+}
+
+// everything on screen; drawn from scratch on every change
+struct Screen {
+    game: Game,
+    moves: Vec<Move>,
+    san_moves: Vec<String>,
+    clock: Option<Clock>,
+    names: [String; 2],
+    messages: Vec<Message>,
+    cursor: Square,
+    selected: Option<Square>,
+    perspective: Perspective,
+    // the square a mouse drag picked the piece up from
+    drag_from: Option<Square>,
+    prompt: Option<String>,
+    open: bool,
+}
+
+// the full-screen interface, shared by the game loop and the players sitting at it
+#[derive(Clone)]
+pub struct Tui {
+    screen: Rc<RefCell<Screen>>,
+}
+
+impl Screen {
+    fn bottom(&self) -> Team {
+        self.perspective.bottom(&self.game)
+    }
+    fn square_at(&self, row: usize, column: usize) -> Square {
+        match self.bottom() {
+            White => Square::new(7 - row, column),
+            Black => Square::new(row, 7 - column),
+        }
+    }
+    fn position_of(&self, square: Square) -> (usize, usize) {
+        match self.bottom() {
+            White => (7 - square.rank(), square.file()),
+            Black => (square.rank(), 7 - square.file()),
+        }
+    }
+    // the square drawn at a terminal cell, if any; the borders between squares are not part of one
+    fn square_under(&self, column: u16, row: u16) -> Option<Square> {
+        let column = column.checked_sub(BOARD_LEFT)?;
+        let row = row.checked_sub(BOARD_TOP)?;
+        if column % SQUARE_WIDTH == 0 || row % SQUARE_HEIGHT != 0 {
+            return None;
+        }
+        let (column, row) = (
+            (column / SQUARE_WIDTH) as usize,
+            (row / SQUARE_HEIGHT) as usize,
+        );
+        (column < 8 && row < 8).then(|| self.square_at(row, column))
+    }
+    fn targets(&self) -> Vec<Square> {
+        self.selected
+            .map_or(vec![], |square| self.game.get_legal_moves(square))
+    }
+    // the same marks as the console board
+    fn mark(&self, square: Square, targets: &[Square]) -> Option<Mark> {
+        let is_target = targets.contains(&square);
+        let is_capture = self
+            .selected
+            .is_some_and(|from| self.game.captured_piece(Move::new(from, square)).is_some());
+        Mark::strongest([
+            (square == self.cursor).then_some(Mark::Cursor),
+            (Some(square) == self.selected).then_some(Mark::Selected),
+            (is_target && is_capture).then_some(Mark::Capture),
+            is_target.then_some(Mark::Target),
+            (self.game.in_check() && square == self.game.find_king(self.game.turn()))
+                .then_some(Mark::Check),
+            self.moves
+                .last()
+                .is_some_and(|mv| mv.from == square || mv.to == square)
+                .then_some(Mark::LastMove),
+        ])
+    }
+    fn cell(&self, square: Square, targets: &[Square]) -> StyledContent<String> {
+        let theme = theme::theme();
+        let piece = self.game.get_piece(square);
+        let is_target = targets.contains(&square);
+        let glyph = match piece {
+            Some(piece) => theme.glyph(piece),
+            None if is_target => "·",
+            None => " ",
+        };
+        // brackets stand in for the backgrounds when there is no color
+        let mark = self.mark(square, targets);
+        if !theme.colors() {
+            let (open, close) = mark.map_or((' ', ' '), Mark::brackets);
+            let cell = format!("{open}{glyph}{close} ").stylize();
+            return if mark == Some(Mark::Cursor) {
+                cell.reverse()
+            } else {
+                cell
+            };
+        }
+        let mut cell = format!(" {glyph}  ").stylize();
+        if let Some(piece) = piece {
+            if mark == Some(Mark::Check) {
+                // a red king would vanish on the red of check
+                cell = cell.white().bold();
+            } else if theme.team_colors() {
+                cell = cell.with(match piece.team() {
+                    White => Color::Blue,
+                    Black => Color::Red,
+                });
+            } else if theme.checkered {
+                cell = cell.with(Color::Black);
+            }
+        }
+        let background = match mark {
+            Some(mark) => mark.color(),
+            None if theme.checkered => Theme::square_color(square),
+            None => return cell,
+        };
+        let (r, g, b) = background;
+        cell.on(Color::Rgb { r, g, b })
+    }
+    fn board_lines(&self) -> Vec<Vec<StyledContent<String>>> {
+        let border = "   +----+----+----+----+----+----+----+----+".to_string();
+        let targets = self.targets();
+        let mut lines = vec![vec![], vec![border.clone().stylize()]];
+        for row in 0..8 {
+            let rank_label = self.square_at(row, 0).rank() + 1;
+            let mut line = vec![format!("{rank_label}  ").stylize()];
+            for column in 0..8 {
+                line.push("|".to_string().stylize());
+                line.push(self.cell(self.square_at(row, column), &targets));
+            }
+            line.push("|".to_string().stylize());
+            lines.push(line);
+            lines.push(vec![border.clone().stylize()]);
+        }
+        let files: String = (0..8)
+            .map(|column| {
+                let file = self.square_at(0, column).file();
+                format!("  {}  ", (b'a' + file as u8) as char)
+            })
+            .collect();
+        lines.push(vec![format!("   {files}").stylize()]);
+        lines
+    }
+    fn player_label(&self, team: Team) -> String {
+        let name = &self.names[(team == Black) as usize];
+        if name == team_name(team) {
+            name.clone()
+        } else {
+            format!("{}: {name}", team_name(team))
+        }
+    }
+    fn panel_lines(&self) -> Vec<StyledContent<String>> {
+        let mut lines = vec![
+            String::new().stylize(),
+            tint(self.player_label(White), Color::Blue),
+            tint(self.player_label(Black), Color::Red),
+            String::new().stylize(),
+        ];
+        lines.push(match &self.prompt {
+            Some(prompt) => tint(prompt.clone(), Color::Yellow).bold(),
+            None if self.game.in_check() => tint(
+                format!("{} to move, in check!", team_name(self.game.turn())),
+                Color::Red,
+            )
+            .bold(),
+            None => format!("{} to move", team_name(self.game.turn())).bold(),
+        });
+        lines.push(match &self.clock {
+            Some(clock) => format!(
+                "{} {}   {} {}",
+                team_name(White),
+                format_time(clock.remaining(White)),
+                team_name(Black),
+                format_time(clock.remaining(Black))
+            )
+            .stylize(),
+            None => String::new().stylize(),
+        });
+        lines.push(String::new().stylize());
+
+        lines.push("Moves".to_string().underlined());
+        let pairs: Vec<String> = self
+            .san_moves
+            .chunks(2)
+            .enumerate()
+            .map(|(index, pair)| format!("{:>3}. {}", index + 1, pair.join(" ")))
+            .collect();
+        for row in 0..MOVE_ROWS {
+            let line = pairs.len().saturating_sub(MOVE_ROWS) + row;
+            lines.push(pairs.get(line).cloned().unwrap_or_default().stylize());
+        }
+        lines.push(String::new().stylize());
+
+        lines.push("Messages".to_string().underlined());
+        // long messages wrap to the width left beside the board, the newest rows stay in view
+        let (columns, _) = terminal::size().unwrap_or((120, 40));
+        let width = (columns.saturating_sub(PANEL_COLUMN) as usize).max(20);
+        let mut rows: Vec<StyledContent<String>> = vec![];
+        for message in &self.messages {
+            for text in wrap(&message.text, width) {
+                rows.push(tint(
+                    text,
+                    if message.warning {
+                        Color::Red
+                    } else {
+                        Color::Green
+                    },
+                ));
+            }
+        }
+        let first = rows.len().saturating_sub(MESSAGE_ROWS);
+        rows.resize(first + MESSAGE_ROWS, String::new().stylize());
+        lines.extend(rows.drain(first..));
+        lines
+    }
+    fn draw(&self) -> io::Result<()> {
+        if !self.open {
+            return Ok(());
+        }
+        let mut out = stdout();
+        let board = self.board_lines();
+        let panel = self.panel_lines();
+        let rows = board.len().max(panel.len());
+        for row in 0..rows {
+            queue!(out, MoveTo(0, row as u16), Clear(ClearType::CurrentLine))?;
+            for cell in board.get(row).into_iter().flatten() {
+                queue!(out, PrintStyledContent(cell.clone()))?;
+            }
+            if let Some(line) = panel.get(row) {
+                queue!(
+                    out,
+                    MoveTo(PANEL_COLUMN, row as u16),
+                    PrintStyledContent(line.clone())
+                )?;
+            }
+        }
+        queue!(
+            out,
+            MoveTo(0, rows as u16 + 1),
+            Clear(ClearType::FromCursorDown),
+            PrintStyledContent(HELP.dim())
+        )?;
+        out.flush()
+    }
+    fn move_cursor(&mut self, down: i32, right: i32) {
+        let (row, column) = self.position_of(self.cursor);
+        let row = (row as i32 + down).clamp(0, 7) as usize;
+        let column = (column as i32 + right).clamp(0, 7) as usize;
+        self.cursor = self.square_at(row, column);
+    }
+    fn is_own_piece(&self, square: Square) -> bool {
+        self.game
+            .get_piece(square)
+            .is_some_and(|piece| piece.team() == self.game.turn())
+    }
+    // enter on the cursor: picks up a piece, puts it down again, or moves it
+    fn pick(&mut self) -> Option<Move> {
+        let square = self.cursor;
+        match self.selected {
+            Some(from) if from == square => self.selected = None,
+            _ if self.is_own_piece(square) => self.selected = Some(square),
+            Some(from) => match self.game.try_move(Move::new(from, square)) {
+                Ok(mv) => {
+                    self.selected = None;
+                    return Some(mv);
+                }
+                Err(err) => self.warn(err),
+            },
+            None => self.warn("Pick one of your own pieces first".to_string()),
+        }
+        None
+    }
+    // a click picks like enter does; a drag carries the piece and drops it where the button is let go
+    fn mouse(&mut self, mouse: MouseEvent) -> Option<Move> {
+        let square = self.square_under(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.cursor = square?;
+                if self.is_own_piece(self.cursor) {
+                    self.selected = square;
+                    self.drag_from = square;
+                    None
+                } else {
+                    self.pick()
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.drag_from.is_some() => {
+                self.cursor = square?;
+                None
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                let from = self.drag_from.take()?;
+                // letting go where it was picked up leaves it selected for a second click
+                self.cursor = square.filter(|&square| square != from)?;
+                self.pick()
+            }
+            _ => None,
+        }
+    }
+    fn warn(&mut self, text: String) {
+        self.messages.push(Message {
+            text,
+            warning: true,
+        });
+    }
+    fn inform(&mut self, text: String) {
+        self.messages.push(Message {
+            text,
+            warning: false,
+        });
+    }
+}
+
+// panel text in a color, or plain with a monochrome theme
+fn tint(text: String, color: Color) -> StyledContent<String> {
+    if theme::theme().colors() {
+        text.with(color)
+    } else {
+        text.stylize()
+    }
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let line = lines.last_mut().unwrap();
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(word.to_string());
+        } else {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+    }
+    lines
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        if self.open {
+            let _ = execute!(stdout(), DisableMouseCapture, Show, LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+impl Tui {
+    pub fn new(perspective: Perspective) -> Tui {
+        Tui {
+            screen: Rc::new(RefCell::new(Screen {
+                game: Game::init(),
+                moves: vec![],
+                san_moves: vec![],
+                clock: None,
+                names: [team_name(White).to_string(), team_name(Black).to_string()],
+                messages: vec![],
+                cursor: Square::new(1, 4),
+                selected: None,
+                perspective,
+                drag_from: None,
+                prompt: None,
+                open: false,
+            })),
+        }
+    }
+    pub fn open(&self, names: [String; 2]) -> Result<(), String> {
+        let mut screen = self.screen.borrow_mut();
+        screen.names = names;
+        terminal::enable_raw_mode()
+            .and_then(|_| execute!(stdout(), EnterAlternateScreen, EnableMouseCapture, Hide))
+            .map_err(|err| format!("Error: Could not start the full-screen interface: {err}"))?;
+        screen.open = true;
+        let _ = screen.draw();
+        Ok(())
+    }
+    pub fn close(&self) {
+        let mut screen = self.screen.borrow_mut();
+        if screen.open {
+            let _ = execute!(stdout(), DisableMouseCapture, Show, LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+            screen.open = false;
+        }
+    }
+    fn draw(&self) {
+        let _ = self.screen.borrow().draw();
+    }
+    // waits a tick for a key press or mouse event, redrawing on the way
+    fn next_event(&self) -> Option<Event> {
+        self.draw();
+        if !event::poll(TICK).unwrap_or(false) {
+            return None;
+        }
+        match event::read() {
+            Ok(Event::Resize(..)) => {
+                let _ = execute!(stdout(), Clear(ClearType::All));
+                None
+            }
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Press => None,
+            event => event.ok(),
+        }
+    }
+    fn next_key(&self) -> Option<KeyEvent> {
+        match self.next_event()? {
+            Event::Key(key) => Some(key),
+            _ => None,
+        }
+    }
+    // shows `question` until one of `answers` is pressed
+    fn ask(&self, question: &str, answers: &[char]) -> char {
+        self.screen.borrow_mut().prompt = Some(question.to_string());
+        let answer = loop {
+            if let Some(KeyCode::Char(answer)) = self.next_key().map(|key| key.code) {
+                if answers.contains(&answer) {
+                    break answer;
+                }
+            }
+        };
+        self.screen.borrow_mut().prompt = None;
+        answer
+    }
+}
+
+impl View for Tui {
+    fn show_position(&mut self, game: &Game, record: &GameRecord, clock: Option<&Clock>) {
+        let mut screen = self.screen.borrow_mut();
+        screen.game = *game;
+        screen.clock = clock.copied();
+        screen.selected = None;
+        screen.drag_from = None;
+        if screen.moves != record.moves {
+            let mut position = Game::init();
+            screen.san_moves = record
+                .moves
+                .iter()
+                .map(|mv| {
+                    let san = position.san(*mv);
+                    position.make_legal_move(*mv);
+                    san
+                })
+                .collect();
+            screen.moves = record.moves.clone();
+        }
+        let _ = screen.draw();
+    }
+    fn message(&mut self, text: &str) {
+        self.screen.borrow_mut().inform(text.to_string());
+        self.draw();
+    }
+    fn warning(&mut self, text: &str) {
+        self.screen.borrow_mut().warn(text.to_string());
+        self.draw();
+    }
+    fn game_over(&mut self, game: &Game, record: &GameRecord, lines: &[String]) {
+        self.show_position(game, record, None);
+        for line in lines {
+            self.message(line);
+        }
+        self.screen.borrow_mut().prompt = Some("Game over, press any key".to_string());
+        while self.next_key().is_none() {}
+        self.close();
+    }
+}
+
+// a human at the full-screen interface, moving pieces with the cursor
+pub struct TuiPlayer {
+    team: Team,
+    tui: Tui,
+    assistance: Rc<Assistance>,
+}
+
+impl TuiPlayer {
+    pub fn new(team: Team, tui: Tui, assistance: Rc<Assistance>) -> TuiPlayer {
+        TuiPlayer {
+            team,
+            tui,
+            assistance,
+        }
+    }
+    fn report(&self, report: Result<String, String>) {
+        let mut screen = self.tui.screen.borrow_mut();
+        match report {
+            Ok(text) => screen.inform(text),
+            Err(err) => screen.warn(err),
+        }
+    }
+}
+
+impl Player for TuiPlayer {
+    fn name(&self) -> &str {
+        team_name(self.team)
+    }
+    fn interactive(&self) -> bool {
+        true
+    }
+    fn set_clock(&mut self, clock: &Clock) {
+        self.tui.screen.borrow_mut().clock = Some(*clock);
+    }
+    fn choose_move(&mut self, game: &Game, record: &mut GameRecord) -> Result<Action, String> {
+        loop {
+            // checked on every tick, so waiting out the clock loses too
+            let clock = self.tui.screen.borrow().clock;
+            if clock.is_some_and(|clock| clock.flag_fallen().is_some()) {
+                return Ok(Action::OutOfTime);
+            }
+            let key = match self.tui.next_event() {
+                Some(Event::Key(key)) => key,
+                Some(Event::Mouse(mouse)) => {
+                    if let Some(mv) = self.tui.screen.borrow_mut().mouse(mouse) {
+                        return Ok(Action::Move(mv));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let mut screen = self.tui.screen.borrow_mut();
+            match key.code {
+                KeyCode::Up | KeyCode::Char('k') => screen.move_cursor(-1, 0),
+                KeyCode::Down | KeyCode::Char('j') => screen.move_cursor(1, 0),
+                KeyCode::Left | KeyCode::Char('h') => screen.move_cursor(0, -1),
+                KeyCode::Right | KeyCode::Char('l') => screen.move_cursor(0, 1),
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    if let Some(mv) = screen.pick() {
+                        return Ok(Action::Move(mv));
+                    }
+                }
+                KeyCode::Char('f') => screen.perspective.flip(),
+                KeyCode::Esc => {
+                    screen.selected = None;
+                    screen.drag_from = None;
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(Action::Resign)
+                }
+                KeyCode::Char(command) => {
+                    drop(screen);
+                    match command {
+                        '?' => {
+                            let hint = self.assistance.hint(game);
+                            if hint.is_ok() {
+                                record.add_hint(self.team);
+                            }
+                            self.report(hint);
+                        }
+                        'b' => self.report(self.assistance.book_report(game)),
+                        'e' => self.report(self.assistance.endgame_report(game)),
+                        'd' => return Ok(Action::OfferDraw),
+                        'q' if self.tui.ask("Resign this game? (y/n)", &['y', 'n']) == 'y' => {
+                            return Ok(Action::Resign)
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    fn choose_promotion(&mut self, _game: &Game) -> PieceType {
+        match self.tui.ask(
+            "Promote to (q)ueen, (r)ook, (b)ishop or k(n)ight?",
+            &['q', 'r', 'b', 'n'],
+        ) {
+            'r' => Rook,
+            'b' => Bishop,
+            'n' => Knight,
+            _ => Queen,
+        }
+    }
+    fn accept_draw(&mut self, _game: &Game) -> bool {
+        self.tui
+            .ask("Your opponent offers a draw, accept? (y/n)", &['y', 'n'])
+            == 'y'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::Orientation;
+
+    fn screen(fen: &str, orientation: Orientation) -> Tui {
+        let tui = Tui::new(Perspective::new(orientation, [true, true]));
+        tui.screen.borrow_mut().game = Game::from_fen(fen).unwrap();
+        tui
+    }
+
+    fn pick_at(screen: &mut Screen, coords: &str) -> Option<Move> {
+        screen.cursor = Square::from_coords(coords).unwrap();
+        screen.pick()
+    }
+
+    #[test]
+    fn marks_en_passant_targets_as_captures() {
+        let tui = screen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", Orientation::White);
+        let mut screen = tui.screen.borrow_mut();
+        assert_eq!(pick_at(&mut screen, "e5"), None);
+        screen.cursor = Square::from_coords("a1").unwrap();
+        let targets = screen.targets();
+        let at = |coords| Square::from_coords(coords).unwrap();
+        assert_eq!(screen.mark(at("d6"), &targets), Some(Mark::Capture));
+        assert_eq!(screen.mark(at("e6"), &targets), Some(Mark::Target));
+        assert_eq!(screen.mark(at("e5"), &targets), Some(Mark::Selected));
+        assert_eq!(screen.mark(at("a1"), &targets), Some(Mark::Cursor));
+    }
+
+    #[test]
+    fn picks_up_and_moves_a_piece() {
+        let tui = screen(&Game::init().fen(), Orientation::White);
+        let mut screen = tui.screen.borrow_mut();
+        assert_eq!(pick_at(&mut screen, "e2"), None);
+        assert_eq!(screen.selected, Square::from_coords("e2").ok());
+        // another own piece is picked up instead
+        assert_eq!(pick_at(&mut screen, "d2"), None);
+        assert_eq!(screen.selected, Square::from_coords("d2").ok());
+        assert_eq!(pick_at(&mut screen, "d2"), None);
+        assert_eq!(screen.selected, None);
+        pick_at(&mut screen, "g1");
+        assert_eq!(pick_at(&mut screen, "f3"), Move::from_coords("g1f3").ok());
+        assert_eq!(screen.selected, None);
+        assert!(screen.messages.is_empty());
+    }
+
+    #[test]
+    fn illegal_picks_say_why() {
+        let tui = screen("4kr2/8/8/8/8/8/8/4K2R w K - 0 1", Orientation::White);
+        let mut screen = tui.screen.borrow_mut();
+        assert_eq!(pick_at(&mut screen, "e8"), None);
+        assert_eq!(screen.messages[0].text, "Pick one of your own pieces first");
+        pick_at(&mut screen, "e1");
+        assert_eq!(pick_at(&mut screen, "g1"), None);
+        assert_eq!(screen.messages[1].text, "You cannot castle through check");
+        assert!(screen.messages[1].warning);
+        // the piece stays in hand for another try
+        assert_eq!(pick_at(&mut screen, "d1"), Move::from_coords("e1d1").ok());
+    }
+
+    #[test]
+    fn the_cursor_follows_the_screen_and_stays_on_the_board() {
+        let tui = screen(&Game::init().fen(), Orientation::Black);
+        let mut screen = tui.screen.borrow_mut();
+        screen.cursor = Square::from_coords("e2").unwrap();
+        // with black at the bottom, up on the screen is down the ranks
+        screen.move_cursor(-1, 0);
+        assert_eq!(screen.cursor, Square::from_coords("e1").unwrap());
+        screen.move_cursor(-1, 0);
+        assert_eq!(screen.cursor, Square::from_coords("e1").unwrap());
+        screen.move_cursor(0, 5);
+        assert_eq!(screen.cursor, Square::from_coords("a1").unwrap());
+    }
+
+    // the terminal cell in the middle of the top row of `coords`, with white at the bottom
+    fn cell_of(coords: &str) -> (u16, u16) {
+        let square = Square::from_coords(coords).unwrap();
+        (
+            BOARD_LEFT + square.file() as u16 * SQUARE_WIDTH + 2,
+            BOARD_TOP + (7 - square.rank() as u16) * SQUARE_HEIGHT,
+        )
+    }
+
+    fn mouse(screen: &mut Screen, kind: MouseEventKind, coords: &str) -> Option<Move> {
+        let (column, row) = cell_of(coords);
+        screen.mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    #[test]
+    fn finds_the_square_under_the_mouse() {
+        let tui = screen(&Game::init().fen(), Orientation::White);
+        let screen = tui.screen.borrow();
+        let (column, row) = cell_of("e2");
+        assert_eq!(
+            screen.square_under(column, row),
+            Square::from_coords("e2").ok()
+        );
+        assert_eq!(
+            screen.square_under(BOARD_LEFT + 1, BOARD_TOP),
+            Square::from_coords("a8").ok()
+        );
+        // borders, the row under each square and anything off the board
+        assert_eq!(screen.square_under(BOARD_LEFT, BOARD_TOP), None);
+        assert_eq!(screen.square_under(column, row + 1), None);
+        assert_eq!(
+            screen.square_under(BOARD_LEFT + 8 * SQUARE_WIDTH + 1, row),
+            None
+        );
+        assert_eq!(screen.square_under(0, 0), None);
+        drop(screen);
+        tui.screen.borrow_mut().perspective.flip();
+        assert_eq!(
+            tui.screen.borrow().square_under(BOARD_LEFT + 1, BOARD_TOP),
+            Square::from_coords("h1").ok()
+        );
+    }
+
+    #[test]
+    fn clicks_and_drags_move_pieces() {
+        let left = MouseButton::Left;
+        let tui = screen(&Game::init().fen(), Orientation::White);
+        let mut screen = tui.screen.borrow_mut();
+        assert_eq!(mouse(&mut screen, MouseEventKind::Down(left), "e2"), None);
+        assert_eq!(mouse(&mut screen, MouseEventKind::Up(left), "e2"), None);
+        assert_eq!(screen.selected, Square::from_coords("e2").ok());
+        assert_eq!(
+            mouse(&mut screen, MouseEventKind::Down(left), "e4"),
+            Move::from_coords("e2e4").ok()
+        );
+
+        assert_eq!(mouse(&mut screen, MouseEventKind::Down(left), "g1"), None);
+        assert_eq!(mouse(&mut screen, MouseEventKind::Drag(left), "g2"), None);
+        assert_eq!(screen.cursor, Square::from_coords("g2").unwrap());
+        assert_eq!(
+            mouse(&mut screen, MouseEventKind::Up(left), "f3"),
+            Move::from_coords("g1f3").ok()
+        );
+        // dropped on an illegal square it stays in hand with the reason shown
+        mouse(&mut screen, MouseEventKind::Down(left), "b1");
+        assert_eq!(mouse(&mut screen, MouseEventKind::Up(left), "b4"), None);
+        assert_eq!(screen.selected, Square::from_coords("b1").ok());
+        assert!(screen.messages.last().unwrap().warning);
+    }
+}
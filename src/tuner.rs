@@ -0,0 +1,355 @@
+use crate::eval::{eval_terms, EvalParams, PARAM_COUNT};
+use crate::pgn::{self, GameResult};
+use crate::Game;
+use std::fs;
+use std::time::Instant;
+
+const DEFAULT_EPOCHS: u32 = 300;
+// PGN games give one position per ply; the opening plies mostly come from books
+const SKIP_OPENING_PLIES: usize = 8;
+const LEARNING_RATE: f64 = 1.0;
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+
+// a position reduced to its evaluation features, with the result from white's side
+struct Sample {
+    features: Vec<(u16, i16)>,
+    result: f64,
+}
+
+impl Sample {
+    fn new(game: &Game, result: f64) -> Sample {
+        let mut features: Vec<(u16, i16)> = vec![];
+        eval_terms(game, |index, count| {
+            match features
+                .iter_mut()
+                .find(|(feature, _)| *feature as usize == index)
+            {
+                Some((_, total)) => *total += count as i16,
+                None => features.push((index as u16, count as i16)),
+            }
+        });
+        features.retain(|(_, count)| *count != 0);
+        Sample { features, result }
+    }
+    fn score(&self, weights: &[f64]) -> f64 {
+        self.features
+            .iter()
+            .map(|(index, count)| weights[*index as usize] * *count as f64)
+            .sum()
+    }
+}
+
+// the expected score for a white-relative evaluation in centipawns
+fn sigmoid(score: f64, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+fn mean_error(samples: &[Sample], weights: &[f64], k: f64) -> f64 {
+    samples
+        .iter()
+        .map(|sample| (sample.result - sigmoid(sample.score(weights), k)).powi(2))
+        .sum::<f64>()
+        / samples.len() as f64
+}
+
+// numbers only count in brackets, so a FEN's move counters are not read as a result
+fn parse_result(token: &str) -> Option<f64> {
+    let token = token.trim_matches(|c| matches!(c, '"' | ';'));
+    let bracketed = token
+        .strip_prefix('[')
+        .and_then(|token| token.strip_suffix(']'));
+    match (bracketed.unwrap_or(token), bracketed.is_some()) {
+        ("1-0", _) | ("1.0" | "1", true) => Some(1.0),
+        ("0-1", _) | ("0.0" | "0", true) => Some(0.0),
+        ("1/2-1/2", _) | ("0.5", true) => Some(0.5),
+        _ => None,
+    }
+}
+
+// one position per line: a FEN (or its first four fields) followed by the game result, as
+// `1-0`, `0-1` or `1/2-1/2`, optionally written as `c9 "1-0";` or as `[1.0]`, `[0.5]`, `[0.0]`
+fn samples_from_epd(text: &str) -> Result<Vec<Sample>, String> {
+    let mut samples = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let result = fields
+            .iter()
+            .skip(4)
+            .rev()
+            .find_map(|field| parse_result(field))
+            .ok_or_else(|| format!("Error: No result on line {}", number + 1))?;
+        let game = Game::from_fen(&fields[..4.min(fields.len())].join(" "))
+            .map_err(|err| format!("{err} on line {}", number + 1))?;
+        samples.push(Sample::new(&game, result));
+    }
+    Ok(samples)
+}
+
+// every position after the opening of each finished game, skipping positions in check
+fn samples_from_pgn(text: &str) -> Vec<Sample> {
+    let mut samples = vec![];
+    for game in pgn::parse_pgn(text) {
+        let result = match game.result {
+            GameResult::WhiteWins => 1.0,
+            GameResult::BlackWins => 0.0,
+            GameResult::Draw => 0.5,
+            GameResult::Unknown => continue,
+        };
+        let (Ok(mut position), Ok(moves)) = (game.start_position(), game.moves()) else {
+            continue;
+        };
+        for (ply, mv) in moves.into_iter().enumerate() {
+            if ply >= SKIP_OPENING_PLIES && !position.in_check() {
+                samples.push(Sample::new(&position, result));
+            }
+            position.make_legal_move(mv);
+        }
+    }
+    samples
+}
+
+// the scaling constant that best fits the starting weights, found by narrowing a grid
+fn find_k(samples: &[Sample], weights: &[f64]) -> f64 {
+    let (mut low, mut high) = (0.0, 3.0);
+    let mut best = 1.0;
+    for _ in 0..4 {
+        let step = (high - low) / 10.0;
+        best = (0..=10)
+            .map(|index| low + step * index as f64)
+            .filter(|k| *k > 0.0)
+            .min_by(|a, b| {
+                mean_error(samples, weights, *a).total_cmp(&mean_error(samples, weights, *b))
+            })
+            .unwrap();
+        (low, high) = (best - step, best + step);
+    }
+    best
+}
+
+// mean squared error gradient with respect to each weight
+fn gradient(samples: &[Sample], weights: &[f64], k: f64) -> Vec<f64> {
+    let mut gradient = vec![0.0; PARAM_COUNT];
+    let scale = std::f64::consts::LN_10 * k / 400.0;
+    for sample in samples {
+        let predicted = sigmoid(sample.score(weights), k);
+        let slope = 2.0 * (predicted - sample.result) * predicted * (1.0 - predicted) * scale;
+        for (index, count) in &sample.features {
+            gradient[*index as usize] += slope * *count as f64;
+        }
+    }
+    for value in &mut gradient {
+        *value /= samples.len() as f64;
+    }
+    gradient
+}
+
+// `--tune <positions> <params.out> [--epochs n] [--start params]`; positions ending in .pgn
+// are read as games, anything else as one labelled FEN per line
+pub fn run_tune(args: &[String]) -> Result<(), String> {
+    let usage = "Error: Usage: --tune <positions> <params.out> [--epochs n] [--start params]";
+    let (Some(positions_path), Some(output_path)) = (args.first(), args.get(1)) else {
+        return Err(usage.to_string());
+    };
+    let mut epochs = DEFAULT_EPOCHS;
+    let mut start = EvalParams::default();
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        let value = options
+            .next()
+            .ok_or_else(|| format!("Error: {option} needs a value"))?;
+        match option.as_str() {
+            "--epochs" => {
+                epochs = value
+                    .parse()
+                    .map_err(|_| "Error: --epochs needs a number".to_string())?
+            }
+            "--start" => start = EvalParams::load(value)?,
+            _ => return Err(format!("Unknown option: {option}")),
+        }
+    }
+
+    let text = fs::read_to_string(positions_path)
+        .map_err(|err| format!("Error: Could not read {positions_path}: {err}"))?;
+    let samples = if positions_path.ends_with(".pgn") {
+        samples_from_pgn(&text)
+    } else {
+        samples_from_epd(&text)?
+    };
+    if samples.is_empty() {
+        return Err(format!("Error: No positions found in {positions_path}"));
+    }
+    println!("Loaded {} positions", samples.len());
+
+    let mut weights: Vec<f64> = start.weights.iter().map(|weight| *weight as f64).collect();
+    let k = find_k(&samples, &weights);
+    let initial_error = mean_error(&samples, &weights, k);
+    println!("K = {k:.3}, starting error {initial_error:.6}");
+
+    // Adam keeps a running mean and variance of each weight's gradient
+    let start_time = Instant::now();
+    let mut mean = vec![0.0; PARAM_COUNT];
+    let mut variance = vec![0.0; PARAM_COUNT];
+    for epoch in 1..=epochs {
+        let gradient = gradient(&samples, &weights, k);
+        for index in 0..PARAM_COUNT {
+            mean[index] = ADAM_BETA1 * mean[index] + (1.0 - ADAM_BETA1) * gradient[index];
+            variance[index] =
+                ADAM_BETA2 * variance[index] + (1.0 - ADAM_BETA2) * gradient[index].powi(2);
+            let mean_hat = mean[index] / (1.0 - ADAM_BETA1.powi(epoch as i32));
+            let variance_hat = variance[index] / (1.0 - ADAM_BETA2.powi(epoch as i32));
+            weights[index] -= LEARNING_RATE * mean_hat / (variance_hat.sqrt() + 1e-12);
+        }
+        if epoch % 25 == 0 || epoch == epochs {
+            println!(
+                "epoch {epoch}: error {:.6} ({:.1}s)",
+                mean_error(&samples, &weights, k),
+                start_time.elapsed().as_secs_f64()
+            );
+        }
+    }
+
+    let mut tuned = EvalParams::default();
+    for (weight, value) in tuned.weights.iter_mut().zip(&weights) {
+        *weight = value.round() as i32;
+    }
+    tuned.save(output_path)?;
+    println!("Saved tuned weights to {output_path}, load them with --eval-params {output_path}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::evaluate_with;
+
+    const POSITIONS: &str = "\
+# a queen up wins, bare kings draw
+4k3/8/8/8/8/8/8/3QK3 w - - 1-0
+4k3/8/8/8/8/8/8/3QK3 b - - c9 \"1-0\";
+3qk3/8/8/8/8/8/8/4K3 w - - [0.0]
+4k3/8/8/8/8/8/8/4K3 w - - 0 1 1/2-1/2
+";
+
+    fn start_weights() -> Vec<f64> {
+        EvalParams::default()
+            .weights
+            .iter()
+            .map(|weight| *weight as f64)
+            .collect()
+    }
+
+    #[test]
+    fn reads_results_in_every_notation() {
+        for (token, result) in [
+            ("1-0", 1.0),
+            ("\"0-1\";", 0.0),
+            ("[0.5]", 0.5),
+            ("1/2-1/2", 0.5),
+        ] {
+            assert_eq!(parse_result(token), Some(result), "{token}");
+        }
+        assert_eq!(parse_result("[1]"), Some(1.0));
+        assert_eq!(parse_result("1"), None);
+        assert_eq!(parse_result("0.5"), None);
+        assert_eq!(parse_result("*"), None);
+        let results: Vec<f64> = samples_from_epd(POSITIONS)
+            .unwrap()
+            .iter()
+            .map(|sample| sample.result)
+            .collect();
+        assert_eq!(results, [1.0, 1.0, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn reports_the_bad_line() {
+        let err = samples_from_epd("4k3/8/8/8/8/8/8/4K3 w - -\n")
+            .err()
+            .unwrap();
+        assert_eq!(err, "Error: No result on line 1");
+        // move counters are not a result
+        let err = samples_from_epd("4k3/8/8/8/8/8/8/3QK3 w - - 0 1\n")
+            .err()
+            .unwrap();
+        assert_eq!(err, "Error: No result on line 1");
+        let err = samples_from_epd("\n4k3/8/8/8/8/8/8/4X3 w - - 1-0\n")
+            .err()
+            .unwrap();
+        assert!(err.ends_with("on line 2"), "{err}");
+    }
+
+    #[test]
+    fn features_reproduce_the_evaluation() {
+        let weights = start_weights();
+        let params = EvalParams::default();
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1",
+            "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R b KQ - 0 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            let white_score = if game.is_white_turn() {
+                evaluate_with(&game, &params)
+            } else {
+                -evaluate_with(&game, &params)
+            };
+            assert_eq!(Sample::new(&game, 0.5).score(&weights), white_score as f64);
+        }
+    }
+
+    #[test]
+    fn samples_games_after_the_opening() {
+        let pgn = "[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n\n\
+            [Result \"*\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O *\n\n\
+            [Result \"1-0\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 1-0\n";
+        let samples = samples_from_pgn(pgn);
+        assert_eq!(samples.len(), 2);
+        assert!(samples.iter().all(|sample| sample.result == 1.0));
+    }
+
+    #[test]
+    fn gradient_matches_the_error_slope() {
+        let samples = samples_from_epd(POSITIONS).unwrap();
+        let weights = start_weights();
+        let k = find_k(&samples, &weights);
+        assert!(k > 0.0);
+        let gradient = gradient(&samples, &weights, k);
+        let queen = crate::eval::MATERIAL + 4;
+        let mut nudged = weights.clone();
+        nudged[queen] += 1e-3;
+        let slope = (mean_error(&samples, &nudged, k) - mean_error(&samples, &weights, k)) / 1e-3;
+        assert!((slope - gradient[queen]).abs() < 1e-6 * (1.0 + slope.abs()));
+        // a step against the gradient lowers the error
+        let stepped: Vec<f64> = weights
+            .iter()
+            .zip(&gradient)
+            .map(|(weight, slope)| weight - 1e3 * slope)
+            .collect();
+        assert!(mean_error(&samples, &stepped, k) < mean_error(&samples, &weights, k));
+    }
+
+    #[test]
+    fn writes_weights_that_load_back() {
+        let directory = std::env::temp_dir();
+        let positions = directory.join(format!("tune-{}.epd", std::process::id()));
+        let output = directory.join(format!("tune-{}.txt", std::process::id()));
+        fs::write(&positions, POSITIONS).unwrap();
+        let args: Vec<String> = [
+            positions.to_str().unwrap(),
+            output.to_str().unwrap(),
+            "--epochs",
+            "5",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let tuned = run_tune(&args).and_then(|_| EvalParams::load(output.to_str().unwrap()));
+        fs::remove_file(&positions).unwrap();
+        let _ = fs::remove_file(&output);
+        assert!(tuned.is_ok());
+        assert!(run_tune(&args[..1]).is_err());
+    }
+}
@@ -0,0 +1,508 @@
+use crate::book::OpeningBook;
+use crate::eval::{MATE_SCORE, MATE_THRESHOLD};
+use crate::moves::Move;
+use crate::nnue::Network;
+use crate::ordering::OrderingConfig;
+use crate::retrograde::EndgameTables;
+use crate::search::{allocate_time, SearchLimits, SearchResult, Searcher, MAX_THREADS};
+use crate::Game;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const ENGINE_NAME: &str = "chess-cli-game";
+const ENGINE_AUTHOR: &str = "corbanpro";
+
+// clock values from `go`, in the side-relative form the time manager needs
+#[derive(Debug, Default)]
+struct GoCommand {
+    limits: SearchLimits,
+    time_left: Option<Duration>,
+    increment: Duration,
+    moves_to_go: Option<u32>,
+    infinite: bool,
+    // search the expected reply on the opponent's time until `ponderhit` or `stop`
+    ponder: bool,
+}
+
+fn parse_go(tokens: &[&str], game: &Game) -> GoCommand {
+    let mut go = GoCommand::default();
+    let mut tokens = tokens.iter();
+    let millis = |value: Option<&&str>| value.and_then(|value| value.parse::<u64>().ok());
+    while let Some(token) = tokens.next() {
+        match *token {
+            "depth" => go.limits.depth = tokens.next().and_then(|value| value.parse().ok()),
+            "nodes" => go.limits.nodes = tokens.next().and_then(|value| value.parse().ok()),
+            "movetime" => go.limits.movetime = millis(tokens.next()).map(Duration::from_millis),
+            "movestogo" => go.moves_to_go = tokens.next().and_then(|value| value.parse().ok()),
+            "infinite" => go.infinite = true,
+            "ponder" => go.ponder = true,
+            "wtime" | "btime" => {
+                let value = millis(tokens.next()).map(Duration::from_millis);
+                if (*token == "wtime") == game.is_white_turn() {
+                    go.time_left = value;
+                }
+            }
+            "winc" | "binc" => {
+                let value = millis(tokens.next()).map(Duration::from_millis);
+                if (*token == "winc") == game.is_white_turn() {
+                    go.increment = value.unwrap_or_default();
+                }
+            }
+            _ => {}
+        }
+    }
+    if go.limits.movetime.is_none() && !go.infinite {
+        if let Some(time_left) = go.time_left {
+            go.limits.movetime = Some(allocate_time(time_left, go.increment, go.moves_to_go));
+        }
+    }
+    go
+}
+
+// `position [startpos | fen <fen>] [moves <m1> <m2> ...]`
+pub fn parse_position(tokens: &[&str]) -> Result<Game, String> {
+    let moves_index = tokens.iter().position(|token| *token == "moves");
+    let setup = &tokens[..moves_index.unwrap_or(tokens.len())];
+    let mut game = match setup.first() {
+        Some(&"startpos") => Game::init(),
+        Some(&"fen") => Game::from_fen(&setup[1..].join(" "))?,
+        _ => return Err("Error: position needs startpos or fen".to_string()),
+    };
+    if let Some(moves_index) = moves_index {
+        for coords in &tokens[moves_index + 1..] {
+            let mv = game.find_move(coords)?;
+            game.make_move(mv)?;
+        }
+    }
+    Ok(game)
+}
+
+// `setoption name <name...> [value <value...>]`, names may contain spaces
+fn parse_setoption(tokens: &[&str]) -> (String, Option<String>) {
+    let value_index = tokens.iter().position(|token| *token == "value");
+    let name_tokens = &tokens[..value_index.unwrap_or(tokens.len())];
+    let name = name_tokens
+        .iter()
+        .skip_while(|token| **token == "name")
+        .copied()
+        .collect::<Vec<&str>>()
+        .join(" ");
+    let value = value_index.map(|index| tokens[index + 1..].join(" "));
+    (name, value)
+}
+
+pub fn format_score(score: i32) -> String {
+    if score > MATE_THRESHOLD {
+        format!("mate {}", (MATE_SCORE - score + 1) / 2)
+    } else if score < -MATE_THRESHOLD {
+        format!("mate -{}", (MATE_SCORE + score) / 2)
+    } else {
+        format!("cp {score}")
+    }
+}
+
+// one line per principal variation, numbered with `multipv` when there are several
+fn info_lines(result: &SearchResult) -> Vec<String> {
+    let line = |score: i32, pv: &[Move], multi_pv: Option<usize>| {
+        let pv: Vec<String> = pv.iter().map(|mv| mv.to_string()).collect();
+        format!(
+            "info depth {}{} score {} nodes {} nps {} tbhits {} time {} pv {}",
+            result.depth,
+            multi_pv.map_or(String::new(), |index| format!(" multipv {index}")),
+            format_score(score),
+            result.stats.nodes,
+            result.nodes_per_second(),
+            result.stats.tablebase_hits,
+            result.time.as_millis(),
+            pv.join(" ")
+        )
+    };
+    if result.lines.len() <= 1 {
+        return vec![line(result.score, &result.pv, None)];
+    }
+    result
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(index, pv_line)| line(pv_line.score, &pv_line.pv, Some(index + 1)))
+        .collect()
+}
+
+fn send(line: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{line}");
+    let _ = stdout.flush();
+}
+
+struct Engine {
+    game: Game,
+    searcher: Arc<Mutex<Searcher>>,
+    stop: Arc<AtomicBool>,
+    search_thread: Option<(JoinHandle<()>, bool)>,
+    book: Option<OpeningBook>,
+    // raised during `go ponder`; the search thread holds back its bestmove until it drops
+    pondering: Arc<AtomicBool>,
+    // the time the pondering search gets once the expected move is played
+    ponder_budget: Option<Duration>,
+    // counts searches so a ponderhit timer cannot stop a later search
+    search_id: Arc<AtomicU64>,
+}
+
+impl Engine {
+    fn new() -> Engine {
+        let searcher = Searcher::new(OrderingConfig::all());
+        // grabbed up front, since the search thread holds the lock while it runs
+        let stop = searcher.stop_flag();
+        Engine {
+            game: Game::init(),
+            searcher: Arc::new(Mutex::new(searcher)),
+            stop,
+            search_thread: None,
+            book: None,
+            pondering: Arc::new(AtomicBool::new(false)),
+            ponder_budget: None,
+            search_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+    fn start_search(&mut self, mut go: GoCommand) {
+        self.wait_for_search();
+        // a ponderhit timer may have fired after the last search had already finished
+        self.stop.store(false, Ordering::Relaxed);
+        self.search_id.fetch_add(1, Ordering::Relaxed);
+        self.pondering.store(go.ponder, Ordering::Relaxed);
+        self.ponder_budget = None;
+        if go.ponder {
+            self.ponder_budget = go.limits.movetime.take();
+        }
+        let game = self.game;
+        let searcher = self.searcher.clone();
+        let pondering = self.pondering.clone();
+        let stop = self.stop.clone();
+        let infinite = go.infinite || go.ponder;
+        let handle = thread::spawn(move || {
+            let mut searcher = searcher.lock().unwrap();
+            let result = searcher.search_with(&game, go.limits, |result| {
+                for line in info_lines(result) {
+                    send(&line);
+                }
+            });
+            while pondering.load(Ordering::Relaxed) && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            if result.threads.len() > 1 {
+                for (id, thread) in result.threads.iter().enumerate() {
+                    send(&format!(
+                        "info string thread {id} depth {} nodes {} time {}",
+                        thread.depth,
+                        thread.nodes,
+                        thread.time.as_millis()
+                    ));
+                }
+            }
+            // a search stopped during its first iteration still has to answer with a move
+            let best_move = result
+                .best_move
+                .or_else(|| game.legal_moves().first().copied());
+            match (best_move, result.pv.get(1)) {
+                (Some(mv), Some(reply)) if result.best_move == Some(mv) => {
+                    send(&format!("bestmove {mv} ponder {reply}"))
+                }
+                (Some(mv), _) => send(&format!("bestmove {mv}")),
+                (None, _) => send("bestmove 0000"),
+            }
+        });
+        self.search_thread = Some((handle, infinite));
+    }
+    // the expected move was played: the pondering search carries on as a normal timed search
+    fn ponder_hit(&mut self) {
+        if !self.pondering.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        if let Some((handle, _)) = self.search_thread.take() {
+            self.search_thread = Some((handle, self.ponder_budget.is_none()));
+        }
+        if let Some(budget) = self.ponder_budget.take() {
+            let search_id = self.search_id.clone();
+            let current = search_id.load(Ordering::Relaxed);
+            let stop = self.stop.clone();
+            thread::spawn(move || {
+                thread::sleep(budget);
+                if search_id.load(Ordering::Relaxed) == current {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+    }
+    fn stop_search(&mut self) {
+        if let Some((handle, _)) = self.search_thread.take() {
+            self.pondering.store(false, Ordering::Relaxed);
+            self.stop.store(true, Ordering::Relaxed);
+            let _ = handle.join();
+            self.stop.store(false, Ordering::Relaxed);
+        }
+    }
+    // lets a finite search run to completion, so piped scripts still get their bestmove
+    fn wait_for_search(&mut self) {
+        match self.search_thread.take() {
+            Some((handle, false)) => {
+                let _ = handle.join();
+            }
+            Some(search) => {
+                self.search_thread = Some(search);
+                self.stop_search();
+            }
+            None => {}
+        }
+    }
+    fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<(), String> {
+        match name.to_lowercase().as_str() {
+            "threads" => {
+                let threads = value
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| "Error: Threads needs a number".to_string())?;
+                self.searcher.lock().unwrap().set_threads(threads);
+            }
+            "tablebasepath" => {
+                let tablebases = match value.filter(|path| !path.is_empty() && *path != "<empty>") {
+                    Some(path) => Some(Arc::new(EndgameTables::load(path)?)),
+                    None => None,
+                };
+                self.searcher.lock().unwrap().set_tablebases(tablebases);
+            }
+            "evalfile" => {
+                let network = match value.filter(|path| !path.is_empty() && *path != "<empty>") {
+                    Some(path) => Some(Arc::new(Network::load(path)?)),
+                    None => None,
+                };
+                self.searcher.lock().unwrap().set_network(network);
+            }
+            "multipv" => {
+                let lines = value
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| "Error: MultiPV needs a number".to_string())?;
+                self.searcher.lock().unwrap().set_multi_pv(lines);
+            }
+            // the GUI only tells us whether it will send `go ponder`
+            "ponder" => {}
+            "bookfile" => {
+                self.book = match value.filter(|path| !path.is_empty() && *path != "<empty>") {
+                    Some(path) => Some(OpeningBook::open(path)?),
+                    None => None,
+                };
+            }
+            _ => return Err(format!("Error: Unknown option {name}")),
+        }
+        Ok(())
+    }
+    fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(command) = tokens.first() else {
+            return true;
+        };
+        match *command {
+            "uci" => {
+                send(&format!("id name {ENGINE_NAME}"));
+                send(&format!("id author {ENGINE_AUTHOR}"));
+                send(&format!(
+                    "option name Threads type spin default 1 min 1 max {MAX_THREADS}"
+                ));
+                send("option name MultiPV type spin default 1 min 1 max 256");
+                send("option name Ponder type check default false");
+                send("option name BookFile type string default <empty>");
+                send("option name TablebasePath type string default <empty>");
+                send("option name EvalFile type string default <empty>");
+                send("uciok");
+            }
+            "isready" => send("readyok"),
+            "ucinewgame" => {
+                self.wait_for_search();
+                self.searcher.lock().unwrap().clear();
+                self.game = Game::init();
+            }
+            "position" => {
+                self.wait_for_search();
+                match parse_position(&tokens[1..]) {
+                    Ok(game) => self.game = game,
+                    Err(err) => send(&format!("info string {err}")),
+                }
+            }
+            "setoption" => {
+                self.wait_for_search();
+                let (name, value) = parse_setoption(&tokens[1..]);
+                if let Err(err) = self.set_option(&name, value.as_deref()) {
+                    send(&format!("info string {err}"));
+                }
+            }
+            "go" => {
+                let go = parse_go(&tokens[1..], &self.game);
+                // book moves are played instantly, except when the GUI asked to analyze or ponder
+                let book_move = self
+                    .book
+                    .as_ref()
+                    .filter(|_| !go.infinite && !go.ponder)
+                    .and_then(|book| book.choose(&self.game));
+                match book_move {
+                    Some(mv) => {
+                        self.wait_for_search();
+                        send(&format!("info string book move {mv}"));
+                        send(&format!("bestmove {mv}"));
+                    }
+                    None => self.start_search(go),
+                }
+            }
+            "ponderhit" => self.ponder_hit(),
+            "stop" => self.stop_search(),
+            "quit" => {
+                self.stop_search();
+                return false;
+            }
+            _ => send(&format!("info string Unknown command: {line}")),
+        }
+        true
+    }
+}
+
+pub fn run_uci() {
+    let mut engine = Engine::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !engine.handle_command(line.trim()) {
+            return;
+        }
+    }
+    engine.wait_for_search();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(line: &str) -> Vec<&str> {
+        line.split_whitespace().collect()
+    }
+
+    #[test]
+    fn position_startpos_plays_the_moves() {
+        let game =
+            parse_position(&tokens("startpos moves e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 e1g1")).unwrap();
+        assert_eq!(
+            game.fen(),
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 0 1"
+        );
+        assert_eq!(
+            parse_position(&tokens("startpos")).unwrap().fen(),
+            Game::init().fen()
+        );
+    }
+
+    #[test]
+    fn position_fen_with_moves() {
+        let game = parse_position(&tokens(
+            "fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4 e8d7",
+        ))
+        .unwrap();
+        assert_eq!(game.fen(), "8/3k4/8/8/4P3/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn position_rejects_bad_input() {
+        assert!(parse_position(&tokens("")).is_err());
+        assert!(parse_position(&tokens("fen 8/8/8 w - - 0 1")).is_err());
+        assert!(parse_position(&tokens("startpos moves e2e5")).is_err());
+    }
+
+    #[test]
+    fn go_reads_fixed_limits() {
+        let game = Game::init();
+        let go = parse_go(&tokens("depth 6 nodes 5000"), &game);
+        assert_eq!(go.limits.depth, Some(6));
+        assert_eq!(go.limits.nodes, Some(5000));
+        assert_eq!(go.limits.movetime, None);
+        let go = parse_go(&tokens("movetime 250"), &game);
+        assert_eq!(go.limits.movetime, Some(Duration::from_millis(250)));
+        assert!(parse_go(&tokens("infinite"), &game).infinite);
+    }
+
+    #[test]
+    fn go_uses_the_clock_of_the_side_to_move() {
+        let line = tokens("wtime 60000 btime 2000 winc 1000 binc 0 movestogo 20");
+        let go = parse_go(&line, &Game::init());
+        assert_eq!(go.time_left, Some(Duration::from_secs(60)));
+        assert_eq!(go.increment, Duration::from_secs(1));
+        assert_eq!(go.moves_to_go, Some(20));
+        assert_eq!(
+            go.limits.movetime,
+            Some(allocate_time(
+                Duration::from_secs(60),
+                Duration::from_secs(1),
+                Some(20)
+            ))
+        );
+
+        let black = parse_position(&tokens("startpos moves e2e4")).unwrap();
+        let go = parse_go(&line, &black);
+        assert_eq!(go.time_left, Some(Duration::from_secs(2)));
+        assert_eq!(go.increment, Duration::ZERO);
+    }
+
+    #[test]
+    fn infinite_go_ignores_the_clock() {
+        let go = parse_go(&tokens("wtime 60000 btime 60000 infinite"), &Game::init());
+        assert_eq!(go.limits.movetime, None);
+    }
+
+    #[test]
+    fn setoption_names_may_contain_spaces() {
+        assert_eq!(
+            parse_setoption(&tokens("name Clear Hash")),
+            ("Clear Hash".to_string(), None)
+        );
+        assert_eq!(
+            parse_setoption(&tokens("name Threads value 4")),
+            ("Threads".to_string(), Some("4".to_string()))
+        );
+    }
+
+    #[test]
+    fn scores_are_reported_in_centipawns_or_moves_to_mate() {
+        assert_eq!(format_score(35), "cp 35");
+        assert_eq!(format_score(MATE_SCORE - 1), "mate 1");
+        assert_eq!(format_score(MATE_SCORE - 3), "mate 2");
+        assert_eq!(format_score(-(MATE_SCORE - 2)), "mate -1");
+    }
+
+    #[test]
+    fn go_ponder_keeps_the_clock_budget_for_later() {
+        let go = parse_go(&tokens("ponder wtime 60000 btime 60000"), &Game::init());
+        assert!(go.ponder);
+        assert!(!go.infinite);
+        // `start_search` moves this into the budget used after `ponderhit`
+        assert_eq!(
+            go.limits.movetime,
+            Some(allocate_time(Duration::from_secs(60), Duration::ZERO, None))
+        );
+    }
+
+    #[test]
+    fn info_lines_number_each_multi_pv_line() {
+        let game = Game::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(OrderingConfig::all());
+        let single = info_lines(&searcher.search(&game, 2));
+        assert_eq!(single.len(), 1);
+        assert!(
+            single[0].starts_with("info depth 2 score cp "),
+            "{}",
+            single[0]
+        );
+        assert!(single[0].contains(" pv d2d5 "), "{}", single[0]);
+
+        searcher.set_multi_pv(2);
+        let lines = info_lines(&searcher.search(&game, 2));
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("info depth 2 multipv 1 score "));
+        assert!(lines[1].starts_with("info depth 2 multipv 2 score "));
+    }
+}
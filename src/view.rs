@@ -0,0 +1,167 @@
+use crate::clock::Clock;
+use crate::record::GameRecord;
+use crate::Team::{self, *};
+use crate::{Game, Highlight};
+use colored::Colorize;
+use std::cell::Cell;
+use std::rc::Rc;
+
+// which side is drawn at the bottom of the board
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    White,
+    Black,
+    SideToMove,
+    // the human's side against the computer; two humans share the side to move, two bots
+    // are watched from White
+    Human,
+}
+
+pub fn parse_orientation(text: &str) -> Result<Orientation, String> {
+    match text {
+        "white" | "blue" => Ok(Orientation::White),
+        "black" | "red" => Ok(Orientation::Black),
+        "side" | "turn" => Ok(Orientation::SideToMove),
+        "human" => Ok(Orientation::Human),
+        _ => Err(format!("Error: Unknown orientation '{text}'")),
+    }
+}
+
+// the orientation with the players it follows, and whether `flip` turned it around
+#[derive(Debug, Clone, Copy)]
+pub struct Perspective {
+    orientation: Orientation,
+    humans: [bool; 2],
+    flipped: bool,
+}
+
+impl Perspective {
+    pub fn new(orientation: Orientation, humans: [bool; 2]) -> Perspective {
+        Perspective {
+            orientation,
+            humans,
+            flipped: false,
+        }
+    }
+    pub fn bottom(&self, game: &Game) -> Team {
+        let bottom = match (self.orientation, self.humans) {
+            (Orientation::White, _) => White,
+            (Orientation::Black, _) => Black,
+            (Orientation::Human, [true, false] | [false, false]) => White,
+            (Orientation::Human, [false, true]) => Black,
+            (Orientation::SideToMove, _) | (Orientation::Human, [true, true]) => game.turn(),
+        };
+        match (bottom, self.flipped) {
+            (White, true) => Black,
+            (Black, true) => White,
+            (bottom, false) => bottom,
+        }
+    }
+    pub fn flip(&mut self) {
+        self.flipped = !self.flipped;
+    }
+}
+
+// shared between the console view and the humans typing `flip` at it
+pub type SharedPerspective = Rc<Cell<Perspective>>;
+
+// where the game loop shows the game: the scrolling console or the full-screen interface
+pub trait View {
+    // the position before the side to move is asked for a move
+    fn show_position(&mut self, game: &Game, record: &GameRecord, clock: Option<&Clock>);
+    fn message(&mut self, text: &str);
+    fn warning(&mut self, text: &str);
+    // the final position and how the game ended
+    fn game_over(&mut self, game: &Game, record: &GameRecord, lines: &[String]);
+}
+
+// prints a fresh board every move, as the game always has
+pub struct ConsoleView {
+    pub perspective: SharedPerspective,
+}
+
+impl View for ConsoleView {
+    fn show_position(&mut self, game: &Game, record: &GameRecord, clock: Option<&Clock>) {
+        game.display_board(
+            clock,
+            &Highlight::position(game, record.moves.last().copied()),
+            self.perspective.get().bottom(game),
+        );
+    }
+    fn message(&mut self, text: &str) {
+        println!("{text}");
+    }
+    fn warning(&mut self, text: &str) {
+        println!("{}\n", text.red().bold());
+    }
+    fn game_over(&mut self, game: &Game, record: &GameRecord, lines: &[String]) {
+        game.display_board(
+            None,
+            &Highlight::position(game, record.moves.last().copied()),
+            self.perspective.get().bottom(game),
+        );
+        for line in lines {
+            println!("{line}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE_TO_MOVE: &str = "4k3/8/8/8/8/8/8/4K3 w - - 0 1";
+    const BLACK_TO_MOVE: &str = "4k3/8/8/8/8/8/8/4K3 b - - 0 1";
+
+    fn bottoms(orientation: Orientation, humans: [bool; 2]) -> [Team; 2] {
+        let perspective = Perspective::new(orientation, humans);
+        [WHITE_TO_MOVE, BLACK_TO_MOVE].map(|fen| perspective.bottom(&Game::from_fen(fen).unwrap()))
+    }
+
+    #[test]
+    fn parse_orientations() {
+        assert_eq!(parse_orientation("white"), Ok(Orientation::White));
+        assert_eq!(parse_orientation("blue"), Ok(Orientation::White));
+        assert_eq!(parse_orientation("black"), Ok(Orientation::Black));
+        assert_eq!(parse_orientation("red"), Ok(Orientation::Black));
+        assert_eq!(parse_orientation("side"), Ok(Orientation::SideToMove));
+        assert_eq!(parse_orientation("turn"), Ok(Orientation::SideToMove));
+        assert_eq!(parse_orientation("human"), Ok(Orientation::Human));
+        assert_eq!(
+            parse_orientation("sideways"),
+            Err("Error: Unknown orientation 'sideways'".to_string())
+        );
+    }
+
+    #[test]
+    fn fixed_and_side_to_move_orientations() {
+        assert_eq!(bottoms(Orientation::White, [false, true]), [White, White]);
+        assert_eq!(bottoms(Orientation::Black, [true, false]), [Black, Black]);
+        assert_eq!(
+            bottoms(Orientation::SideToMove, [true, false]),
+            [White, Black]
+        );
+    }
+
+    #[test]
+    fn human_orientation_follows_the_humans() {
+        assert_eq!(bottoms(Orientation::Human, [true, false]), [White, White]);
+        assert_eq!(bottoms(Orientation::Human, [false, true]), [Black, Black]);
+        assert_eq!(bottoms(Orientation::Human, [true, true]), [White, Black]);
+        assert_eq!(bottoms(Orientation::Human, [false, false]), [White, White]);
+    }
+
+    #[test]
+    fn flip_turns_any_orientation_around() {
+        let game = Game::from_fen(BLACK_TO_MOVE).unwrap();
+        let mut perspective = Perspective::new(Orientation::SideToMove, [true, true]);
+        perspective.flip();
+        assert_eq!(perspective.bottom(&game), White);
+        perspective.flip();
+        assert_eq!(perspective.bottom(&game), Black);
+
+        let mut perspective = Perspective::new(Orientation::Human, [false, true]);
+        perspective.flip();
+        assert_eq!(perspective.bottom(&game), White);
+    }
+}
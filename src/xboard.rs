@@ -0,0 +1,465 @@
+use crate::eval::{MATE_SCORE, MATE_THRESHOLD};
+use crate::moves::{Move, Outcome};
+use crate::ordering::OrderingConfig;
+use crate::search::{allocate_time, SearchLimits, SearchResult, Searcher};
+use crate::Team::*;
+use crate::{Game, Team};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const ENGINE_NAME: &str = "chess-cli-game";
+
+fn send(line: &str) {
+    let mut stdout = io::stdout().lock();
+    let _ = writeln!(stdout, "{line}");
+    let _ = stdout.flush();
+}
+
+// CECP reports mates as 100000 + moves to mate
+fn format_score(score: i32) -> i32 {
+    if score > MATE_THRESHOLD {
+        100000 + (MATE_SCORE - score + 1) / 2
+    } else if score < -MATE_THRESHOLD {
+        -100000 - (MATE_SCORE + score) / 2
+    } else {
+        score
+    }
+}
+
+fn thinking_line(result: &SearchResult) -> String {
+    let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_string()).collect();
+    format!(
+        "{} {} {} {} {}",
+        result.depth,
+        format_score(result.score),
+        result.time.as_millis() / 10,
+        result.stats.nodes,
+        pv.join(" ")
+    )
+}
+
+// `level` base time is either minutes or minutes:seconds
+fn parse_base_time(base: &str) -> Option<Duration> {
+    match base.split_once(':') {
+        Some((minutes, seconds)) => {
+            let minutes: u64 = minutes.parse().ok()?;
+            let seconds: u64 = seconds.parse().ok()?;
+            Some(Duration::from_secs(minutes * 60 + seconds))
+        }
+        None => Some(Duration::from_secs(base.parse::<u64>().ok()? * 60)),
+    }
+}
+
+fn centiseconds(value: Option<&&str>) -> Option<Duration> {
+    let centiseconds: u64 = value?.parse().ok()?;
+    Some(Duration::from_millis(centiseconds * 10))
+}
+
+// budget from our clock; when we are ahead of the opponent's clock (`otim`) half the lead
+// may be spent too, never more than half of what we have
+fn clock_budget(
+    time_left: Duration,
+    opponent_time: Option<Duration>,
+    increment: Duration,
+    moves_to_go: Option<u32>,
+) -> Duration {
+    let lead = opponent_time.map_or(Duration::ZERO, |opponent_time| {
+        time_left.saturating_sub(opponent_time) / 2
+    });
+    allocate_time(time_left + lead, increment, moves_to_go).min(time_left / 2)
+}
+
+// the engine's move being searched for, on its own thread so commands are still read
+struct SearchThread {
+    // the move and its score, or `None` when the search was cancelled before it answered
+    handle: JoinHandle<Option<(Move, i32)>>,
+    // held while the move is sent, so a cancel either comes first or sees the move played
+    cancelled: Arc<Mutex<bool>>,
+}
+
+struct XBoard {
+    game: Game,
+    history: Vec<Game>,
+    searcher: Arc<Mutex<Searcher>>,
+    stop: Arc<AtomicBool>,
+    search: Option<SearchThread>,
+    force: bool,
+    engine_team: Team,
+    post: bool,
+    moves_per_session: u32,
+    increment: Duration,
+    time_left: Option<Duration>,
+    opponent_time: Option<Duration>,
+    time_per_move: Option<Duration>,
+    depth_limit: Option<u32>,
+    engine_moves: u32,
+    // the engine's score for its last move, from its own side, to answer draw offers
+    last_score: Option<i32>,
+}
+
+impl XBoard {
+    fn new() -> XBoard {
+        let searcher = Searcher::new(OrderingConfig::all());
+        // grabbed up front, since the search thread holds the lock while it runs
+        let stop = searcher.stop_flag();
+        XBoard {
+            game: Game::init(),
+            history: vec![],
+            searcher: Arc::new(Mutex::new(searcher)),
+            stop,
+            search: None,
+            force: false,
+            engine_team: Black,
+            post: false,
+            moves_per_session: 0,
+            increment: Duration::ZERO,
+            time_left: None,
+            opponent_time: None,
+            time_per_move: None,
+            depth_limit: None,
+            engine_moves: 0,
+            last_score: None,
+        }
+    }
+    fn new_game(&mut self) {
+        self.game = Game::init();
+        self.history.clear();
+        self.searcher.lock().unwrap().clear();
+        self.force = false;
+        self.engine_team = Black;
+        self.depth_limit = None;
+        self.engine_moves = 0;
+        self.last_score = None;
+    }
+    fn search_limits(&self) -> SearchLimits {
+        let mut limits = SearchLimits {
+            depth: self.depth_limit,
+            ..SearchLimits::default()
+        };
+        if let Some(time_per_move) = self.time_per_move {
+            limits.movetime = Some(time_per_move);
+        } else if let Some(time_left) = self.time_left {
+            let moves_to_go = (self.moves_per_session > 0)
+                .then(|| self.moves_per_session - self.engine_moves % self.moves_per_session);
+            limits.movetime = Some(clock_budget(
+                time_left,
+                self.opponent_time,
+                self.increment,
+                moves_to_go,
+            ));
+        }
+        limits
+    }
+    fn play_move(&mut self, coords: &str) -> Result<(), String> {
+        let mv = self.game.find_move(coords)?;
+        self.history.push(self.game);
+        self.game.make_move(mv)
+    }
+    // announces the result if the last move ended the game; true when it did
+    fn report_outcome(&mut self) -> bool {
+        let Some(outcome) = self.game.outcome() else {
+            return false;
+        };
+        match outcome {
+            Outcome::Checkmate { winner: White } => send("1-0 {White mates}"),
+            Outcome::Checkmate { winner: Black } => send("0-1 {Black mates}"),
+            Outcome::Stalemate => send("1/2-1/2 {Stalemate}"),
+        }
+        self.force = true;
+        true
+    }
+    fn think(&mut self) {
+        if self.report_outcome() {
+            return;
+        }
+        let limits = self.search_limits();
+        let post = self.post;
+        let game = self.game;
+        let searcher = self.searcher.clone();
+        let cancelled = Arc::new(Mutex::new(false));
+        let cancel = cancelled.clone();
+        let handle = thread::spawn(move || {
+            let result = searcher
+                .lock()
+                .unwrap()
+                .search_with(&game, limits, |result| {
+                    if post {
+                        send(&thinking_line(result));
+                    }
+                });
+            // a search stopped during its first iteration still has to answer with a move
+            let mv = result
+                .best_move
+                .or_else(|| game.legal_moves().first().copied())?;
+            let cancelled = cancel.lock().unwrap();
+            if *cancelled {
+                return None;
+            }
+            send(&format!("move {mv}"));
+            Some((mv, result.score))
+        });
+        self.search = Some(SearchThread { handle, cancelled });
+    }
+    // waits for the running search and plays the move it sent
+    fn finish_search(&mut self) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+        let reply = search.handle.join().unwrap();
+        self.stop.store(false, Ordering::Relaxed);
+        if let Some((mv, score)) = reply {
+            self.history.push(self.game);
+            self.game.make_legal_move(mv);
+            self.engine_moves += 1;
+            self.last_score = Some(score);
+            self.report_outcome();
+        }
+    }
+    // `?`: play the best move found so far
+    fn move_now(&mut self) {
+        if self.search.is_some() {
+            self.stop.store(true, Ordering::Relaxed);
+            self.finish_search();
+        }
+    }
+    // drops the running search without a move, unless it was sent already
+    fn cancel_search(&mut self) {
+        if let Some(search) = &self.search {
+            *search.cancelled.lock().unwrap() = true;
+            self.stop.store(true, Ordering::Relaxed);
+            self.finish_search();
+        }
+    }
+    fn engine_to_move(&self) -> bool {
+        !self.force && self.game.turn() == self.engine_team
+    }
+    fn handle_command(&mut self, line: &str) -> bool {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(command) = tokens.first() else {
+            return true;
+        };
+        // a command that changes the game drops the search, most others wait for its move
+        match *command {
+            "new" | "force" | "setboard" | "undo" | "remove" | "result" | "quit" => {
+                self.cancel_search()
+            }
+            "?" => self.move_now(),
+            "post" | "nopost" | "time" | "otim" | "draw" | "easy" | "hard" | "computer"
+            | "name" | "rating" | "ics" | "accepted" | "rejected" => {}
+            _ => self.finish_search(),
+        }
+        match *command {
+            "xboard" | "accepted" | "rejected" | "hard" | "easy" | "random" | "computer"
+            | "name" | "rating" | "ics" | "?" => {}
+            "protover" => {
+                send(&format!(
+                    "feature myname=\"{ENGINE_NAME}\" usermove=1 setboard=1 ping=1 \
+                     playother=1 smp=1 colors=0 sigint=0 sigterm=0 san=0 analyze=0 done=1"
+                ));
+            }
+            "new" => self.new_game(),
+            "force" => self.force = true,
+            "go" => {
+                self.force = false;
+                self.engine_team = self.game.turn();
+                self.think();
+            }
+            "playother" => {
+                self.force = false;
+                self.engine_team = if self.game.is_white_turn() {
+                    Black
+                } else {
+                    White
+                };
+            }
+            "usermove" => {
+                let Some(coords) = tokens.get(1) else {
+                    send("Error (missing move): usermove");
+                    return true;
+                };
+                if self.play_move(coords).is_err() {
+                    send(&format!("Illegal move: {coords}"));
+                    return true;
+                }
+                if !self.report_outcome() && self.engine_to_move() {
+                    self.think();
+                }
+            }
+            "setboard" => match Game::from_fen(&tokens[1..].join(" ")) {
+                Ok(game) => {
+                    self.game = game;
+                    self.history.clear();
+                }
+                Err(err) => send(&format!("tellusererror Illegal position: {err}")),
+            },
+            "level" => {
+                self.moves_per_session =
+                    tokens.get(1).and_then(|mps| mps.parse().ok()).unwrap_or(0);
+                self.time_left = tokens.get(2).and_then(|base| parse_base_time(base));
+                self.increment = tokens
+                    .get(3)
+                    .and_then(|increment| increment.parse::<f64>().ok())
+                    .map(Duration::from_secs_f64)
+                    .unwrap_or_default();
+                self.time_per_move = None;
+            }
+            "st" => {
+                self.time_per_move = tokens
+                    .get(1)
+                    .and_then(|seconds| seconds.parse::<u64>().ok())
+                    .map(Duration::from_secs);
+            }
+            "cores" => {
+                if let Some(cores) = tokens.get(1).and_then(|cores| cores.parse().ok()) {
+                    self.searcher.lock().unwrap().set_threads(cores);
+                }
+            }
+            "sd" => self.depth_limit = tokens.get(1).and_then(|depth| depth.parse().ok()),
+            "time" => self.time_left = centiseconds(tokens.get(1)),
+            "otim" => self.opponent_time = centiseconds(tokens.get(1)),
+            // accepted when the engine does not think it is better
+            "draw" => {
+                if self.last_score.is_some_and(|score| score <= 0) {
+                    send("offer draw");
+                }
+            }
+            "undo" => {
+                if let Some(game) = self.history.pop() {
+                    self.game = game;
+                }
+            }
+            "remove" => {
+                for _ in 0..2 {
+                    if let Some(game) = self.history.pop() {
+                        self.game = game;
+                    }
+                }
+            }
+            "result" => self.force = true,
+            "ping" => send(&format!("pong {}", tokens.get(1).unwrap_or(&""))),
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "quit" => return false,
+            _ => send(&format!("Error (unknown command): {command}")),
+        }
+        true
+    }
+}
+
+pub fn run_xboard() {
+    let mut xboard = XBoard::new();
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if !xboard.handle_command(line.trim()) {
+            return;
+        }
+    }
+    xboard.finish_search();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn xboard(commands: &[&str]) -> XBoard {
+        let mut xboard = XBoard::new();
+        for command in commands {
+            assert!(xboard.handle_command(command));
+        }
+        xboard
+    }
+
+    #[test]
+    fn level_and_time_parsing() {
+        assert_eq!(parse_base_time("5"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_base_time("0:30"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_base_time("x"), None);
+        assert_eq!(
+            centiseconds(Some(&"1234")),
+            Some(Duration::from_millis(12340))
+        );
+
+        let xboard = xboard(&["level 40 5 2", "time 30000", "otim 30000"]);
+        assert_eq!(xboard.moves_per_session, 40);
+        assert_eq!(xboard.time_left, Some(Duration::from_secs(300)));
+        assert_eq!(xboard.opponent_time, Some(Duration::from_secs(300)));
+        assert_eq!(xboard.increment, Duration::from_secs(2));
+        assert_eq!(
+            xboard.search_limits().movetime,
+            Some(allocate_time(
+                Duration::from_secs(300),
+                Duration::from_secs(2),
+                Some(40)
+            ))
+        );
+    }
+
+    #[test]
+    fn a_lead_on_the_clock_buys_time() {
+        let even = clock_budget(
+            Duration::from_secs(60),
+            Some(Duration::from_secs(60)),
+            Duration::ZERO,
+            None,
+        );
+        let ahead = clock_budget(
+            Duration::from_secs(60),
+            Some(Duration::from_secs(20)),
+            Duration::ZERO,
+            None,
+        );
+        let behind = clock_budget(
+            Duration::from_secs(60),
+            Some(Duration::from_secs(90)),
+            Duration::ZERO,
+            None,
+        );
+        assert_eq!(
+            even,
+            allocate_time(Duration::from_secs(60), Duration::ZERO, None)
+        );
+        assert_eq!(
+            ahead,
+            allocate_time(Duration::from_secs(80), Duration::ZERO, None)
+        );
+        assert_eq!(behind, even);
+        let budget = clock_budget(
+            Duration::from_secs(10),
+            Some(Duration::ZERO),
+            Duration::ZERO,
+            Some(1),
+        );
+        assert_eq!(budget, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn engine_answers_a_user_move() {
+        let mut xboard = xboard(&["new", "sd 2", "usermove e2e4"]);
+        xboard.finish_search();
+        assert_eq!(xboard.history.len(), 2);
+        assert!(xboard.game.is_white_turn());
+        assert!(xboard.last_score.is_some());
+    }
+
+    #[test]
+    fn move_now_and_force_interrupt_the_search() {
+        let start = Instant::now();
+        let mut xboard = xboard(&["new", "st 60", "go"]);
+        thread::sleep(Duration::from_millis(50));
+        xboard.handle_command("?");
+        assert!(xboard.search.is_none());
+        assert!(xboard.game.is_black_turn());
+
+        xboard.handle_command("go");
+        thread::sleep(Duration::from_millis(50));
+        xboard.handle_command("force");
+        assert!(xboard.search.is_none());
+        assert!(xboard.game.is_black_turn());
+        assert!(xboard.force);
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::PieceType::*;
+use crate::Team::*;
+use crate::{Game, Piece};
+
+// 12 piece kinds x 64 squares, then side to move, castling flags and en passant files
+const PIECE_KEYS: usize = 12 * 64;
+const TURN_KEY: usize = PIECE_KEYS;
+const CASTLE_KEYS: usize = TURN_KEY + 1;
+const EN_PASSANT_KEYS: usize = CASTLE_KEYS + 6;
+const KEY_COUNT: usize = EN_PASSANT_KEYS + 8;
+
+static KEYS: [u64; KEY_COUNT] = generate_keys();
+
+const fn generate_keys() -> [u64; KEY_COUNT] {
+    // splitmix64, so the table is fixed across runs and platforms
+    let mut keys = [0; KEY_COUNT];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut index = 0;
+    while index < KEY_COUNT {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[index] = z ^ (z >> 31);
+        index += 1;
+    }
+    keys
+}
+
+fn piece_kind(piece: Piece) -> usize {
+    let type_index = match piece.piece_type() {
+        Pawn => 0,
+        Knight => 1,
+        Bishop => 2,
+        Rook => 3,
+        Queen => 4,
+        King => 5,
+    };
+    match piece.team() {
+        White => type_index,
+        Black => type_index + 6,
+    }
+}
+
+impl Game {
+    pub fn hash(&self) -> u64 {
+        let mut hash = 0;
+        for (rank_index, rank) in self.get_board().iter().enumerate() {
+            for (file_index, piece) in rank.iter().enumerate() {
+                if let Some(piece) = piece {
+                    hash ^= KEYS[piece_kind(*piece) * 64 + rank_index * 8 + file_index];
+                }
+            }
+        }
+        if self.is_black_turn() {
+            hash ^= KEYS[TURN_KEY];
+        }
+        let castle_flags = [
+            self.white_king_moved,
+            self.white_rook_a_moved,
+            self.white_rook_h_moved,
+            self.black_king_moved,
+            self.black_rook_a_moved,
+            self.black_rook_h_moved,
+        ];
+        for (index, moved) in castle_flags.iter().enumerate() {
+            if *moved {
+                hash ^= KEYS[CASTLE_KEYS + index];
+            }
+        }
+        if let Some(en_passant_pawn) = self.en_passant_pawn {
+            hash ^= KEYS[EN_PASSANT_KEYS + en_passant_pawn.file()];
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::moves::Move;
+    use crate::Game;
+
+    fn play(line: &str) -> Game {
+        let mut game = Game::init();
+        for coords in line.split_whitespace() {
+            game.make_move(Move::from_coords(coords).unwrap()).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn transpositions_share_a_hash() {
+        assert_eq!(
+            play("g1f3 g8f6 b1c3 b8c6").hash(),
+            play("b1c3 b8c6 g1f3 g8f6").hash()
+        );
+    }
+
+    #[test]
+    fn side_to_move_changes_the_hash() {
+        let white = Game::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Game::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(white.hash(), black.hash());
+    }
+
+    #[test]
+    fn castling_rights_and_en_passant_change_the_hash() {
+        let rights = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let no_rights = Game::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+        assert_ne!(rights.hash(), no_rights.hash());
+
+        let en_passant = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let no_en_passant = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_ne!(en_passant.hash(), no_en_passant.hash());
+    }
+
+    #[test]
+    fn hash_survives_a_fen_round_trip() {
+        let game = play("e2e4 c7c5 g1f3");
+        assert_eq!(Game::from_fen(&game.fen()).unwrap().hash(), game.hash());
+    }
+}
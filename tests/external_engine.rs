@@ -0,0 +1,89 @@
+// drives `ExternalEngine` end to end: a console game and `chess --match` between mock engines
+// scripted to play given moves, so the handshake, bestmove parsing and timeouts run against a
+// real subprocess
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn mock(args: &str) -> String {
+    format!("uci:{} {args}", env!("CARGO_BIN_EXE_mock_engine"))
+}
+
+fn run_match(white: &str, black: &str, movetime: u64) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .args(["--match", "--games", "1", "--movetime"])
+        .arg(movetime.to_string())
+        .args(["--a", white, "--b", black])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn play(engine: &str, input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .args(["--movetime", "50", "--engine"])
+        .arg(format!("{} {engine}", env!("CARGO_BIN_EXE_mock_engine")))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn plays_against_the_console() {
+    let stdout = play("e7e5 d8h4", "f2 f3\ng2 g4\n");
+    // the name comes from the engine's `id name` during the handshake
+    assert!(stdout.contains("Mock Engine plays e5"), "{stdout}");
+    assert!(stdout.contains("Mock Engine plays Qh4#"), "{stdout}");
+    assert!(stdout.contains("Checkmate!!"), "{stdout}");
+}
+
+#[test]
+fn plays_the_engines_moves_to_the_end() {
+    let pgn = std::env::temp_dir().join(format!("mock-match-{}.pgn", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .args(["--match", "--games", "1", "--movetime", "50", "--pgn"])
+        .arg(&pgn)
+        .args(["--a", &mock("f2f3 g2g4"), "--b", &mock("e7e5 d8h4")])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    // the names come from the engines' `id name` during the handshake
+    assert!(
+        stdout.contains("Game 1/1: Mock Engine - Mock Engine 0-1 (checkmate)"),
+        "{stdout}"
+    );
+    let text = std::fs::read_to_string(&pgn).unwrap();
+    std::fs::remove_file(&pgn).unwrap();
+    assert!(text.contains("1. f3 e5 2. g4 Qh4#"), "{text}");
+}
+
+#[test]
+fn illegal_and_missing_moves_forfeit() {
+    let stdout = run_match(&mock("e2e5"), &mock(""), 50);
+    assert!(
+        stdout.contains("0-1 (forfeit: Engine sent an illegal move: e2e5)"),
+        "{stdout}"
+    );
+    let stdout = run_match(&mock("e2e4"), &mock(""), 50);
+    assert!(stdout.contains("1-0 (forfeit: no move)"), "{stdout}");
+}
+
+#[test]
+fn an_engine_that_never_answers_times_out() {
+    // past its budget it is sent `stop`, then forfeits once the grace period runs out too
+    let stdout = run_match(&mock("--delay 10000 e2e4"), &mock("e7e5"), 10);
+    assert!(
+        stdout.contains("0-1 (forfeit: Engine timed out)"),
+        "{stdout}"
+    );
+}